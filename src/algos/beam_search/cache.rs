@@ -1,14 +1,15 @@
-use std::{
-    cmp::Ordering,
-    collections::{hash_map::Entry, BinaryHeap},
-};
+use std::{cmp::Ordering, collections::BinaryHeap};
 
 use fxhash::FxHashMap;
 use itertools::Itertools;
 
 use crate::{
-    cache::{EnumFormulaCache, EnumFormulaCacheLine, FormulaCache},
-    traits::Hashed,
+    cache::{
+        collisions::CacheKind,
+        enum_cache::{binary_child_lines, unary_child_lines},
+        find_slot, EnumFormulaCache, EnumFormulaCacheLine, FormulaCache,
+    },
+    traits::{Hashed, Rehash},
 };
 
 use crate::bool::{charac::BoolCharac, BoolFormula};
@@ -18,7 +19,7 @@ use crate::bool::{charac::BoolCharac, BoolFormula};
 /// Computes domination over the line size.
 #[derive(Debug)]
 pub struct BeamSearchCache {
-    entries: FxHashMap<<BoolCharac as Hashed>::HashType, BoolFormula>,
+    entries: FxHashMap<<BoolCharac as Hashed>::HashType, Slot>,
     lines: Vec<BinaryHeap<PcoBoolFormula>>,
     max_line_size: usize,
 }
@@ -39,23 +40,21 @@ impl FormulaCache<BoolCharac> for BeamSearchCache {
     }
 
     fn get(&self, hash: &<BoolCharac as Hashed>::HashType) -> Option<&BoolFormula> {
-        self.entries.get(hash)
+        match self.entries.get(hash)? {
+            Slot::Kept(f) => Some(f),
+            Slot::Dropped(_) => None,
+        }
     }
 }
 
 impl EnumFormulaCache<BoolCharac> for BeamSearchCache {
     type CacheLine<'a> = BeamSearchBoolCacheLine<'a>;
 
-    fn iter_size<'a>(&'a self, size: usize) -> impl Iterator<Item = &'a BoolFormula>
-    where
-        BoolCharac: 'a,
-    {
-        self.lines[size].iter().map(|pf| &pf.f)
-    }
-
     fn new_line_and_iter_size<'a>(
         &'a mut self,
         size: usize,
+        unary_costs: &[usize],
+        binary_costs: &[usize],
     ) -> (
         impl Iterator<Item = &'a BoolFormula>,
         impl Iterator<Item = (&'a BoolFormula, &'a BoolFormula)>,
@@ -66,6 +65,7 @@ impl EnumFormulaCache<BoolCharac> for BeamSearchCache {
     {
         self.lines.push(BinaryHeap::new());
         let (old_lines, new) = self.lines.split_at_mut(size);
+        let old_lines: &'a [_] = old_lines;
 
         let new_line = BeamSearchBoolCacheLine {
             line: &mut new[0],
@@ -73,15 +73,13 @@ impl EnumFormulaCache<BoolCharac> for BeamSearchCache {
             max_line_size: self.max_line_size,
         };
 
-        let iter_size = size - 1;
-        let iter_formulas = old_lines[iter_size].iter().map(|pf| &pf.f);
+        let iter_formulas = unary_child_lines(size, unary_costs)
+            .into_iter()
+            .flat_map(|i| old_lines[i].iter().map(|pf| &pf.f));
 
-        let iter_pairs_size = (size + 1) / 2;
-        let iter_pairs = old_lines
-            .iter()
-            .zip(old_lines.iter().rev())
-            .take(iter_pairs_size)
-            .flat_map(|(i1, i2)| i1.iter().cartesian_product(i2))
+        let iter_pairs = binary_child_lines(size, binary_costs)
+            .into_iter()
+            .flat_map(|(i, j)| old_lines[i].iter().cartesian_product(&old_lines[j]))
             .map(|(pf1, pf2)| (&pf1.f, &pf2.f));
         (iter_formulas, iter_pairs, new_line)
     }
@@ -114,9 +112,28 @@ impl IntoIterator for BeamSearchCache {
     }
 }
 
+/// Entry of the hashmap of a [`BeamSearchCache`].
+///
+/// Formulas dropped from their line leave their characteristic behind as a tombstone,
+/// so that the hashes probed by [`find_slot`] after theirs can still be reached.
+#[derive(Debug)]
+enum Slot {
+    Kept(BoolFormula),
+    Dropped(BoolCharac),
+}
+
+impl Slot {
+    fn charac(&self) -> &BoolCharac {
+        match self {
+            Slot::Kept(f) => &f.charac,
+            Slot::Dropped(charac) => charac,
+        }
+    }
+}
+
 pub(crate) struct BeamSearchBoolCacheLine<'a> {
     line: &'a mut BinaryHeap<PcoBoolFormula>,
-    hashes: &'a mut FxHashMap<<BoolCharac as Hashed>::HashType, BoolFormula>,
+    hashes: &'a mut FxHashMap<<BoolCharac as Hashed>::HashType, Slot>,
     max_line_size: usize,
 }
 
@@ -133,26 +150,29 @@ impl<'a> BeamSearchBoolCacheLine<'a> {
 }
 
 impl<'a> EnumFormulaCacheLine<BoolCharac> for BeamSearchBoolCacheLine<'a> {
-    fn push(&mut self, f: BoolFormula) -> bool {
+    fn push(&mut self, mut f: BoolFormula) -> bool {
         if self.dominates(&f).is_some() {
             return false;
         }
 
-        let hash = f.hashed();
-        let removed = match self.hashes.entry(hash) {
-            Entry::Occupied(_) => return false,
-            Entry::Vacant(e) => {
-                e.insert(f.clone());
-                self.line.push(PcoBoolFormula { f });
-                if self.line.len() > self.max_line_size {
-                    self.line.pop()
-                } else {
-                    return true;
-                }
+        let equal = find_slot(&mut f, CacheKind::BeamSearch, |hash| {
+            self.hashes.get(hash).map(Slot::charac)
+        });
+        if let Some(hash) = equal {
+            if let Slot::Kept(_) = self.hashes[&hash] {
+                return false;
             }
-        };
+            // Equal to a dropped formula, whose slot is reused.
+            f.charac.set_hash(hash);
+        }
 
-        self.hashes.remove(&removed.unwrap().f.hashed());
+        self.hashes.insert(f.hashed(), Slot::Kept(f.clone()));
+        self.line.push(PcoBoolFormula { f });
+        if self.line.len() > self.max_line_size {
+            let removed = self.line.pop().unwrap();
+            self.hashes
+                .insert(removed.f.hashed(), Slot::Dropped(removed.f.charac));
+        }
 
         true
     }
@@ -185,6 +205,44 @@ impl Ord for PcoBoolFormula {
 
 impl PcoBoolFormula {
     pub(crate) fn dominates(&self, f: &BoolFormula) -> bool {
-        self.f.charac.sv.dominates(f.charac.sv)
+        self.f.charac.sv.dominates(&f.charac.sv)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use crate::{
+        bool::cv::CharVec,
+        cache::collisions::with_degenerate_hash,
+        formula::tree::FormulaTree,
+        ltl::{Predicate, PredicateForm},
+    };
+
+    use super::*;
+
+    #[test]
+    fn dropped_formulas_keep_collision_chains() {
+        with_degenerate_hash(0, || {
+            let target: CharVec = [false; 4].into_iter().collect();
+            let formula = |cv: [bool; 4]| {
+                let charac = BoolCharac::from_cv(cv.into_iter().collect(), &target);
+                let atom = FormulaTree::Atom(Predicate("p".to_owned(), PredicateForm::Positive(0)));
+                BoolFormula::new_base(charac, 1, Arc::new(atom))
+            };
+            let mut cache = BeamSearchCache::new(1);
+
+            // All the hashes collide: `b` is stored after `a`, which is then dropped.
+            let mut line = cache.new_line(0);
+            assert!(line.push(formula([false, true, true, true])));
+            assert!(line.push(formula([true, false, false, true])));
+            assert_eq!(cache.len(), 1);
+
+            // `b` is still found past the slot of `a`, and `a` can be added again.
+            let mut line = cache.new_line(1);
+            assert!(!line.push(formula([true, false, false, true])));
+            assert!(line.push(formula([false, true, true, true])));
+        });
     }
 }
@@ -16,7 +16,11 @@ use crate::{
 
 pub mod cache;
 
-use super::{meta::cache::InitialBoolCache, BoolAlgoParams};
+use super::{
+    budget::{Budget, Exhausted},
+    meta::cache::InitialBoolCache,
+    BoolAlgoParams,
+};
 
 #[derive(Args, Clone, Copy)]
 pub struct BeamSearchParams {
@@ -26,6 +30,15 @@ pub struct BeamSearchParams {
     max_size_bool: usize,
 }
 
+impl BeamSearchParams {
+    pub fn new(beam_width: usize, max_size_bool: usize) -> Self {
+        Self {
+            beam_width,
+            max_size_bool,
+        }
+    }
+}
+
 impl BoolAlgoParams for BeamSearchParams {
     type Data = ();
 
@@ -34,18 +47,23 @@ impl BoolAlgoParams for BeamSearchParams {
         cache: InitialBoolCache,
         operators: Operators,
         target: &[bool],
-    ) -> (Option<FormulaTree>, Self::Data) {
+        max_errors: usize,
+        budget: &Budget,
+    ) -> (Result<Option<FormulaTree>, Exhausted>, Self::Data) {
         let bool_target = target.iter().copied().collect();
         let bool_operators = operators.filter_bool();
-        let mut cache = convert_cache_beam_search(cache, self.beam_width, bool_target);
+        let mut cache = convert_cache_beam_search(cache, self.beam_width, &bool_target);
         let f = enum_aux(
             &mut cache,
             &bool_operators,
             &bool_target,
+            max_errors,
             self.max_size_bool,
+            1,
+            budget,
         );
 
-        let f_str = f.map(|f| rebuild_formula(&f, &cache));
+        let f_str = f.map(|f| f.map(|f| rebuild_formula(&f, &cache)));
         (f_str, ())
     }
 
@@ -57,7 +75,7 @@ impl BoolAlgoParams for BeamSearchParams {
 fn convert_cache_beam_search(
     cache: InitialBoolCache,
     max_line_size: usize,
-    target: CharVec,
+    target: &CharVec,
 ) -> BeamSearchCache {
     let mut bs_cache = BeamSearchCache::new(max_line_size);
 
@@ -73,3 +91,18 @@ fn convert_cache_beam_search(
 
     bs_cache
 }
+
+#[cfg(test)]
+mod test {
+    use crate::algos::meta::test::run_with_degenerate_hash;
+
+    use super::*;
+
+    #[test]
+    fn learns_with_hash_collisions() {
+        run_with_degenerate_hash(BeamSearchParams {
+            beam_width: 8,
+            max_size_bool: 5,
+        });
+    }
+}
@@ -0,0 +1,137 @@
+//! Limits on the time and memory used by a run.
+//!
+//! A [`Budget`] is started from [`BudgetParams`] at the beginning of a run,
+//! and checked regularly by the algorithms, which stop with the reason
+//! of the exhaustion ([`Exhausted`]) once one of the limits is reached.
+use std::{
+    fs,
+    time::{Duration, Instant},
+};
+
+use clap::Args;
+use thiserror::Error;
+
+/// Limits on the resources of a run, all unlimited by default.
+#[derive(Args, Clone, Debug, Default)]
+pub struct BudgetParams {
+    /// Stop after this many seconds.
+    #[arg(long, value_name = "SECONDS", global = true, value_parser = parse_timeout)]
+    pub timeout: Option<f64>,
+    /// Stop when the formula caches contain more than this many formulas.
+    #[arg(long, value_name = "N", global = true)]
+    pub max_cache_entries: Option<usize>,
+    /// Stop when the resident memory of the process exceeds this many MiB.
+    ///
+    /// Only enforced on Linux, where the memory is read from `/proc/self/statm`.
+    #[arg(long, value_name = "MIB", global = true)]
+    pub max_memory: Option<usize>,
+}
+
+impl BudgetParams {
+    /// Start a budget with these limits, with the time limit counted from now.
+    ///
+    /// Limits too large to be represented are not enforced.
+    pub fn start(&self) -> Budget {
+        Budget {
+            deadline: self
+                .timeout
+                .and_then(|t| Duration::try_from_secs_f64(t).ok())
+                .and_then(|d| Instant::now().checked_add(d)),
+            max_cache_entries: self.max_cache_entries,
+            max_memory: self.max_memory.and_then(|m| m.checked_mul(1 << 20)),
+        }
+    }
+}
+
+fn parse_timeout(s: &str) -> Result<f64, String> {
+    let t: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if t >= 0. && t.is_finite() {
+        Ok(t)
+    } else {
+        Err("the timeout must be a finite, non-negative number of seconds".to_owned())
+    }
+}
+
+/// Reason for which a budget is exhausted.
+#[derive(Debug, Error, Clone, Copy, PartialEq, Eq)]
+pub enum Exhausted {
+    #[error("time budget exhausted")]
+    Time,
+    #[error("memory budget exhausted")]
+    Memory,
+}
+
+/// Limits on the resources of a run, started at a given time.
+#[derive(Debug, Clone, Default)]
+pub struct Budget {
+    deadline: Option<Instant>,
+    max_cache_entries: Option<usize>,
+    /// Maximum resident memory, in bytes.
+    max_memory: Option<usize>,
+}
+
+impl Budget {
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// Check that the budget is not exhausted, when the caches contain `cache_entries` formulas.
+    pub(crate) fn check(&self, cache_entries: usize) -> Result<(), Exhausted> {
+        if self.deadline.is_some_and(|d| Instant::now() >= d) {
+            return Err(Exhausted::Time);
+        }
+        if self.max_cache_entries.is_some_and(|m| cache_entries > m) {
+            return Err(Exhausted::Memory);
+        }
+        if let Some(max) = self.max_memory {
+            if resident_memory().is_some_and(|m| m > max) {
+                return Err(Exhausted::Memory);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Resident memory of the process in bytes, assuming pages of 4 KiB.
+fn resident_memory() -> Option<usize> {
+    let statm = fs::read_to_string("/proc/self/statm").ok()?;
+    let pages: usize = statm.split_whitespace().nth(1)?.parse().ok()?;
+    Some(pages * 4096)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn exhaustion_reasons() {
+        assert_eq!(Budget::unlimited().check(usize::MAX), Ok(()));
+
+        let params = BudgetParams {
+            max_cache_entries: Some(10),
+            ..Default::default()
+        };
+        assert_eq!(params.start().check(10), Ok(()));
+        assert_eq!(params.start().check(11), Err(Exhausted::Memory));
+
+        let params = BudgetParams {
+            timeout: Some(0.),
+            ..Default::default()
+        };
+        assert_eq!(params.start().check(0), Err(Exhausted::Time));
+    }
+
+    #[test]
+    fn huge_limits_are_not_enforced() {
+        let params = BudgetParams {
+            timeout: Some(1e30),
+            max_memory: Some(usize::MAX),
+            ..Default::default()
+        };
+        assert_eq!(params.start().check(0), Ok(()));
+        assert_eq!(parse_timeout("1e30"), Ok(1e30));
+        assert!(parse_timeout("-1").is_err());
+        assert!(parse_timeout("inf").is_err());
+        assert!(parse_timeout("NaN").is_err());
+    }
+}
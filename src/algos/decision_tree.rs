@@ -0,0 +1,373 @@
+//! Decision tree algorithm for Boolean Synthesis.
+//!
+//! The traces are split recursively by the LTL formula of the initial cache with the highest
+//! information gain, cheaper formulas first in case of ties. A node becomes a leaf, predicting
+//! the majority label of its traces, once the proportion of this label reaches the purity limit,
+//! once the maximal depth is reached, or when no formula improves the information gain.
+//!
+//! The tree is then compiled into a formula, a node splitting on `f` with children `hi` and `lo`
+//! becoming `(f & hi) | (!f & lo)`, simplified when a child is a leaf.
+//! The negation of `f` is taken from the cache if it contains it, and written `!f` otherwise.
+//! If `!` is not among the operators, trees needing a negation missing from the cache
+//! are not compiled.
+use std::sync::Arc;
+
+use clap::Args;
+
+use crate::{
+    formula::tree::FormulaTree,
+    ltl::trace::Operators,
+    ops::{binary::LtlBinaryOp, unary::LtlUnaryOp},
+};
+
+use super::{
+    budget::{Budget, Exhausted},
+    meta::cache::InitialBoolCache,
+    BoolAlgoParams,
+};
+
+/// Minimal information gain of a split.
+const MIN_GAIN: f64 = 1e-9;
+
+#[derive(Args, Clone, Copy)]
+pub struct DecisionTreeParams {
+    /// Maximum depth of the decision tree.
+    max_depth: usize,
+    /// Stop splitting the traces of a node once this proportion of them has the same label,
+    /// between 0.5 and 1.
+    #[arg(long, default_value_t = 1., value_parser = parse_purity)]
+    min_purity: f64,
+}
+
+impl DecisionTreeParams {
+    pub fn new(max_depth: usize, min_purity: f64) -> Self {
+        Self {
+            max_depth,
+            min_purity,
+        }
+    }
+}
+
+fn parse_purity(s: &str) -> Result<f64, String> {
+    let purity: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if (0.5..=1.).contains(&purity) {
+        Ok(purity)
+    } else {
+        Err("the purity must be between 0.5 and 1".to_owned())
+    }
+}
+
+impl BoolAlgoParams for DecisionTreeParams {
+    type Data = ();
+
+    /// Learn a decision tree, and compile it if it misclassifies at most `max_errors` traces.
+    ///
+    /// Returns `None` if the tree is a single leaf, which is not a formula,
+    /// or if it cannot be compiled with the allowed operators.
+    fn run(
+        &self,
+        cache: InitialBoolCache,
+        operators: Operators,
+        target: &[bool],
+        max_errors: usize,
+        budget: &Budget,
+    ) -> (Result<Option<FormulaTree>, Exhausted>, Self::Data) {
+        let negation = operators.unary.contains(&LtlUnaryOp::Not);
+        let features = (cache.iter_all().into_iter())
+            .map(|(cv, f, _)| (cv.as_slice(), f.clone()))
+            .collect::<Vec<_>>();
+        let res = self
+            .build(&features, target, (0..target.len()).collect(), 0, budget)
+            .map(|tree| {
+                let errors = (0..target.len())
+                    .filter(|&i| tree.predict(&features, i) != target[i])
+                    .count();
+                if errors > max_errors {
+                    return None;
+                }
+                match tree.compile(&features, &cache, target, negation) {
+                    Some(Compiled::Formula(f)) => Some(Arc::unwrap_or_clone(f)),
+                    Some(Compiled::Const(_)) | None => None,
+                }
+            });
+        (res, ())
+    }
+
+    fn name() -> &'static str {
+        "decision_tree"
+    }
+}
+
+/// Characteristic vector and formula of each feature.
+type Features<'a> = [(&'a [bool], Arc<FormulaTree>)];
+
+enum Node {
+    Leaf(bool),
+    /// Split on a feature, `hi` for the traces satisfying it and `lo` for the others.
+    Split {
+        feature: usize,
+        hi: Box<Node>,
+        lo: Box<Node>,
+    },
+}
+
+/// Formula of a subtree, or the label of all the traces if it is constant.
+enum Compiled {
+    Const(bool),
+    Formula(Arc<FormulaTree>),
+}
+
+impl DecisionTreeParams {
+    /// Decision tree classifying `traces`, rooted at depth `depth`.
+    fn build(
+        &self,
+        features: &Features,
+        target: &[bool],
+        traces: Vec<usize>,
+        depth: usize,
+        budget: &Budget,
+    ) -> Result<Node, Exhausted> {
+        budget.check(0)?;
+        let positives = traces.iter().filter(|&&i| target[i]).count();
+        let majority = 2 * positives >= traces.len();
+        let purity = positives.max(traces.len() - positives) as f64 / traces.len().max(1) as f64;
+        if depth >= self.max_depth || purity >= self.min_purity {
+            return Ok(Node::Leaf(majority));
+        }
+
+        let parent_entropy = entropy(positives, traces.len());
+        let mut best = None;
+        let mut best_gain = MIN_GAIN;
+        for (k, (cv, _)) in features.iter().enumerate() {
+            let (mut hi, mut hi_positives) = (0, 0);
+            for &i in &traces {
+                if cv[i] {
+                    hi += 1;
+                    hi_positives += usize::from(target[i]);
+                }
+            }
+            let (lo, lo_positives) = (traces.len() - hi, positives - hi_positives);
+            let children_entropy = (hi as f64 * entropy(hi_positives, hi)
+                + lo as f64 * entropy(lo_positives, lo))
+                / traces.len() as f64;
+            let gain = parent_entropy - children_entropy;
+            if gain > best_gain {
+                best = Some(k);
+                best_gain = gain;
+            }
+        }
+        let Some(feature) = best else {
+            return Ok(Node::Leaf(majority));
+        };
+
+        let cv = features[feature].0;
+        let (hi, lo) = traces.into_iter().partition(|&i| cv[i]);
+        let hi = self.build(features, target, hi, depth + 1, budget)?;
+        let lo = self.build(features, target, lo, depth + 1, budget)?;
+        Ok(match (hi, lo) {
+            (Node::Leaf(a), Node::Leaf(b)) if a == b => Node::Leaf(a),
+            (hi, lo) => Node::Split {
+                feature,
+                hi: Box::new(hi),
+                lo: Box::new(lo),
+            },
+        })
+    }
+}
+
+/// Entropy of the labels of `n` traces, `positives` of which are positive.
+fn entropy(positives: usize, n: usize) -> f64 {
+    [positives, n - positives]
+        .into_iter()
+        .filter(|&k| k > 0)
+        .map(|k| {
+            let p = k as f64 / n as f64;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+impl Node {
+    /// Label predicted for trace `i`.
+    fn predict(&self, features: &Features, i: usize) -> bool {
+        match self {
+            Node::Leaf(b) => *b,
+            Node::Split { feature, hi, lo } => {
+                if features[*feature].0[i] {
+                    hi.predict(features, i)
+                } else {
+                    lo.predict(features, i)
+                }
+            }
+        }
+    }
+
+    /// Formula of the subtree, writing the negation of a feature missing from the cache with `!`
+    /// if `negation` is set.
+    ///
+    /// Returns `None` if a negation is needed but missing from the cache and `negation` is unset.
+    fn compile(
+        &self,
+        features: &Features,
+        cache: &InitialBoolCache,
+        target: &[bool],
+        negation: bool,
+    ) -> Option<Compiled> {
+        let (feature, hi, lo) = match self {
+            Node::Leaf(b) => return Some(Compiled::Const(*b)),
+            Node::Split { feature, hi, lo } => (feature, hi, lo),
+        };
+        let (cv, f) = &features[*feature];
+        let f = f.clone();
+        let not_f = || {
+            let negated = cv.iter().map(|b| !b).collect::<Vec<_>>();
+            match cache.get_from_cv(&negated, target) {
+                Some(g) => Some(Arc::new(g)),
+                None => negation.then(|| {
+                    Arc::new(FormulaTree::UnaryNode {
+                        op: LtlUnaryOp::Not,
+                        child: f.clone(),
+                    })
+                }),
+            }
+        };
+        let binary = |op, left, right| Arc::new(FormulaTree::BinaryNode { op, left, right });
+        let (and, or) = (LtlBinaryOp::And, LtlBinaryOp::Or);
+
+        let hi = hi.compile(features, cache, target, negation)?;
+        let lo = lo.compile(features, cache, target, negation)?;
+        Some(Compiled::Formula(match (hi, lo) {
+            (Compiled::Const(a), Compiled::Const(b)) if a == b => return Some(Compiled::Const(a)),
+            (Compiled::Const(true), Compiled::Const(_)) => f.clone(),
+            (Compiled::Const(false), Compiled::Const(_)) => not_f()?,
+            (Compiled::Formula(hi), Compiled::Const(false)) => binary(and, f.clone(), hi),
+            (Compiled::Formula(hi), Compiled::Const(true)) => binary(or, not_f()?, hi),
+            (Compiled::Const(false), Compiled::Formula(lo)) => binary(and, not_f()?, lo),
+            (Compiled::Const(true), Compiled::Formula(lo)) => binary(or, f.clone(), lo),
+            (Compiled::Formula(hi), Compiled::Formula(lo)) => {
+                let hi = binary(and, f.clone(), hi);
+                binary(or, hi, binary(and, not_f()?, lo))
+            }
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        algos::{
+            atoms, create_initial_cache,
+            enumeration::aux::enum_aux,
+            meta::{divide_conquer, test::F_P_AND_G_Q, MetaParams},
+        },
+        ltl::trace::parse_traces,
+    };
+
+    use super::*;
+
+    #[test]
+    fn separates_with_decision_tree() {
+        let (traces, alphabet, target, operators) = parse_traces(F_P_AND_G_Q).unwrap();
+        let meta_params = MetaParams {
+            max_size_ltl: 2,
+            domin_nb: 8,
+            no_minimise: true,
+            ..Default::default()
+        };
+        let res = divide_conquer(
+            &traces,
+            alphabet,
+            operators,
+            target.clone(),
+            &meta_params,
+            DecisionTreeParams::new(4, 1.),
+            &Budget::unlimited(),
+        );
+        let f = res.sol().unwrap();
+        assert_eq!(f.classify(&traces, &target).errors(), 0);
+    }
+
+    #[test]
+    fn depth_and_purity_limits() {
+        let (traces, alphabet, target, operators) = parse_traces(F_P_AND_G_Q).unwrap();
+        let atoms = atoms(&traces, alphabet, &operators.costs);
+        let (_, mut ltl_cache) = create_initial_cache(atoms, &target, 0);
+        let found = enum_aux(
+            &mut ltl_cache,
+            &operators,
+            &target,
+            0,
+            2,
+            1,
+            &Budget::unlimited(),
+        );
+        assert_eq!(found, Ok(None));
+        let cache = InitialBoolCache::from_ltl_cache(8, &ltl_cache, &target);
+        let run = |max_depth, min_purity, max_errors| {
+            let params = DecisionTreeParams::new(max_depth, min_purity);
+            let res = params.run(
+                cache.clone(),
+                operators.clone(),
+                &target,
+                max_errors,
+                &Budget::unlimited(),
+            );
+            let f = res.0.unwrap();
+            if let Some(f) = &f {
+                assert!(f.classify(&traces, &target).errors() <= max_errors);
+            }
+            f
+        };
+
+        assert!(run(4, 1., 0).is_some());
+        // A single leaf is not a formula.
+        assert!(run(0, 1., target.len()).is_none());
+        assert!(run(4, 0.5, target.len()).is_none());
+        // No formula of cost at most 2 separates the traces.
+        assert!(run(1, 1., 0).is_none());
+        assert!(run(1, 1., target.len()).is_some());
+    }
+
+    /// Whether `f` contains a `!` node, which is not a negative literal.
+    fn has_not(f: &FormulaTree) -> bool {
+        match f {
+            FormulaTree::Atom(_) => false,
+            FormulaTree::UnaryNode { op, child } => *op == LtlUnaryOp::Not || has_not(child),
+            FormulaTree::BinaryNode { left, right, .. } => has_not(left) || has_not(right),
+        }
+    }
+
+    #[test]
+    fn negation_needs_not_operator() {
+        // The compiled tree needs `!(F !q)`, which is not in the cache.
+        let buf = "1,0;0,1\n1,1\n0,1;0,0\n1,1\n---\n1,1;1,0\n1,0;1,1\n0,0\n1,0\n---\n\
+            !,F,&,|\n---\np,q";
+        let run = |buf: &str| {
+            let (traces, alphabet, target, operators) = parse_traces(buf).unwrap();
+            let atoms = atoms(&traces, alphabet, &operators.costs);
+            let (_, mut ltl_cache) = create_initial_cache(atoms, &target, 0);
+            let found = enum_aux(
+                &mut ltl_cache,
+                &operators,
+                &target,
+                0,
+                2,
+                1,
+                &Budget::unlimited(),
+            );
+            assert_eq!(found, Ok(None));
+            let cache = InitialBoolCache::from_ltl_cache(8, &ltl_cache, &target);
+            let params = DecisionTreeParams::new(4, 1.);
+            let res = params.run(cache, operators, &target, 0, &Budget::unlimited());
+            let f = res.0.unwrap();
+            if let Some(f) = &f {
+                assert_eq!(f.classify(&traces, &target).errors(), 0);
+            }
+            f
+        };
+
+        assert!(has_not(&run(buf).unwrap()));
+        // Without `!`, the negation cannot be written.
+        assert_eq!(run(&buf.replace("!,", "")), None);
+    }
+}
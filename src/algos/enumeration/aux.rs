@@ -1,6 +1,11 @@
+use itertools::Itertools;
 use log::{debug, info};
 
-use crate::ltl::trace::Operators;
+use crate::{
+    algos::budget::{Budget, Exhausted},
+    ltl::trace::Operators,
+    ops::{binary::LtlBinaryOp, unary::LtlUnaryOp},
+};
 
 use crate::{
     cache::{EnumFormulaCache, EnumFormulaCacheLine},
@@ -9,16 +14,124 @@ use crate::{
     traits::{EqTarget, Hashed},
 };
 
-use std::fmt::Debug;
+use std::{fmt::Debug, thread};
 
+/// Number of children (or pairs of children) handled by a thread at once in parallel searches.
+const BATCH_SIZE: usize = 1 << 12;
+
+/// Enumerate formulas by increasing cost, from the first line missing in `cache` up to `max_size`,
+/// until one of them matches `target` up to `max_errors` misclassified inputs.
+///
+/// The candidates of each cost are generated by `threads` threads,
+/// and added to the cache in the same order as with a single thread,
+/// so that the result does not depend on the number of threads.
+///
+/// Stops early if `budget` is exhausted, checking it regularly.
 pub(crate) fn enum_aux<Cache, Char>(
     cache: &mut Cache,
     operators: &Operators,
     target: &Char::TargetType,
+    max_errors: usize,
+    max_size: usize,
+    threads: usize,
+    budget: &Budget,
+) -> Result<Option<Formula<Char>>, Exhausted>
+where
+    Char: UnaryOp + BinaryOp + Debug + Eq + EqTarget + Hashed + Clone + Debug + Send + Sync,
+    Char::TargetType: Debug,
+    Cache: EnumFormulaCache<Char> + Debug,
+{
+    let mut matches = Matches::new(target, max_errors, Collect::First);
+    enum_matches(
+        cache,
+        &[],
+        operators,
+        &mut matches,
+        max_size,
+        threads,
+        budget,
+    )?;
+    Ok(matches.found.pop())
+}
+
+/// Like [`enum_aux`], but add the formulas of `seeds[size]` to the line of each `size`
+/// before the candidates built from the previous lines.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn enum_seeded_aux<Cache, Char>(
+    cache: &mut Cache,
+    seeds: &[Vec<Formula<Char>>],
+    operators: &Operators,
+    target: &Char::TargetType,
+    max_errors: usize,
+    max_size: usize,
+    threads: usize,
+    budget: &Budget,
+) -> Result<Option<Formula<Char>>, Exhausted>
+where
+    Char: UnaryOp + BinaryOp + Debug + Eq + EqTarget + Hashed + Clone + Debug + Send + Sync,
+    Char::TargetType: Debug,
+    Cache: EnumFormulaCache<Char> + Debug,
+{
+    let mut matches = Matches::new(target, max_errors, Collect::First);
+    enum_matches(
+        cache,
+        seeds,
+        operators,
+        &mut matches,
+        max_size,
+        threads,
+        budget,
+    )?;
+    Ok(matches.found.pop())
+}
+
+/// Like [`enum_aux`], but finish the line of the first matching formula, and return
+/// all the matching formulas of this line which are not equivalent to another formula
+/// of the cache, in the order of the enumeration.
+///
+/// Unlike with [`enum_aux`], the matching formulas are added to the cache.
+/// Stops after `limit` matching formulas, if set.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn enum_all_aux<Cache, Char>(
+    cache: &mut Cache,
+    operators: &Operators,
+    target: &Char::TargetType,
+    max_errors: usize,
+    max_size: usize,
+    limit: Option<usize>,
+    threads: usize,
+    budget: &Budget,
+) -> Result<Vec<Formula<Char>>, Exhausted>
+where
+    Char: UnaryOp + BinaryOp + Debug + Eq + EqTarget + Hashed + Clone + Debug + Send + Sync,
+    Char::TargetType: Debug,
+    Cache: EnumFormulaCache<Char> + Debug,
+{
+    let mut matches = Matches::new(target, max_errors, Collect::All { limit });
+    enum_matches(
+        cache,
+        &[],
+        operators,
+        &mut matches,
+        max_size,
+        threads,
+        budget,
+    )?;
+    Ok(matches.found)
+}
+
+/// Enumerate formulas by increasing cost until `matches` has found some.
+fn enum_matches<Cache, Char>(
+    cache: &mut Cache,
+    seeds: &[Vec<Formula<Char>>],
+    operators: &Operators,
+    matches: &mut Matches<Char>,
     max_size: usize,
-) -> Option<Formula<Char>>
+    threads: usize,
+    budget: &Budget,
+) -> Result<(), Exhausted>
 where
-    Char: UnaryOp + BinaryOp + Debug + Eq + EqTarget + Hashed + Clone + Debug,
+    Char: UnaryOp + BinaryOp + Debug + Eq + EqTarget + Hashed + Clone + Debug + Send + Sync,
     Char::TargetType: Debug,
     Cache: EnumFormulaCache<Char> + Debug,
 {
@@ -29,7 +142,25 @@ where
         start_size, max_size
     );
     debug!("operators: {operators:?}");
-    debug!("target: {target:?}");
+    debug!("target: {:?}", matches.target);
+
+    let unary = operators
+        .unary
+        .iter()
+        .map(|&op| (op, operators.costs.unary(op)))
+        .collect_vec();
+    let binary = operators
+        .binary
+        .iter()
+        .map(|&op| (op, operators.costs.binary(op)))
+        .collect_vec();
+    let unary_costs = unary.iter().map(|&(_, c)| c).sorted().dedup().collect_vec();
+    let binary_costs = binary
+        .iter()
+        .map(|&(_, c)| c)
+        .sorted()
+        .dedup()
+        .collect_vec();
 
     for size in start_size..=max_size {
         debug!(
@@ -37,109 +168,317 @@ where
             size,
             cache.len(),
         );
-        let (iter, pair_iter, mut new_line) = cache.new_line_and_iter_size(size);
+        let mut tracker = BudgetTracker::new(budget, cache.len());
+        tracker.check()?;
+        let (iter, pair_iter, mut new_line) =
+            cache.new_line_and_iter_size(size, &unary_costs, &binary_costs);
 
-        debug!("  Unary:");
-        let res = aux_search_unary::<Cache, Char>(iter, &mut new_line, operators, target);
-        match res {
-            Ok(f) => {
+        if let Some(seeds) = seeds.get(size) {
+            debug!("  Seeds:");
+            let hits = search::<Cache, Char, _>(
+                seeds.iter(),
+                |&f| vec![f.clone()],
+                &mut new_line,
+                matches,
+                threads,
+                &mut tracker,
+            )?;
+            debug!("    Got {hits} hits");
+            if matches.done() {
                 info!("Found formula");
-                return Some(f);
+                return Ok(());
             }
-            Err(hits) => debug!("    Got {hits} hits"),
+        }
+
+        debug!("  Unary:");
+        let hits = search::<Cache, Char, _>(
+            iter,
+            |f| unary_candidates(f, &unary, size),
+            &mut new_line,
+            matches,
+            threads,
+            &mut tracker,
+        )?;
+        debug!("    Got {hits} hits");
+        if matches.done() {
+            info!("Found formula");
+            return Ok(());
         }
 
         debug!("  Binary:");
-        let res = aux_search_binary::<Cache, Char>(pair_iter, &mut new_line, operators, target);
+        let hits = search::<Cache, Char, _>(
+            pair_iter,
+            |&(f_l, f_r)| binary_candidates(f_l, f_r, &binary, size),
+            &mut new_line,
+            matches,
+            threads,
+            &mut tracker,
+        )?;
+        debug!("    Got {hits} hits");
+        if !matches.found.is_empty() {
+            info!("Found {} formulas", matches.found.len());
+            return Ok(());
+        }
+    }
 
-        match res {
-            Ok(f) => {
-                info!("Found formula");
-                return Some(f);
-            }
+    info!("Not found, exiting");
+    Ok(())
+}
+
+/// Which formulas matching the target an enumeration collects.
+#[derive(Debug, Clone, Copy)]
+enum Collect {
+    /// Only the first one, which is not added to the cache.
+    First,
+    /// All those of the line of the first one, up to `limit`, which are added to the cache.
+    All { limit: Option<usize> },
+}
+
+/// Formulas matching a target found by an enumeration.
+struct Matches<'t, Char>
+where
+    Char: EqTarget + Hashed,
+{
+    target: &'t Char::TargetType,
+    max_errors: usize,
+    collect: Collect,
+    found: Vec<Formula<Char>>,
+}
+
+impl<'t, Char> Matches<'t, Char>
+where
+    Char: EqTarget + Hashed,
+{
+    fn new(target: &'t Char::TargetType, max_errors: usize, collect: Collect) -> Self {
+        Self {
+            target,
+            max_errors,
+            collect,
+            found: vec![],
+        }
+    }
 
-            Err(hits) => debug!("    Got {hits} hits"),
+    fn is_match(&self, f: &Formula<Char>) -> bool {
+        f.eq_target(self.target, self.max_errors)
+    }
+
+    /// Whether no more formulas should be collected.
+    fn done(&self) -> bool {
+        match self.collect {
+            Collect::First => !self.found.is_empty(),
+            Collect::All { limit } => limit.is_some_and(|l| self.found.len() >= l),
         }
     }
+}
 
-    info!("Not found, exiting");
-    None
+/// Number of candidates between two checks of the budget.
+const CHECK_INTERVAL: usize = 1 << 10;
+
+/// Checks of the budget during the enumeration of a line,
+/// counting the formulas added to the cache.
+struct BudgetTracker<'b> {
+    budget: &'b Budget,
+    cache_entries: usize,
+    unchecked: usize,
 }
 
-fn aux_search_unary<'a, Cache, Char>(
-    formula_iter: impl Iterator<Item = &'a Formula<Char>>,
+impl<'b> BudgetTracker<'b> {
+    fn new(budget: &'b Budget, cache_entries: usize) -> Self {
+        Self {
+            budget,
+            cache_entries,
+            unchecked: 0,
+        }
+    }
+
+    fn check(&self) -> Result<(), Exhausted> {
+        self.budget.check(self.cache_entries)
+    }
+
+    /// Record a candidate, and whether it was added to the cache.
+    fn candidate(&mut self, added: bool) -> Result<(), Exhausted> {
+        self.cache_entries += usize::from(added);
+        self.unchecked += 1;
+        if self.unchecked < CHECK_INTERVAL {
+            return Ok(());
+        }
+        self.unchecked = 0;
+        self.check()
+    }
+}
+
+/// Add the candidates generated from each item of `items` to the new line of the cache,
+/// recording those matching the target in `matches` until it is done.
+///
+/// With several threads, the items are processed by batches: the candidates of a batch
+/// are generated in parallel, then added to the cache in order.
+///
+/// Returns the number of candidates already in the cache.
+fn search<'a, Cache, Char, T>(
+    items: impl Iterator<Item = T>,
+    candidates: impl Fn(&T) -> Vec<Formula<Char>> + Sync,
     new_cache: &mut Cache::CacheLine<'a>,
-    operators: &Operators,
-    target: &Char::TargetType,
-) -> Result<Formula<Char>, usize>
+    matches: &mut Matches<Char>,
+    threads: usize,
+    tracker: &mut BudgetTracker,
+) -> Result<usize, Exhausted>
 where
-    Char: UnaryOp + EqTarget + Hashed + 'a + Debug,
+    Char: EqTarget + Hashed + Clone + 'a + Send,
     Cache: EnumFormulaCache<Char>,
+    T: Sync,
 {
-    let mut hits = 0;
-
-    let ops = &operators.unary;
-    if ops.is_empty() {
-        return Err(0);
+    if threads <= 1 {
+        return push_candidates::<Cache, Char>(
+            items.flat_map(|x| candidates(&x)),
+            new_cache,
+            matches,
+            tracker,
+        );
     }
 
-    for f in formula_iter {
-        for &op in ops {
-            let g = apply_unary(op, f);
-            if g.eq_target(target) {
-                return Ok(g);
-            }
-
-            if !new_cache.push(g) {
-                hits += 1;
-            }
+    let mut hits = 0;
+    for batch in &items.chunks(threads * BATCH_SIZE) {
+        let batch = batch.collect_vec();
+        let chunk_size = batch.len().div_ceil(threads);
+        let generated = thread::scope(|s| {
+            let handles = batch
+                .chunks(chunk_size)
+                .map(|chunk| s.spawn(|| chunk.iter().flat_map(&candidates).collect_vec()))
+                .collect_vec();
+            handles
+                .into_iter()
+                .map(|h| h.join().expect("Enumeration thread panicked"))
+                .collect_vec()
+        });
+        let generated = generated.into_iter().flatten();
+        hits += push_candidates::<Cache, Char>(generated, new_cache, matches, tracker)?;
+        if matches.done() {
+            break;
         }
+        tracker.check()?;
     }
-    Err(hits)
+    Ok(hits)
 }
 
-fn aux_search_binary<'a, Cache, Char>(
-    pair_iter: impl Iterator<Item = (&'a Formula<Char>, &'a Formula<Char>)>,
+fn push_candidates<'a, Cache, Char>(
+    candidates: impl Iterator<Item = Formula<Char>>,
     new_cache: &mut Cache::CacheLine<'a>,
-    operators: &Operators,
-    target: &Char::TargetType,
-) -> Result<Formula<Char>, usize>
+    matches: &mut Matches<Char>,
+    tracker: &mut BudgetTracker,
+) -> Result<usize, Exhausted>
 where
-    Char: BinaryOp + EqTarget + Hashed + 'a + Clone + Debug,
+    Char: EqTarget + Hashed + Clone + 'a,
     Cache: EnumFormulaCache<Char>,
 {
     let mut hits = 0;
+    for g in candidates {
+        let is_match = matches.is_match(&g);
+        if is_match && matches!(matches.collect, Collect::First) {
+            matches.found.push(g);
+            return Ok(hits);
+        }
 
-    let ops = &operators.binary;
-    if ops.is_empty() {
-        return Err(0);
+        let added = if is_match {
+            let added = new_cache.push(g.clone());
+            if added {
+                matches.found.push(g);
+            }
+            added
+        } else {
+            new_cache.push(g)
+        };
+        if !added {
+            hits += 1;
+        }
+        tracker.candidate(added)?;
+        if matches.done() {
+            return Ok(hits);
+        }
     }
+    Ok(hits)
+}
 
-    for (f_l, f_r) in pair_iter {
-        for &op in ops {
-            let g = apply_binary(op, f_l, f_r);
-            if g.eq_target(target) {
-                return Ok(g);
-            }
+/// Formulas of cost `size` obtained by applying one of `ops` to `f`.
+fn unary_candidates<Char>(
+    f: &Formula<Char>,
+    ops: &[(LtlUnaryOp, usize)],
+    size: usize,
+) -> Vec<Formula<Char>>
+where
+    Char: UnaryOp + Hashed,
+{
+    ops.iter()
+        .filter(|&&(_, op_cost)| f.cost + op_cost == size)
+        .map(|&(op, op_cost)| apply_unary(op, op_cost, f))
+        .collect()
+}
 
-            if !new_cache.push(g) {
-                hits += 1;
-            }
+/// Formulas of cost `size` obtained by applying one of `ops` to `f_l` and `f_r`,
+/// in both orders for non-commutative operators.
+fn binary_candidates<Char>(
+    f_l: &Formula<Char>,
+    f_r: &Formula<Char>,
+    ops: &[(LtlBinaryOp, usize)],
+    size: usize,
+) -> Vec<Formula<Char>>
+where
+    Char: BinaryOp + Hashed,
+{
+    let mut res = vec![];
+    for &(op, op_cost) in ops {
+        if f_l.cost + op_cost + f_r.cost != size {
+            continue;
+        }
+        res.push(apply_binary(op, op_cost, f_l, f_r));
 
-            if op.commutes() {
-                continue;
-            }
+        // For non-commutative operations
+        if !op.commutes() {
+            res.push(apply_binary(op, op_cost, f_r, f_l));
+        }
+    }
+    res
+}
 
-            // For non-commutative operations
-            let g = apply_binary(op, f_r, f_l);
-            if g.eq_target(target) {
-                return Ok(g);
-            }
+#[cfg(test)]
+mod test {
+    use crate::{
+        algos::{atoms, create_initial_cache},
+        ltl::trace::parse_traces,
+    };
 
-            if !new_cache.push(g) {
-                hits += 1;
-            }
+    use super::*;
+
+    #[test]
+    fn parallel_search_matches_sequential() {
+        let buf = "1,0;1,0;0,0\n0,0;0,1\n1,0;1,0\n0,0;1,1;1,1\n1,0;0,0::1\n---\n\
+            0,1;0,0\n1,0;0,1\n1,0;1,0;1,1\n0,1;1,0;1,0::0\n1,0;0,1;0,0\n---\n\
+            !,F,G,X,&,|,U,R\n---\np,q";
+        let (traces, alphabet, target, operators) = parse_traces(buf).unwrap();
+        let run = |threads| {
+            let atoms = atoms(&traces, alphabet.clone(), &operators.costs);
+            let (_, mut cache) = create_initial_cache(atoms, &target, 0);
+            let res = enum_aux(
+                &mut cache,
+                &operators,
+                &target,
+                0,
+                4,
+                threads,
+                &Budget::unlimited(),
+            )
+            .unwrap();
+            let lines = cache
+                .lines
+                .iter()
+                .map(|l| l.iter().map(|f| f.hashed()).collect_vec())
+                .collect_vec();
+            (res.map(|f| f.hashed()), lines)
+        };
+
+        let sequential = run(1);
+        assert!(sequential.0.is_some());
+        for threads in [2, 3, 8] {
+            assert_eq!(run(threads), sequential);
         }
     }
-    Err(hits)
 }
@@ -12,7 +12,11 @@ use crate::{
     ltl::trace::Operators,
 };
 
-use super::{meta::cache::InitialBoolCache, BoolAlgoParams};
+use super::{
+    budget::{Budget, Exhausted},
+    meta::cache::InitialBoolCache,
+    BoolAlgoParams,
+};
 
 #[derive(Args, Clone, Copy)]
 pub struct EnumParams {
@@ -20,6 +24,15 @@ pub struct EnumParams {
     domin_nb: usize,
 }
 
+impl EnumParams {
+    pub fn new(max_size_bool: usize, domin_nb: usize) -> Self {
+        Self {
+            max_size_bool,
+            domin_nb,
+        }
+    }
+}
+
 impl BoolAlgoParams for EnumParams {
     type Data = Vec<usize>;
 
@@ -28,18 +41,23 @@ impl BoolAlgoParams for EnumParams {
         cache: InitialBoolCache,
         operators: Operators,
         target: &[bool],
-    ) -> (Option<FormulaTree>, Self::Data) {
+        max_errors: usize,
+        budget: &Budget,
+    ) -> (Result<Option<FormulaTree>, Exhausted>, Self::Data) {
         let bool_target: CharVec = target.iter().copied().collect();
-        let mut bool_cache = convert_cache_enum(cache, bool_target, self.domin_nb);
+        let mut bool_cache = convert_cache_enum(cache, &bool_target, self.domin_nb);
         let bool_operators = operators.filter_bool();
         let f = enum_aux(
             &mut bool_cache,
             &bool_operators,
             &bool_target,
+            max_errors,
             self.max_size_bool,
+            1,
+            budget,
         );
 
-        let f_str = f.map(|f| rebuild_formula(&f, &bool_cache));
+        let f_str = f.map(|f| f.map(|f| rebuild_formula(&f, &bool_cache)));
         (
             f_str,
             bool_cache
@@ -55,7 +73,7 @@ impl BoolAlgoParams for EnumParams {
     }
 }
 
-fn convert_cache_enum(cache: InitialBoolCache, target: CharVec, k: usize) -> BoolCache {
+pub(crate) fn convert_cache_enum(cache: InitialBoolCache, target: &CharVec, k: usize) -> BoolCache {
     let mut bs_cache = BoolCache::new(k);
 
     for (size, cache) in cache.iter_lines().into_iter().enumerate() {
@@ -70,3 +88,161 @@ fn convert_cache_enum(cache: InitialBoolCache, target: CharVec, k: usize) -> Boo
 
     bs_cache
 }
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        algos::meta::{divide_conquer, test::run_with_degenerate_hash, MetaParams},
+        ltl::trace::parse_traces,
+    };
+
+    use super::*;
+
+    #[test]
+    fn learns_recurrence_on_lassos() {
+        // Positive lassos visit `p` infinitely often, negative ones eventually stop.
+        // The first positive and the last negative only differ by their loop.
+        let buf =
+            "1;0::0\n0;0;1::1\n0;1::1\n---\n1;1;0::2\n0;1;0::2\n1;0::1\n---\nF,G,X,&,|\n---\np";
+        let (traces, alphabet, target, operators) = parse_traces(buf).unwrap();
+        let meta_params = MetaParams {
+            max_size_ltl: 4,
+            domin_nb: 4,
+            ..Default::default()
+        };
+        let params = EnumParams {
+            max_size_bool: 4,
+            domin_nb: 4,
+        };
+
+        let res = divide_conquer(
+            &traces,
+            alphabet,
+            operators,
+            target.clone(),
+            &meta_params,
+            params,
+            &Budget::unlimited(),
+        );
+        let f = res.sol().unwrap();
+        assert_eq!(f.eval(&traces).accepted_vec(), target);
+        assert_eq!(f.size(), 3);
+    }
+
+    #[test]
+    fn learns_negated_until() {
+        // Traces are labelled by `!(p U q)`, which has no small equivalent without `Not`.
+        let buf = "1,0;1,0;0,0\n0,0;0,1\n1,0;1,0\n0,0;1,1;1,1\n1,0;0,0::1\n---\n\
+            0,1;0,0\n1,0;0,1\n1,0;1,0;1,1\n0,1;1,0;1,0::0\n1,0;0,1;0,0\n---\n\
+            !,F,G,X,&,|,U\n---\np,q";
+        let (traces, alphabet, target, operators) = parse_traces(buf).unwrap();
+        let meta_params = MetaParams {
+            max_size_ltl: 4,
+            domin_nb: 4,
+            ..Default::default()
+        };
+        let params = EnumParams {
+            max_size_bool: 4,
+            domin_nb: 4,
+        };
+
+        let res = divide_conquer(
+            &traces,
+            alphabet,
+            operators,
+            target.clone(),
+            &meta_params,
+            params,
+            &Budget::unlimited(),
+        );
+        let f = res.sol().unwrap();
+        assert_eq!(f.eval(&traces).accepted_vec(), target);
+        assert_eq!(f.size(), 4);
+    }
+
+    #[test]
+    fn learns_cheapest_formula_for_weights() {
+        // Traces are labelled both by `p` and by `X q`.
+        let buf = "1,0;0,1\n1,1;0,1\n---\n0,0;0,0\n0,1;1,0\n---\nX,&,|\n---\np,q";
+        let (traces, alphabet, target, operators) = parse_traces(buf).unwrap();
+        let meta_params = MetaParams {
+            max_size_ltl: 3,
+            domin_nb: 4,
+            ..Default::default()
+        };
+        let params = EnumParams {
+            max_size_bool: 3,
+            domin_nb: 4,
+        };
+
+        let res = divide_conquer(
+            &traces,
+            alphabet.clone(),
+            operators.clone(),
+            target.clone(),
+            &meta_params,
+            params,
+            &Budget::unlimited(),
+        );
+        assert_eq!(res.sol().unwrap().size(), 1);
+        assert_eq!(res.cost(), Some(1));
+
+        let operators = operators.with_weights(["p=3".parse().unwrap()]);
+        let res = divide_conquer(
+            &traces,
+            alphabet,
+            operators,
+            target.clone(),
+            &meta_params,
+            params,
+            &Budget::unlimited(),
+        );
+        let f = res.sol().unwrap();
+        assert_eq!(f.eval(&traces).accepted_vec(), target);
+        assert_eq!(format!("{f}"), "X (q)");
+        assert_eq!(res.cost(), Some(2));
+    }
+
+    #[test]
+    fn learns_with_past_operators() {
+        // Positive traces have a `q` right after a `p`, which can only be expressed
+        // with the available operators as `F (q & Y p)`.
+        let buf = "1,0;0,1\n0,0;1,0;0,1;0,0\n1,1;1,1\n---\n\
+            0,1;1,0\n1,0;0,0;0,1\n0,0;0,1;1,0\n---\n\
+            F,&,Y\n---\np,q";
+        let (traces, alphabet, target, operators) = parse_traces(buf).unwrap();
+        let meta_params = MetaParams {
+            max_size_ltl: 5,
+            domin_nb: 4,
+            ..Default::default()
+        };
+        let params = EnumParams {
+            max_size_bool: 5,
+            domin_nb: 4,
+        };
+
+        let res = divide_conquer(
+            &traces,
+            alphabet,
+            operators,
+            target.clone(),
+            &meta_params,
+            params,
+            &Budget::unlimited(),
+        );
+        let f = res.sol().unwrap();
+        assert_eq!(f.eval(&traces).accepted_vec(), target);
+        assert!(f.has_past());
+        assert_eq!(f.size(), 5);
+    }
+
+    #[test]
+    fn same_result_with_hash_collisions() {
+        let params = EnumParams {
+            max_size_bool: 5,
+            domin_nb: 4,
+        };
+        let (normal, degenerate) = run_with_degenerate_hash(params);
+        assert_eq!(normal.cost(), degenerate.cost());
+    }
+}
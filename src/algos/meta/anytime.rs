@@ -0,0 +1,273 @@
+//! Anytime meta-algorithm, reporting improving solutions as they are found.
+//!
+//! The LTL enumeration is run one cost at a time. After each cost, the Boolean algorithm
+//! is run on the formulas enumerated so far, preceded by a quick [set cover](crate::algos::set_cover)
+//! as long as no solution is known. The search stops when no cheaper formula can be found,
+//! when `max_size_ltl` is reached or when the budget is exhausted.
+use std::time::{Duration, Instant};
+
+use log::{debug, info};
+
+use crate::{
+    algos::{
+        atoms,
+        budget::{Budget, Exhausted},
+        create_initial_cache,
+        enumeration::aux::enum_aux,
+        set_cover::SetCoverParams,
+        BoolAlgoParams,
+    },
+    formula::{cost::CostModel, rebuild_formula, tree::FormulaTree},
+    ltl::trace::{Operators, Trace},
+};
+
+use super::{cache::InitialBoolCache, minimise::minimise, solve_or_split, MetaParams};
+
+/// Number of greedy formulas of the quick set cover run before the first solution.
+const QUICK_SET_COVER_FORMULAS: usize = 4;
+
+/// Solution strictly cheaper than all the previous ones.
+#[derive(Debug, Clone)]
+pub struct Improvement {
+    pub formula: FormulaTree,
+    pub size: usize,
+    pub cost: usize,
+    /// Time elapsed since the start of the search.
+    pub elapsed: Duration,
+}
+
+/// Result of an [anytime] search.
+#[derive(Debug, Clone)]
+pub struct AnytimeResult {
+    /// Cheapest solution found.
+    pub best: Option<Improvement>,
+    /// Reason for which the search was stopped early, if it was.
+    pub exhausted: Option<Exhausted>,
+}
+
+/// Search for cheaper and cheaper separating formulas, calling `on_improvement` on each of them.
+#[allow(clippy::too_many_arguments)]
+pub fn anytime<P>(
+    traces: &[Trace],
+    alphabet: Vec<String>,
+    operators: Operators,
+    target: Vec<bool>,
+    meta_params: &MetaParams,
+    params: P,
+    budget: &Budget,
+    on_improvement: impl FnMut(&Improvement),
+) -> AnytimeResult
+where
+    P: BoolAlgoParams + Clone,
+{
+    let costs = operators.costs.clone();
+    let max_errors = meta_params.allowed_errors(target.len());
+    let mut progress = Progress {
+        start: Instant::now(),
+        costs: &costs,
+        best: None,
+        on_improvement,
+    };
+
+    let (atom, mut ltl_cache) =
+        create_initial_cache(atoms(traces, alphabet, &costs), &target, max_errors);
+    if let Some(f) = atom {
+        progress.improve(Some(rebuild_formula(&f, &ltl_cache)));
+    }
+
+    let mut exhausted = None;
+    for size in ltl_cache.lines.len()..=meta_params.max_size_ltl {
+        // Formulas found from now on cost at least `size`.
+        if progress.best_cost().is_some_and(|c| c <= size) {
+            break;
+        }
+
+        debug!("Anytime search: LTL enumeration up to cost {size}");
+        let ltl_res = enum_aux(
+            &mut ltl_cache,
+            &operators,
+            &target,
+            max_errors,
+            size,
+            meta_params.threads,
+            budget,
+        );
+        match ltl_res {
+            // The LTL enumeration finds the cheapest formula.
+            Ok(Some(f)) => {
+                progress.improve(Some(rebuild_formula(&f, &ltl_cache)));
+                break;
+            }
+            Ok(None) => (),
+            Err(e) => {
+                exhausted = Some(e);
+                break;
+            }
+        }
+
+        let initial_cache =
+            InitialBoolCache::from_ltl_cache(meta_params.domin_nb, &ltl_cache, &target);
+        if progress.best.is_none() {
+            let quick = SetCoverParams::new(QUICK_SET_COVER_FORMULAS);
+            let res = bool_search(
+                traces,
+                &operators,
+                &initial_cache,
+                &target,
+                max_errors,
+                quick,
+                meta_params,
+                budget,
+            );
+            match res {
+                Ok(f) => progress.improve(f),
+                Err(e) => {
+                    exhausted = Some(e);
+                    break;
+                }
+            }
+        }
+        let res = bool_search(
+            traces,
+            &operators,
+            &initial_cache,
+            &target,
+            max_errors,
+            params.clone(),
+            meta_params,
+            budget,
+        );
+        match res {
+            Ok(f) => progress.improve(f),
+            Err(e) => {
+                exhausted = Some(e);
+                break;
+            }
+        }
+    }
+
+    AnytimeResult {
+        best: progress.best,
+        exhausted,
+    }
+}
+
+/// Run the Boolean algorithm of `params` with divide and conquer, and minimise its result.
+#[allow(clippy::too_many_arguments)]
+fn bool_search<P>(
+    traces: &[Trace],
+    operators: &Operators,
+    initial_cache: &InitialBoolCache,
+    target: &[bool],
+    max_errors: usize,
+    params: P,
+    meta_params: &MetaParams,
+    budget: &Budget,
+) -> Result<Option<FormulaTree>, Exhausted>
+where
+    P: BoolAlgoParams + Clone,
+{
+    debug!("Anytime search: running {}", P::name());
+    let f = solve_or_split(
+        traces,
+        operators.clone(),
+        initial_cache,
+        target,
+        max_errors,
+        params,
+        meta_params.split_threshold,
+        budget,
+        &mut vec![],
+    )?;
+    Ok(f.map(|f| {
+        if meta_params.no_minimise {
+            f
+        } else {
+            minimise(
+                f,
+                traces,
+                target,
+                max_errors,
+                initial_cache,
+                &operators.costs,
+            )
+            .0
+        }
+    }))
+}
+
+/// Best solution of an anytime search so far.
+struct Progress<'a, F> {
+    start: Instant,
+    costs: &'a CostModel,
+    best: Option<Improvement>,
+    on_improvement: F,
+}
+
+impl<F: FnMut(&Improvement)> Progress<'_, F> {
+    fn best_cost(&self) -> Option<usize> {
+        self.best.as_ref().map(|b| b.cost)
+    }
+
+    /// Record `formula` if it is cheaper than the best solution so far.
+    fn improve(&mut self, formula: Option<FormulaTree>) {
+        let Some(formula) = formula else {
+            return;
+        };
+        let cost = formula.cost(self.costs);
+        if self.best_cost().is_some_and(|c| c <= cost) {
+            return;
+        }
+        info!("Found solution of cost {cost}");
+        let improvement = Improvement {
+            size: formula.size(),
+            cost,
+            formula,
+            elapsed: self.start.elapsed(),
+        };
+        (self.on_improvement)(&improvement);
+        self.best = Some(improvement);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{algos::meta::test::F_P_AND_G_Q, ltl::trace::parse_traces};
+
+    use super::*;
+
+    #[test]
+    fn improvements_are_cheaper_and_correct() {
+        let (traces, alphabet, target, operators) = parse_traces(F_P_AND_G_Q).unwrap();
+        let meta_params = MetaParams {
+            max_size_ltl: 5,
+            domin_nb: 4,
+            ..Default::default()
+        };
+
+        let mut improvements = vec![];
+        let res = anytime(
+            &traces,
+            alphabet,
+            operators,
+            target.clone(),
+            &meta_params,
+            SetCoverParams::new(4),
+            &Budget::unlimited(),
+            |i| improvements.push(i.clone()),
+        );
+
+        assert!(res.exhausted.is_none());
+        assert!(!improvements.is_empty());
+        for i in &improvements {
+            assert_eq!(i.formula.eval(&traces).accepted_vec(), target);
+        }
+        for (i, j) in improvements.iter().zip(&improvements[1..]) {
+            assert!(j.cost < i.cost);
+            assert!(j.elapsed >= i.elapsed);
+        }
+        let best = res.best.unwrap();
+        assert_eq!(best.cost, improvements.last().unwrap().cost);
+        assert!(best.cost <= 5);
+    }
+}
@@ -1,18 +1,22 @@
 //! Boolean cache used after LTL search
 //! given as input to the boolean algorithms.
 use std::{
+    cmp::Reverse,
     collections::BinaryHeap,
     hash::{Hash, Hasher},
     iter::Flatten,
-    rc::Rc,
+    sync::Arc,
 };
 
-use fxhash::{FxHashMap, FxHasher};
+use fxhash::FxHashMap;
 use itertools::Itertools;
 use log::debug;
 
 use crate::{
-    cache::EnumFormulaCache,
+    cache::{
+        collisions::{self, CacheHasher, CacheKind},
+        EnumFormulaCache,
+    },
     formula::{tree::FormulaTree, FormulaNode},
     ltl::{cache::LtlCache, hash::LtlHash, LtlFormula},
     traits::Hashed,
@@ -21,15 +25,18 @@ use crate::{
 type LsvHash = u64;
 /// Contains a Characteristic vector in [`Vec`] form,
 /// a pointer to the corresponding [`FormulaTree`]
-/// and the size of the formula.
-type BoolInfo = (Vec<bool>, Rc<FormulaTree>, usize);
+/// and the cost of the formula.
+type BoolInfo = (Vec<bool>, Arc<FormulaTree>, usize);
 
 /// Cache for boolean formulas with equivalence and domination test.
 #[derive(Debug, Clone)]
 pub struct InitialBoolCache {
     /// Hashmap of all the hashes of the formulas contained in the cache,
-    /// mapping to the corresponding [`FormulaTree`].
-    hash_cache: FxHashMap<LsvHash, Rc<FormulaTree>>,
+    /// mapping to their satisfiability vector and the corresponding [`FormulaTree`].
+    ///
+    /// A formula whose hash is taken by a different vector is stored under the next free hash,
+    /// as in the other formula caches (see [`find_slot`](crate::cache::find_slot)).
+    hash_cache: FxHashMap<LsvHash, (BitVec, Arc<FormulaTree>)>,
     lines: Vec<Vec<BoolInfo>>,
     /// Set of formulas kept for domination tests.
     ///
@@ -54,6 +61,10 @@ impl InitialBoolCache {
         self.lines.iter().map(|l| l.len()).sum()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.lines.iter().all(|l| l.is_empty())
+    }
+
     pub fn iter_all(&self) -> impl IntoIterator<Item = &BoolInfo> {
         self.lines.iter().flat_map(|l| l.iter())
     }
@@ -65,20 +76,52 @@ impl InitialBoolCache {
     /// Retrieve the formula with the given characteristic vector, if it is present in the cache.
     pub fn get_from_cv(&self, cv: &[bool], target: &[bool]) -> Option<FormulaTree> {
         let lsv = LongSv::from_cv_target(cv, target, 0);
-        self.hash_cache
-            .get(&lsv.hash)
-            .map(|rc| Rc::unwrap_or_clone(rc.clone()))
+        self.find(&lsv)
+            .ok()
+            .map(|(_, rc)| Arc::unwrap_or_clone(rc.clone()))
     }
 
-    /// Test whether the cache contains a formula equivalent to or
-    /// dominating the input formula.
-    fn is_redundant(&self, lsv: &LongSv) -> bool {
-        // Equivalence test
-        if self.hash_cache.contains_key(&lsv.hash) {
-            return true;
+    /// Retrieve the cheapest formula misclassifying at most `max_errors` traces of `target`,
+    /// if it is present in the cache.
+    pub fn get_within(&self, target: &[bool], max_errors: usize) -> Option<FormulaTree> {
+        if max_errors == 0 {
+            return self.get_from_cv(target, target);
         }
+        // Lines are sorted by cost.
+        let (_, f, _) = self.iter_all().into_iter().find(|(cv, _, _)| {
+            let errors = cv.iter().zip(target).filter(|(b, t)| b != t).count();
+            errors <= max_errors
+        })?;
+        Some(FormulaTree::clone(f))
+    }
 
-        // Domination test
+    /// Find the formula with the satisfiability vector of `lsv`,
+    /// recording a collision for each hash taken by a different vector.
+    ///
+    /// Returns the first free hash if there is no such formula.
+    fn find(&self, lsv: &LongSv) -> Result<&(BitVec, Arc<FormulaTree>), LsvHash> {
+        let mut hash = lsv.hash;
+        while let Some(entry) = self.hash_cache.get(&hash) {
+            if entry.0 == lsv.sv {
+                return Ok(entry);
+            }
+            collisions::record(CacheKind::InitialBool);
+            hash = hash.wrapping_add(1);
+        }
+        Err(hash)
+    }
+
+    /// Formula classifying the most traces correctly, the cheapest one in case of ties.
+    pub fn most_accurate(&self, target: &[bool]) -> Option<FormulaTree> {
+        let (_, f, _) = self.iter_all().into_iter().max_by_key(|(cv, _, size)| {
+            let accuracy = cv.iter().zip(target).filter(|(b, t)| b == t).count();
+            (accuracy, Reverse(*size))
+        })?;
+        Some(FormulaTree::clone(f))
+    }
+
+    /// Test whether the cache contains a formula dominating the input formula.
+    fn is_dominated(&self, lsv: &LongSv) -> bool {
         self.best_sv[..lsv.size]
             .iter()
             .any(|h| h.iter().rev().any(|lsv2| lsv2.dominates(lsv)))
@@ -87,21 +130,26 @@ impl InitialBoolCache {
     /// Add a formula to the cache.
     ///
     /// The parameter `cv` is the characteristic vector of the formula
-    /// given in `f_tree`, and `size` is its size.
+    /// given in `f_tree`, and `size` is its cost.
     pub fn push(
         &mut self,
         cv: Vec<bool>,
         target: &[bool],
-        f_tree: Rc<FormulaTree>,
+        f_tree: Arc<FormulaTree>,
         size: usize,
     ) -> bool {
         let lsv = LongSv::from_cv_target(&cv, target, size);
 
-        if self.is_redundant(&lsv) {
+        // Equivalence test
+        let Err(hash) = self.find(&lsv) else {
+            return false;
+        };
+        if self.is_dominated(&lsv) {
             return false;
         }
 
-        self.hash_cache.insert(lsv.hash, f_tree.clone());
+        self.hash_cache
+            .insert(hash, (lsv.sv.clone(), f_tree.clone()));
         self.lines[size].push((cv, f_tree, size));
         self.best_sv[size].push(lsv);
         if self.best_sv[size].len() > self.k {
@@ -111,8 +159,8 @@ impl InitialBoolCache {
         true
     }
 
-    pub(crate) fn from_ltl_cache(k: usize, ltl_cache: LtlCache, target: &[bool]) -> Self {
-        let mut rc_cache: FxHashMap<LtlHash, Rc<FormulaTree>> = FxHashMap::default();
+    pub(crate) fn from_ltl_cache(k: usize, ltl_cache: &LtlCache, target: &[bool]) -> Self {
+        let mut rc_cache: FxHashMap<LtlHash, Arc<FormulaTree>> = FxHashMap::default();
 
         let mut res = Self {
             hash_cache: Default::default(),
@@ -123,12 +171,12 @@ impl InitialBoolCache {
 
         let mut count = 0;
         let mut hits = 0;
-        for l in ltl_cache.lines {
+        for l in &ltl_cache.lines {
             for f in l {
-                let f_tree = rebuild_formula_rc(&f, &rc_cache);
+                let f_tree = rebuild_formula_rc(f, &rc_cache);
                 let cv = f.accepted_vec();
                 let hash = f.hashed();
-                let size = f.size;
+                let size = f.cost;
                 rc_cache.insert(hash, f_tree.clone());
                 if res.push(cv, target, f_tree, size) {
                     count += 1;
@@ -218,7 +266,7 @@ impl InitialBoolCache {
 impl IntoIterator for InitialBoolCache {
     type Item = BoolInfo;
 
-    type IntoIter = Flatten<std::vec::IntoIter<Vec<(Vec<bool>, Rc<FormulaTree>, usize)>>>;
+    type IntoIter = Flatten<std::vec::IntoIter<Vec<(Vec<bool>, Arc<FormulaTree>, usize)>>>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.lines.into_iter().flatten()
@@ -229,15 +277,15 @@ impl IntoIterator for InitialBoolCache {
 /// to explicit tree representation ([`FormulaTree`]).
 fn rebuild_formula_rc(
     f: &LtlFormula,
-    rc_cache: &FxHashMap<LtlHash, Rc<FormulaTree>>,
-) -> Rc<FormulaTree> {
+    rc_cache: &FxHashMap<LtlHash, Arc<FormulaTree>>,
+) -> Arc<FormulaTree> {
     match &f.node {
         FormulaNode::Base(t) => t.clone(),
-        &FormulaNode::Unary { op, child } => Rc::new(FormulaTree::UnaryNode {
+        &FormulaNode::Unary { op, child } => Arc::new(FormulaTree::UnaryNode {
             op,
             child: rc_cache.get(&child).expect("Child not found").clone(),
         }),
-        &FormulaNode::Binary { op, left, right } => Rc::new(FormulaTree::BinaryNode {
+        &FormulaNode::Binary { op, left, right } => Arc::new(FormulaTree::BinaryNode {
             op,
             left: rc_cache.get(&left).expect("Left not found").clone(),
 
@@ -288,7 +336,7 @@ impl LongSv {
             .collect();
         let popcount = sv.count_ones();
 
-        let mut h = FxHasher::default();
+        let mut h = CacheHasher::default();
         sv.hash(&mut h);
         let hash = h.finish();
 
@@ -0,0 +1,258 @@
+//! Incremental learning, when new labelled traces are added to a solved instance.
+//!
+//! A [`Session`] keeps the traces, the LTL cache and the formula of a [divide and conquer](super::divide_conquer)
+//! run. When new traces are added, the formulas of the cache are only evaluated on them.
+//! The formulas dropped by the enumeration as equivalent to a cached one are kept as well,
+//! and added back to the cache when the new traces distinguish them.
+//! If the current formula still classifies all the traces correctly, it is kept.
+//! Otherwise, a new formula is learnt, resuming the LTL enumeration from the extended cache.
+use std::mem;
+
+use crate::{
+    algos::{atoms, budget::Budget, BoolAlgoParams},
+    cache::EnumFormulaCache,
+    formula::tree::FormulaTree,
+    ltl::{
+        cache::LtlCache,
+        trace::{Operators, Trace},
+    },
+};
+
+use super::{divide_conquer_from, meta_res::MetaResult, MetaParams};
+
+/// Traces, LTL cache and formula of a solved instance, which can be updated with new traces.
+#[derive(Debug, Clone)]
+pub struct Session {
+    traces: Vec<Trace>,
+    alphabet: Vec<String>,
+    operators: Operators,
+    target: Vec<bool>,
+    ltl_cache: LtlCache,
+    formula: Option<FormulaTree>,
+}
+
+impl Session {
+    /// Learn a formula separating `traces` by [divide and conquer](super::divide_conquer),
+    /// keeping its LTL cache for [later updates](Session::add_traces).
+    pub fn learn<P>(
+        traces: Vec<Trace>,
+        alphabet: Vec<String>,
+        operators: Operators,
+        target: Vec<bool>,
+        meta_params: &MetaParams,
+        params: P,
+        budget: &Budget,
+    ) -> (Self, MetaResult<P::Data>)
+    where
+        P: BoolAlgoParams + Clone,
+    {
+        let atoms = atoms(&traces, alphabet.clone(), &operators.costs);
+        let mut ltl_cache = LtlCache::keeping_dropped(atoms);
+        ltl_cache.new_line(0);
+        ltl_cache.new_line(1);
+        let (res, ltl_cache) = divide_conquer_from(
+            &traces,
+            alphabet.clone(),
+            operators.clone(),
+            target.clone(),
+            Some(ltl_cache),
+            meta_params,
+            params,
+            budget,
+        );
+        let session = Session {
+            traces,
+            alphabet,
+            operators,
+            target,
+            ltl_cache,
+            formula: res.sol(),
+        };
+        (session, res)
+    }
+
+    /// Current formula, if one was found.
+    pub fn formula(&self) -> Option<&FormulaTree> {
+        self.formula.as_ref()
+    }
+
+    pub fn traces(&self) -> &[Trace] {
+        &self.traces
+    }
+
+    pub fn target(&self) -> &[bool] {
+        &self.target
+    }
+
+    /// Add `new_traces`, labelled by `new_target`, and update the formula.
+    ///
+    /// Returns `None` if the current formula still classifies all the traces correctly,
+    /// up to the errors allowed by `meta_params`, in which case it is kept.
+    /// Otherwise, returns the result of learning a new formula.
+    pub fn add_traces<P>(
+        &mut self,
+        new_traces: Vec<Trace>,
+        new_target: Vec<bool>,
+        meta_params: &MetaParams,
+        params: P,
+        budget: &Budget,
+    ) -> Option<MetaResult<P::Data>>
+    where
+        P: BoolAlgoParams + Clone,
+    {
+        assert_eq!(new_traces.len(), new_target.len());
+        let atoms = atoms(
+            &[&self.traces[..], &new_traces[..]].concat(),
+            self.alphabet.clone(),
+            &self.operators.costs,
+        );
+        self.ltl_cache = self.ltl_cache.extended(&new_traces, atoms, &self.operators);
+        self.traces.extend(new_traces);
+        self.target.extend(new_target);
+
+        let max_errors = meta_params.allowed_errors(self.target.len());
+        if (self.formula.as_ref())
+            .is_some_and(|f| f.classify(&self.traces, &self.target).errors() <= max_errors)
+        {
+            return None;
+        }
+
+        let ltl_cache = mem::replace(&mut self.ltl_cache, LtlCache::new(vec![]));
+        let (res, ltl_cache) = divide_conquer_from(
+            &self.traces,
+            self.alphabet.clone(),
+            self.operators.clone(),
+            self.target.clone(),
+            Some(ltl_cache),
+            meta_params,
+            params,
+            budget,
+        );
+        self.ltl_cache = ltl_cache;
+        self.formula = res.sol();
+        Some(res)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        algos::{meta::test::F_P_AND_G_Q, set_cover::SetCoverParams},
+        formula::rebuild_formula,
+        ltl::trace::parse_traces,
+    };
+
+    use super::*;
+
+    #[test]
+    fn updates_formula_with_new_traces() {
+        let (traces, alphabet, target, operators) = parse_traces(F_P_AND_G_Q).unwrap();
+        let meta_params = MetaParams {
+            max_size_ltl: 4,
+            domin_nb: 4,
+            ..Default::default()
+        };
+        let learn = |traces: &[Trace], target: &[bool]| {
+            Session::learn(
+                traces.to_vec(),
+                alphabet.clone(),
+                operators.clone(),
+                target.to_vec(),
+                &meta_params,
+                SetCoverParams::new(4),
+                &Budget::unlimited(),
+            )
+        };
+        let pick = |ids: &[usize]| {
+            let traces = ids.iter().map(|&i| traces[i].clone()).collect::<Vec<_>>();
+            let target = ids.iter().map(|&i| target[i]).collect::<Vec<_>>();
+            (traces, target)
+        };
+        let add = |session: &mut Session, ids: &[usize]| {
+            let (traces, target) = pick(ids);
+            let params = SetCoverParams::new(4);
+            session.add_traces(traces, target, &meta_params, params, &Budget::unlimited())
+        };
+
+        // The formulas of the cache are evaluated on all the traces.
+        let check_cache = |session: &Session| {
+            for g in session.ltl_cache.lines.iter().flatten() {
+                let g_tree = rebuild_formula(g, &session.ltl_cache);
+                let values = g_tree.eval(session.traces()).accepted_vec();
+                assert_eq!(g.accepted_vec(), values);
+            }
+        };
+
+        // The first trace of each class.
+        let (first_traces, first_target) = pick(&[0, 3]);
+        let (mut session, _) = learn(&first_traces, &first_target);
+        assert!(session.formula().is_some());
+
+        // Traces already classified correctly keep the formula.
+        assert!(add(&mut session, &[0, 3]).is_none());
+        assert_eq!(session.traces().len(), 4);
+        check_cache(&session);
+
+        let res = add(&mut session, &[1, 2, 4, 5, 6, 7]).unwrap();
+        let f = res.sol().unwrap();
+        assert_eq!(session.formula(), Some(&f));
+        assert_eq!(f.eval(session.traces()).accepted_vec(), session.target());
+
+        check_cache(&session);
+    }
+
+    #[test]
+    fn recovers_formulas_distinguished_by_new_traces() {
+        let buf = "1,1;1,1\n---\n1,0;1,0\n0,1;0,1\n---\nF,G,X,&,|,U\n---\np,q";
+        let (traces, alphabet, target, operators) = parse_traces(buf).unwrap();
+        let meta_params = MetaParams {
+            max_size_ltl: 4,
+            domin_nb: 4,
+            ..Default::default()
+        };
+        let budget = Budget::unlimited();
+        let (mut session, _) = Session::learn(
+            traces,
+            alphabet.clone(),
+            operators.clone(),
+            target,
+            &meta_params,
+            SetCoverParams::new(4),
+            &budget,
+        );
+
+        let buf = "1,0;0,0;0,1\n---\n0,0;0,0\n---\nF,G,X,&,|,U\n---\np,q";
+        let (new_traces, _, new_target, _) = parse_traces(buf).unwrap();
+        let res = session
+            .add_traces(
+                new_traces,
+                new_target,
+                &meta_params,
+                SetCoverParams::new(4),
+                &budget,
+            )
+            .unwrap();
+
+        // Same cost as learning from scratch, e.g. `(p) & (F (q))`,
+        // and as many formulas of each cost in the cache.
+        let (scratch, scratch_cache) = divide_conquer_from(
+            session.traces(),
+            alphabet,
+            operators,
+            session.target().to_vec(),
+            None,
+            &meta_params,
+            SetCoverParams::new(4),
+            &budget,
+        );
+        assert_eq!(scratch.cost(), Some(4));
+        assert_eq!(res.cost(), scratch.cost());
+        let line_lens = |c: &LtlCache| c.lines.iter().map(|l| l.len()).collect::<Vec<_>>();
+        let nb_lines = (session.ltl_cache.lines.len()).min(scratch_cache.lines.len());
+        assert!(nb_lines > 2);
+        assert_eq!(
+            line_lens(&session.ltl_cache)[..nb_lines],
+            line_lens(&scratch_cache)[..nb_lines]
+        );
+    }
+}
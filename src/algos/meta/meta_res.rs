@@ -1,7 +1,10 @@
 //! Result types for meta-algorithms
 use std::time::Duration;
 
-use crate::formula::tree::FormulaTree;
+use crate::{
+    algos::budget::Exhausted,
+    formula::tree::{Classification, FormulaTree},
+};
 
 /// Result of a meta-algorithm,
 /// with enumeration and running time data.
@@ -10,7 +13,15 @@ pub struct MetaResult<D> {
     pub(crate) ltl_time: Duration,
     pub ltl_cache_sizes: Vec<usize>,
     pub(crate) algo_time: Option<Duration>,
-    pub algo_data: Option<D>,
+    /// Data returned by each run of the Boolean algorithm, in order: divide and conquer
+    /// runs it again on the subproblems when it finds no formula.
+    pub algo_data: Vec<D>,
+    /// Cost removed from the solution by the minimisation.
+    pub(crate) cost_saved: usize,
+    /// Cost of the solution in the cost model of the operators.
+    pub(crate) cost: Option<usize>,
+    /// Classification of the traces by the solution.
+    pub(crate) classification: Option<Classification>,
     pub(crate) result: MetaRes,
 }
 
@@ -22,6 +33,36 @@ impl<D> MetaResult<D> {
     pub fn sol(&self) -> Option<FormulaTree> {
         self.result.get()
     }
+
+    pub fn cost(&self) -> Option<usize> {
+        self.cost
+    }
+
+    pub fn cost_saved(&self) -> usize {
+        self.cost_saved
+    }
+
+    /// Classification of the traces by the solution, if there is one.
+    ///
+    /// When [errors are allowed](crate::algos::meta::MetaParams::allowed_errors),
+    /// its misclassified traces are the ones sacrificed to find a smaller formula.
+    pub fn classification(&self) -> Option<&Classification> {
+        self.classification.as_ref()
+    }
+
+    /// Reason for which the run was stopped early, if it was.
+    pub fn exhausted(&self) -> Option<Exhausted> {
+        match self.result {
+            MetaRes::TimedOut(_) => Some(Exhausted::Time),
+            MetaRes::OutOfMemory(_) => Some(Exhausted::Memory),
+            _ => None,
+        }
+    }
+
+    /// Most accurate formula found before the run was stopped early, if it was.
+    pub fn partial(&self) -> Option<FormulaTree> {
+        self.result.partial()
+    }
 }
 
 /// Whether a result was found, and which part of
@@ -32,6 +73,10 @@ pub enum MetaRes {
     Atom(FormulaTree),
     FoundByLtl(FormulaTree),
     FoundByBool(FormulaTree),
+    /// The time budget was exhausted, with the most accurate formula found so far.
+    TimedOut(Option<FormulaTree>),
+    /// The memory budget was exhausted, with the most accurate formula found so far.
+    OutOfMemory(Option<FormulaTree>),
 }
 
 impl MetaRes {
@@ -41,6 +86,21 @@ impl MetaRes {
             MetaRes::Atom(f) => Some(f.clone()),
             MetaRes::FoundByLtl(f) => Some(f.clone()),
             MetaRes::FoundByBool(f) => Some(f.clone()),
+            MetaRes::TimedOut(_) | MetaRes::OutOfMemory(_) => None,
+        }
+    }
+
+    pub(crate) fn exhausted(reason: Exhausted, partial: Option<FormulaTree>) -> Self {
+        match reason {
+            Exhausted::Time => MetaRes::TimedOut(partial),
+            Exhausted::Memory => MetaRes::OutOfMemory(partial),
+        }
+    }
+
+    pub fn partial(&self) -> Option<FormulaTree> {
+        match self {
+            MetaRes::TimedOut(f) | MetaRes::OutOfMemory(f) => f.clone(),
+            _ => None,
         }
     }
 }
@@ -0,0 +1,256 @@
+//! Post-hoc minimisation of the formulas found by divide and conquer.
+//!
+//! Divide and conquer glues the solutions of subproblems with `And` and `Or`,
+//! which often yields redundant subformulas.
+//! The minimisation works on the Boolean skeleton of the formula: the subformulas
+//! only reached through `And`, `Or` and `Not`. Each of them determines the value of the
+//! formula on some of the traces, called its relevant traces, the other children of
+//! the `And` and `Or` above it being fixed.
+//! A subformula can then be replaced by:
+//! - one of its children, if it is an `And` or an `Or`,
+//! - a cheaper formula of the cache which agrees with it on its relevant traces.
+//!
+//! Each replacement is checked by evaluating the new formula on all traces.
+use std::sync::Arc;
+
+use itertools::Itertools;
+use log::{debug, info};
+
+use crate::{
+    formula::{cost::CostModel, tree::FormulaTree},
+    ltl::trace::Trace,
+    ops::{binary::LtlBinaryOp, unary::LtlUnaryOp},
+};
+
+use super::cache::InitialBoolCache;
+
+/// Child of a node of a [`FormulaTree`].
+#[derive(Debug, Clone, Copy)]
+enum Child {
+    Unary,
+    Left,
+    Right,
+}
+
+/// Subformula of the Boolean skeleton, with the traces on which it determines the formula.
+struct Skeleton<'f> {
+    path: Vec<Child>,
+    node: &'f FormulaTree,
+    relevant: Vec<bool>,
+}
+
+/// Minimise `f`, a formula separating `traces` according to `target` up to `max_errors`
+/// misclassified traces, using the formulas of `cache` as replacements.
+///
+/// The minimised formula misclassifies at most `max_errors` traces, possibly other ones than `f`.
+///
+/// Returns the minimised formula and the cost saved, in the cost model `costs`.
+pub(crate) fn minimise(
+    f: FormulaTree,
+    traces: &[Trace],
+    target: &[bool],
+    max_errors: usize,
+    cache: &InitialBoolCache,
+    costs: &CostModel,
+) -> (FormulaTree, usize) {
+    let candidates = cache
+        .iter_all()
+        .into_iter()
+        .sorted_by_key(|(_, _, cost)| *cost)
+        .collect_vec();
+
+    let initial_cost = f.cost(costs);
+    let mut f = f;
+    let mut cost = initial_cost;
+    'improve: loop {
+        for s in skeleton(&f, traces) {
+            let s_cost = s.node.cost(costs);
+            let cv = s.node.eval(traces).accepted_vec();
+            let agrees = |other: &[bool]| {
+                (cv.iter().zip(other).zip(&s.relevant)).all(|((a, b), &r)| !r || a == b)
+            };
+
+            let children = match s.node {
+                FormulaTree::BinaryNode {
+                    op: LtlBinaryOp::And | LtlBinaryOp::Or,
+                    left,
+                    right,
+                } => vec![left.as_ref().clone(), right.as_ref().clone()],
+                _ => vec![],
+            };
+            let cached = candidates
+                .iter()
+                .take_while(|(_, _, c)| *c < s_cost)
+                .find(|(other, _, _)| agrees(other))
+                .map(|(_, g, _)| g.as_ref().clone());
+
+            for g in children.into_iter().chain(cached) {
+                let new_f = replace(&f, &s.path, g);
+                let new_cost = new_f.cost(costs);
+                if new_cost < cost && new_f.classify(traces, target).errors() <= max_errors {
+                    debug!("Minimisation: {new_f} (cost {new_cost})");
+                    f = new_f;
+                    cost = new_cost;
+                    continue 'improve;
+                }
+            }
+        }
+        break;
+    }
+
+    let saved = initial_cost - cost;
+    info!("Minimisation saved a cost of {saved}");
+    (f, saved)
+}
+
+/// Subformulas of the Boolean skeleton of `f`, parents first.
+fn skeleton<'f>(f: &'f FormulaTree, traces: &[Trace]) -> Vec<Skeleton<'f>> {
+    let mut res = vec![];
+    let mut stack = vec![Skeleton {
+        path: vec![],
+        node: f,
+        relevant: vec![true; traces.len()],
+    }];
+    while let Some(s) = stack.pop() {
+        match s.node {
+            FormulaTree::UnaryNode {
+                op: LtlUnaryOp::Not,
+                child,
+            } => stack.push(Skeleton {
+                path: [&s.path[..], &[Child::Unary]].concat(),
+                node: child,
+                relevant: s.relevant.clone(),
+            }),
+            FormulaTree::BinaryNode {
+                op: op @ (LtlBinaryOp::And | LtlBinaryOp::Or),
+                left,
+                right,
+            } => {
+                // A child of an `And` determines its value when the other child is true,
+                // and a child of an `Or` when the other child is false.
+                let determines = |other: &FormulaTree| {
+                    let cv = other.eval(traces).accepted_vec();
+                    (s.relevant.iter().zip(cv))
+                        .map(|(&r, b)| r && (b == (*op == LtlBinaryOp::And)))
+                        .collect_vec()
+                };
+                stack.push(Skeleton {
+                    path: [&s.path[..], &[Child::Right]].concat(),
+                    node: right,
+                    relevant: determines(left),
+                });
+                stack.push(Skeleton {
+                    path: [&s.path[..], &[Child::Left]].concat(),
+                    node: left,
+                    relevant: determines(right),
+                });
+            }
+            _ => (),
+        }
+        res.push(s);
+    }
+    res
+}
+
+/// Replace the subformula of `f` at `path` by `g`.
+fn replace(f: &FormulaTree, path: &[Child], g: FormulaTree) -> FormulaTree {
+    let Some((&child, path)) = path.split_first() else {
+        return g;
+    };
+    match (f, child) {
+        (FormulaTree::UnaryNode { op, child }, Child::Unary) => FormulaTree::UnaryNode {
+            op: *op,
+            child: Arc::new(replace(child, path, g)),
+        },
+        (FormulaTree::BinaryNode { op, left, right }, Child::Left) => FormulaTree::BinaryNode {
+            op: *op,
+            left: Arc::new(replace(left, path, g)),
+            right: right.clone(),
+        },
+        (FormulaTree::BinaryNode { op, left, right }, Child::Right) => FormulaTree::BinaryNode {
+            op: *op,
+            left: left.clone(),
+            right: Arc::new(replace(right, path, g)),
+        },
+        _ => unreachable!("Invalid path in formula"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        algos::{
+            atoms, budget::Budget, create_initial_cache, enumeration::aux::enum_aux,
+            meta::test::F_P_AND_G_Q,
+        },
+        formula::parser::parse_formula,
+        ltl::trace::parse_traces,
+    };
+
+    use super::*;
+
+    #[test]
+    fn drops_and_replaces_subformulas() {
+        let (traces, alphabet, target, operators) = parse_traces(F_P_AND_G_Q).unwrap();
+        let (_, mut ltl_cache) = create_initial_cache(
+            atoms(&traces, alphabet.clone(), &operators.costs),
+            &target,
+            0,
+        );
+        enum_aux(
+            &mut ltl_cache,
+            &operators,
+            &target,
+            0,
+            2,
+            1,
+            &Budget::unlimited(),
+        )
+        .unwrap();
+        let cache = InitialBoolCache::from_ltl_cache(4, &ltl_cache, &target);
+
+        let expected = parse_formula("F p & G q", &alphabet).unwrap();
+        for (f, saved) in [
+            // Redundant disjunct
+            ("(F p & G q) | (F p & G q & X q)", 9),
+            // Redundant conjunct
+            ("(F p & G q) & G q", 3),
+            // Subformula equivalent to a cheaper one of the cache
+            ("F F p & G q", 1),
+        ] {
+            let f = parse_formula(f, &alphabet).unwrap();
+            assert_eq!(f.eval(&traces).accepted_vec(), target);
+            let res = minimise(f, &traces, &target, 0, &cache, &operators.costs);
+            assert_eq!(res, (expected.clone(), saved));
+        }
+    }
+
+    #[test]
+    fn reports_cost_saved() {
+        let (traces, alphabet, target, operators) = parse_traces(F_P_AND_G_Q).unwrap();
+        let operators = operators.with_weights(["F=3".parse().unwrap()]);
+        let (_, mut ltl_cache) = create_initial_cache(
+            atoms(&traces, alphabet.clone(), &operators.costs),
+            &target,
+            0,
+        );
+        enum_aux(
+            &mut ltl_cache,
+            &operators,
+            &target,
+            0,
+            4,
+            1,
+            &Budget::unlimited(),
+        )
+        .unwrap();
+        let cache = InitialBoolCache::from_ltl_cache(4, &ltl_cache, &target);
+
+        // `F p` is replaced by the cheaper `!(G !p)`, which has more nodes.
+        let f = parse_formula("F p & G q", &alphabet).unwrap();
+        let (res, saved) = minimise(f.clone(), &traces, &target, 0, &cache, &operators.costs);
+        assert!(res.size() > f.size());
+        assert_eq!(res.cost(&operators.costs), 6);
+        assert_eq!(saved, 1);
+    }
+}
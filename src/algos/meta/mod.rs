@@ -1,142 +1,494 @@
 //! Meta algorithms: Divide and Conquer, ...
-use std::{rc::Rc, time::Instant};
+use std::{
+    cmp::Reverse,
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant},
+};
 
 use cache::InitialBoolCache;
+use clap::{builder::RangedU64ValueParser, Args};
 use itertools::Itertools;
-use log::{debug, info, trace};
+use log::{debug, info, trace, warn};
 use meta_res::{MetaRes, MetaResult};
+use minimise::minimise;
 
 use crate::{
-    algos::{atoms, create_initial_cache, enumeration::aux::enum_aux},
+    algos::{
+        atoms,
+        budget::{Budget, Exhausted},
+        create_initial_cache,
+        enumeration::aux::enum_aux,
+    },
     cache::FormulaCache,
     formula::{rebuild_formula, tree::FormulaTree},
-    ltl::trace::{Operators, Trace},
+    ltl::{
+        cache::LtlCache,
+        persist::{self, cache_key, CacheFileError},
+        trace::{Operators, Trace},
+        LtlFormula,
+    },
     ops::binary::LtlBinaryOp,
+    traits::EqTarget,
 };
 
 use super::BoolAlgoParams;
 
+pub mod anytime;
 pub mod cache;
+pub mod incremental;
 pub mod meta_res;
+mod minimise;
+
+/// Hyperparameters of the [divide and conquer](divide_conquer) meta-algorithm.
+#[derive(Args, Clone, Debug)]
+pub struct MetaParams {
+    /// Run LTL enumeration until formulas of cost `max_size_ltl`
+    /// before switching to boolean algorithm.
+    pub max_size_ltl: usize,
+    /// Number of candidates to use for domination checking
+    /// in the step that converts LTL formulas to boolean formulas.
+    pub domin_nb: usize,
+    /// Split boolean instances with more traces than this
+    /// before running the boolean algorithm on them.
+    #[arg(long, default_value_t = 128)]
+    pub split_threshold: usize,
+    /// Number of threads generating the candidates of the LTL enumeration.
+    ///
+    /// The result does not depend on the number of threads.
+    #[arg(long, default_value_t = 1, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    pub threads: usize,
+    /// Do not minimise the formulas found by divide and conquer.
+    #[arg(long)]
+    pub no_minimise: bool,
+    /// Accept formulas misclassifying at most this many traces.
+    #[arg(long, value_name = "K", conflicts_with = "max_error_rate")]
+    pub max_errors: Option<usize>,
+    /// Accept formulas misclassifying at most this proportion of the traces, between 0 and 1.
+    #[arg(long, value_name = "RATE", value_parser = parse_rate)]
+    pub max_error_rate: Option<f64>,
+    /// Resume the LTL enumeration from this cache file, and save the enumerated formulas to it.
+    ///
+    /// The file is ignored if it was computed for other traces or operators.
+    #[arg(long, value_name = "FILE")]
+    pub ltl_cache: Option<PathBuf>,
+}
+
+impl MetaParams {
+    /// Number of misclassified traces accepted among `nb_traces` traces,
+    /// 0 unless `max_errors` or `max_error_rate` is set.
+    ///
+    /// Allowing a few errors avoids overfitting mislabelled traces.
+    pub fn allowed_errors(&self, nb_traces: usize) -> usize {
+        match (self.max_errors, self.max_error_rate) {
+            (Some(k), _) => k,
+            (None, Some(rate)) => (rate * nb_traces as f64).floor() as usize,
+            (None, None) => 0,
+        }
+    }
+}
+
+/// The defaults of the command line flags, without LTL enumeration
+/// (`max_size_ltl` and `domin_nb` are 0).
+impl Default for MetaParams {
+    fn default() -> Self {
+        MetaParams {
+            max_size_ltl: 0,
+            domin_nb: 0,
+            split_threshold: 128,
+            threads: 1,
+            no_minimise: false,
+            max_errors: None,
+            max_error_rate: None,
+            ltl_cache: None,
+        }
+    }
+}
+
+fn parse_rate(s: &str) -> Result<f64, String> {
+    let rate: f64 = s.parse().map_err(|e| format!("{e}"))?;
+    if (0. ..=1.).contains(&rate) {
+        Ok(rate)
+    } else {
+        Err("the rate must be between 0 and 1".to_owned())
+    }
+}
 
 /// LTL search followed by Divide and Conquer.
+///
+/// Formulas misclassifying at most the [number of traces allowed](MetaParams::allowed_errors)
+/// by `meta_params` are accepted, and the result reports the misclassified traces.
+///
+/// If `budget` is exhausted, returns the reason of the exhaustion along with the most accurate
+/// formula of the LTL cache, or of the initial Boolean cache if the Boolean search was stopped.
+/// The subproblems already solved by the splits of divide and conquer are not combined
+/// into this partial result, which may thus be less accurate than their combination.
 pub fn divide_conquer<P>(
     traces: &[Trace],
     alphabet: Vec<String>,
     operators: Operators,
     target: Vec<bool>,
-    max_size_ltl: usize,
-    domin_nb: usize,
+    meta_params: &MetaParams,
     params: P,
+    budget: &Budget,
 ) -> MetaResult<P::Data>
+where
+    P: BoolAlgoParams + Clone,
+{
+    let Some(path) = meta_params.ltl_cache.as_deref() else {
+        return divide_conquer_from(
+            traces,
+            alphabet,
+            operators,
+            target,
+            None,
+            meta_params,
+            params,
+            budget,
+        )
+        .0;
+    };
+
+    let key = cache_key(traces, &alphabet, &operators);
+    let atoms = atoms(traces, alphabet.clone(), &operators.costs);
+    let loaded = load_ltl_cache(path, key, atoms);
+    let loaded_lines = loaded.as_ref().map_or(0, |c| c.lines.len());
+    let (res, ltl_cache) = divide_conquer_from(
+        traces,
+        alphabet,
+        operators,
+        target,
+        loaded,
+        meta_params,
+        params,
+        budget,
+    );
+    if ltl_cache.lines.len() > loaded_lines {
+        save_ltl_cache(path, &ltl_cache, key, ltl_cache.lines.len());
+    }
+    res
+}
+
+/// [Divide and conquer](divide_conquer), resuming the LTL enumeration from `ltl_cache` if it is set.
+///
+/// `ltl_cache` must come from an enumeration on the same traces, alphabet and operators.
+/// Its formulas are compared to the target, and those more expensive than the cheapest
+/// separating atom are dropped.
+///
+/// Also returns the LTL cache, without its last line if the enumeration stopped before
+/// completing it, so that the enumeration can be resumed from it again.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn divide_conquer_from<P>(
+    traces: &[Trace],
+    alphabet: Vec<String>,
+    operators: Operators,
+    target: Vec<bool>,
+    ltl_cache: Option<LtlCache>,
+    meta_params: &MetaParams,
+    params: P,
+    budget: &Budget,
+) -> (MetaResult<P::Data>, LtlCache)
 where
     P: BoolAlgoParams + Clone,
 {
     let start = Instant::now();
 
-    let atoms = atoms(traces, alphabet);
+    let max_errors = meta_params.allowed_errors(target.len());
+    let atoms = atoms(traces, alphabet, &operators.costs);
     // Add initial formulas
-    let (atom, mut ltl_cache) = create_initial_cache(atoms, &target);
-    // Check if target is an atom
-    if let Some(f) = atom {
-        let ltl_time = start.elapsed();
-        let f_str = rebuild_formula(&f, &ltl_cache);
+    let (atom, initial_cache) = create_initial_cache(atoms, &target, max_errors);
+    // If the target is an atom, only look for cheaper formulas
+    let max_size_ltl = match &atom {
+        Some(f) => meta_params.max_size_ltl.min(f.cost - 1),
+        None => meta_params.max_size_ltl,
+    };
 
+    let (mut ltl_cache, cached) = match ltl_cache {
+        Some(mut ltl_cache) => {
+            ltl_cache.truncate(max_size_ltl + 1);
+            let cached = (ltl_cache.lines.iter().flatten())
+                .find(|f| f.eq_target(&target, max_errors))
+                .cloned();
+            (ltl_cache, cached)
+        }
+        None => (initial_cache, None),
+    };
+
+    // Ltl search
+    let ltl_res = match cached {
+        Some(f) => Ok(Some(f)),
+        None => enum_aux(
+            &mut ltl_cache,
+            &operators,
+            &target,
+            max_errors,
+            max_size_ltl,
+            meta_params.threads,
+            budget,
+        ),
+    };
+    let ltl_time = start.elapsed();
+
+    // The last line is incomplete if the enumeration stopped early
+    let complete_lines = match ltl_res {
+        Ok(None) => ltl_cache.lines.len(),
+        _ => ltl_cache.lines.len() - 1,
+    };
+    let res = conquer(
+        traces,
+        operators,
+        &target,
+        max_errors,
+        atom,
+        ltl_res,
+        &ltl_cache,
+        ltl_time,
+        meta_params,
+        params,
+        budget,
+    );
+    ltl_cache.truncate(complete_lines);
+    (res, ltl_cache)
+}
+
+/// Result of the LTL search `ltl_res` on `ltl_cache`, completed by divide and conquer if needed.
+#[allow(clippy::too_many_arguments)]
+fn conquer<P>(
+    traces: &[Trace],
+    operators: Operators,
+    target: &[bool],
+    max_errors: usize,
+    atom: Option<LtlFormula>,
+    ltl_res: Result<Option<LtlFormula>, Exhausted>,
+    ltl_cache: &LtlCache,
+    ltl_time: Duration,
+    meta_params: &MetaParams,
+    params: P,
+    budget: &Budget,
+) -> MetaResult<P::Data>
+where
+    P: BoolAlgoParams + Clone,
+{
+    let costs = operators.costs.clone();
+    let ltl_cache_sizes = ltl_cache.lines.iter().map(|l| l.len()).collect();
+    if let Ok(Some(f)) = ltl_res {
+        let f_str = rebuild_formula(&f, ltl_cache);
+        return MetaResult {
+            ltl_time,
+            ltl_cache_sizes,
+            algo_time: None,
+            algo_data: vec![],
+            cost_saved: 0,
+            cost: Some(f.cost),
+            classification: Some(f_str.classify(traces, target)),
+            result: MetaRes::FoundByLtl(f_str),
+        };
+    }
+    if let Some(f) = atom {
+        let f_str = rebuild_formula(&f, ltl_cache);
         return MetaResult {
             ltl_time,
-            ltl_cache_sizes: vec![],
+            ltl_cache_sizes,
             algo_time: None,
-            algo_data: None,
+            algo_data: vec![],
+            cost_saved: 0,
+            cost: Some(f.cost),
+            classification: Some(f_str.classify(traces, target)),
             result: MetaRes::Atom(f_str),
         };
     }
-
-    // Ltl search
-    let ltl_res = enum_aux(&mut ltl_cache, &operators, &target, max_size_ltl);
-
-    let ltl_time = start.elapsed();
-    let ltl_cache_sizes = ltl_cache.lines.iter().map(|l| l.len()).collect();
-    if let Some(f) = ltl_res {
-        let f_str = rebuild_formula(&f, &ltl_cache);
+    if let Err(e) = ltl_res {
+        info!("LTL search stopped: {e}");
         return MetaResult {
             ltl_time,
             ltl_cache_sizes,
             algo_time: None,
-            algo_data: None,
-            result: MetaRes::FoundByLtl(f_str),
+            algo_data: vec![],
+            cost_saved: 0,
+            cost: None,
+            classification: None,
+            result: MetaRes::exhausted(e, most_accurate_ltl(ltl_cache, target)),
         };
     }
     debug!("Ltl cache has size {}", ltl_cache.len());
 
     debug!("Running D&C with algo {}", P::name());
     let start = Instant::now();
-    let initial_cache = InitialBoolCache::from_ltl_cache(domin_nb, ltl_cache, &target);
+    let initial_cache = InitialBoolCache::from_ltl_cache(meta_params.domin_nb, ltl_cache, target);
     debug!("Initial bool cache len: {}", initial_cache.len());
-    let f = solve_or_split(traces, operators, initial_cache, &target, params);
+    let mut algo_data = vec![];
+    let f = solve_or_split(
+        traces,
+        operators,
+        &initial_cache,
+        target,
+        max_errors,
+        params,
+        meta_params.split_threshold,
+        budget,
+        &mut algo_data,
+    );
+    let mut cost_saved = 0;
+    let f = f.map(|f| {
+        f.map(|f| {
+            if meta_params.no_minimise {
+                return f;
+            }
+            let (f, saved) = minimise(f, traces, target, max_errors, &initial_cache, &costs);
+            cost_saved = saved;
+            f
+        })
+    });
     let algo_time = Some(start.elapsed());
+    let sol = f.as_ref().ok().and_then(|f| f.as_ref());
 
     MetaResult {
         ltl_time,
         ltl_cache_sizes,
         algo_time,
-        algo_data: None,
+        algo_data,
+        cost_saved,
+        cost: sol.map(|f| f.cost(&costs)),
+        classification: sol.map(|f| f.classify(traces, target)),
         result: match f {
-            Some(f) => MetaRes::FoundByBool(f),
-            None => MetaRes::NotFound,
+            Ok(Some(f)) => MetaRes::FoundByBool(f),
+            Ok(None) => MetaRes::NotFound,
+            Err(e) => {
+                info!("Boolean search stopped: {e}");
+                MetaRes::exhausted(e, initial_cache.most_accurate(target))
+            }
         },
     }
 }
 
+/// Load the LTL cache saved at `path`,
+/// if the file exists and was computed for the traces and operators identified by `key`.
+fn load_ltl_cache(path: &Path, key: u64, atoms: Vec<LtlFormula>) -> Option<LtlCache> {
+    match persist::load_file(path, key, atoms) {
+        Ok(cache) => {
+            info!(
+                "Loaded {} lines of LTL cache from {}",
+                cache.lines.len(),
+                path.display()
+            );
+            Some(cache)
+        }
+        Err(CacheFileError::Io(e)) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => {
+            warn!("Ignoring LTL cache file {}: {e}", path.display());
+            None
+        }
+    }
+}
+
+/// Save the first `nb_lines` lines of the LTL cache to `path`.
+fn save_ltl_cache(path: &Path, cache: &LtlCache, key: u64, nb_lines: usize) {
+    match persist::save_file(path, cache, key, nb_lines) {
+        Ok(()) => info!("Saved {nb_lines} lines of LTL cache to {}", path.display()),
+        Err(e) => warn!("Cannot save LTL cache to {}: {e}", path.display()),
+    }
+}
+
+/// Formula of the cache classifying the most traces correctly, the cheapest one in case of ties.
+fn most_accurate_ltl(cache: &LtlCache, target: &[bool]) -> Option<FormulaTree> {
+    let f = cache.lines.iter().flatten().max_by_key(|f| {
+        let accuracy = (f.accepted_vec().iter().zip(target))
+            .filter(|(b, t)| b == t)
+            .count();
+        (accuracy, Reverse(f.cost))
+    })?;
+    Some(rebuild_formula(f, cache))
+}
+
 /// Solve Boolean Synthesis problem using Divide and Conquer and the algorithm specified in `params`.
 ///
-/// If the number of traces is more than 128 split immediately.
+/// If the number of traces is more than `split_threshold` split immediately.
 /// Otherwise, try to solve the instance with the algorithm implemented by `params`.
 /// If no solution is found, try to find one by splitting recursively.
 /// Splitting is handled using [`split_and_solve_non_overlapping`].
+///
+/// The solution misclassifies at most `max_errors` traces.
+/// The data of each run of the algorithm is appended to `algo_data`, in order.
+#[allow(clippy::too_many_arguments)]
 fn solve_or_split<P>(
     traces: &[Trace],
     operators: Operators,
-    initial_cache: InitialBoolCache,
+    initial_cache: &InitialBoolCache,
     target: &[bool],
+    max_errors: usize,
     params: P,
-) -> Option<FormulaTree>
+    split_threshold: usize,
+    budget: &Budget,
+    algo_data: &mut Vec<P::Data>,
+) -> Result<Option<FormulaTree>, Exhausted>
 where
     P: BoolAlgoParams + Clone,
 {
     let nb_traces = target.len();
     // Check whether the fom
-    if let Some(f) = initial_cache.get_from_cv(target, target) {
+    if let Some(f) = initial_cache.get_within(target, max_errors) {
         debug!("Formula found in cache");
-        return Some(f);
+        return Ok(Some(f));
     }
-    if nb_traces > 128 {
-        split_and_solve_non_overlapping(traces, operators, initial_cache, target, params)
+    if nb_traces > split_threshold {
+        split_and_solve_non_overlapping(
+            traces,
+            operators,
+            initial_cache,
+            target,
+            max_errors,
+            params,
+            split_threshold,
+            budget,
+            algo_data,
+        )
     } else {
-        let (res, _) = params
-            .clone()
-            .run(initial_cache.clone(), operators.clone(), target);
-        match res {
-            Some(f) => Some(f),
-            None => {
-                split_and_solve_non_overlapping(traces, operators, initial_cache, target, params)
-            }
+        let (res, data) = params.clone().run(
+            initial_cache.clone(),
+            operators.clone(),
+            target,
+            max_errors,
+            budget,
+        );
+        algo_data.push(data);
+        match res? {
+            Some(f) => Ok(Some(f)),
+            None => split_and_solve_non_overlapping(
+                traces,
+                operators,
+                initial_cache,
+                target,
+                max_errors,
+                params,
+                split_threshold,
+                budget,
+                algo_data,
+            ),
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn _split_and_solve<P>(
     traces: &[Trace],
     operators: Operators,
-    cache: InitialBoolCache,
+    cache: &InitialBoolCache,
     target: &[bool],
+    max_errors: usize,
     params: P,
-) -> Option<FormulaTree>
+    split_threshold: usize,
+    budget: &Budget,
+    algo_data: &mut Vec<P::Data>,
+) -> Result<Option<FormulaTree>, Exhausted>
 where
     P: BoolAlgoParams + Clone,
 {
-    let (op, left, right) = find_split(target)?;
+    let Some((op, left, right)) = find_split(target) else {
+        return Ok(None);
+    };
     info!("Splitting on op '{op}'");
-    let (left_cache, right_cache) = cache.split(&left, &right, target);
+    let (left_cache, right_cache) = cache.clone().split(&left, &right, target);
     info!(
         "Cache sizes: {} (left) {} right",
         left_cache.len(),
@@ -145,23 +497,45 @@ where
 
     let left_target = left.iter().map(|&i| target[i]).collect_vec();
     let left_traces = left.iter().map(|&i| traces[i].clone()).collect_vec();
-    let left_res = solve_or_split(
+    let Some(left_res) = solve_or_split(
         &left_traces,
         operators.clone(),
-        left_cache,
+        &left_cache,
         &left_target,
+        max_errors,
         params.clone(),
-    )?;
+        split_threshold,
+        budget,
+        algo_data,
+    )?
+    else {
+        return Ok(None);
+    };
+    // The errors of both sides add up in the merged formula.
+    let left_errors = left_res.classify(&left_traces, &left_target).errors();
 
     let right_target = right.iter().map(|&i| target[i]).collect_vec();
     let right_traces = right.iter().map(|&i| traces[i].clone()).collect_vec();
-    let right_res = solve_or_split(&right_traces, operators, right_cache, &right_target, params)?;
+    let Some(right_res) = solve_or_split(
+        &right_traces,
+        operators,
+        &right_cache,
+        &right_target,
+        max_errors.saturating_sub(left_errors),
+        params,
+        split_threshold,
+        budget,
+        algo_data,
+    )?
+    else {
+        return Ok(None);
+    };
 
-    Some(FormulaTree::BinaryNode {
+    Ok(Some(FormulaTree::BinaryNode {
         op,
-        left: Rc::from(left_res),
-        right: Rc::from(right_res),
-    })
+        left: Arc::from(left_res),
+        right: Arc::from(right_res),
+    }))
 }
 
 /// Divide and conquer subrouting to split into two subproblems with clever merging.
@@ -170,17 +544,27 @@ where
 /// If we get a solution, use the set of unsatisfied indices for the right subproblem,
 /// instead of all the other indices.
 /// As the left result might solve traces that were not included in the call, this yields much smaller formulas.
+///
+/// The traces misclassified by the left result that the merge cannot fix are deducted
+/// from the `max_errors` of the right subproblem.
+#[allow(clippy::too_many_arguments)]
 fn split_and_solve_non_overlapping<P>(
     traces: &[Trace],
     operators: Operators,
-    cache: InitialBoolCache,
+    cache: &InitialBoolCache,
     target: &[bool],
+    max_errors: usize,
     params: P,
-) -> Option<FormulaTree>
+    split_threshold: usize,
+    budget: &Budget,
+    algo_data: &mut Vec<P::Data>,
+) -> Result<Option<FormulaTree>, Exhausted>
 where
     P: BoolAlgoParams + Clone,
 {
-    let (op, left, _) = find_split(target)?;
+    let Some((op, left, _)) = find_split(target) else {
+        return Ok(None);
+    };
     info!("Splitting on op '{op}'");
 
     let left_cache = cache.reduce(&left, target);
@@ -188,33 +572,52 @@ where
 
     let left_target = left.iter().map(|&i| target[i]).collect_vec();
     let left_traces = left.iter().map(|&i| traces[i].clone()).collect_vec();
-    let left_res = solve_or_split(
+    let Some(left_res) = solve_or_split(
         &left_traces,
         operators.clone(),
-        left_cache,
+        &left_cache,
         &left_target,
+        max_errors,
         params.clone(),
-    )?;
+        split_threshold,
+        budget,
+        algo_data,
+    )?
+    else {
+        return Ok(None);
+    };
     debug!("Found left formula {}", left_res);
 
     // Compute the indices of the traces that are not satisfied by the left result,
     // and only recurse on these.
     let solved = left_res.eval(traces).accepted_vec();
     let right = solved
-        .into_iter()
-        .zip(target.iter())
+        .iter()
         .enumerate()
-        .filter_map(|(i, (b1, &b2))| match op {
+        .filter_map(|(i, &b1)| match op {
             // When splitting on a Or, we need to keep all negatives
             // and unsat positives, i.e. those for which `cv` is false.
-            LtlBinaryOp::Or if !b2 | !b1 => Some(i),
+            // The negatives for which `cv` is true stay misclassified after the merge anyway.
+            LtlBinaryOp::Or if !b1 => Some(i),
             // When splitting on an And, we instead keep all positives
             // and unsat negatives, i.e. those for which `cv` is true.
-            LtlBinaryOp::And if b2 | b1 => Some(i),
+            // The positives for which `cv` is false stay misclassified after the merge anyway.
+            LtlBinaryOp::And if b1 => Some(i),
             _ => None,
         })
         .collect_vec();
 
+    // All the traces of the kind that was not split are given to the left subproblem,
+    // so that there are at most `max_errors` of these.
+    let left_errors = solved
+        .iter()
+        .zip(target)
+        .filter(|&(&b1, &b2)| match op {
+            LtlBinaryOp::Or => b1 && !b2,
+            LtlBinaryOp::And => !b1 && b2,
+            _ => unreachable!(),
+        })
+        .count();
     let nb_not_sat = right
         .iter()
         .filter(|&&i| match op {
@@ -223,9 +626,9 @@ where
             _ => unreachable!(),
         })
         .count();
-    if nb_not_sat == 0 {
-        debug!("0 left to satisfy, shortcut return");
-        return Some(left_res);
+    if left_errors + nb_not_sat <= max_errors {
+        debug!("{nb_not_sat} left to satisfy, shortcut return");
+        return Ok(Some(left_res));
     }
 
     debug!("Number of unsat after left call: {}", right.len());
@@ -234,17 +637,30 @@ where
     let right_cache = cache.reduce(&right, target);
     let right_target = right.iter().map(|&i| target[i]).collect_vec();
     let right_traces = right.iter().map(|&i| traces[i].clone()).collect_vec();
-    let right_res = solve_or_split(&right_traces, operators, right_cache, &right_target, params)?;
+    let Some(right_res) = solve_or_split(
+        &right_traces,
+        operators,
+        &right_cache,
+        &right_target,
+        max_errors.saturating_sub(left_errors),
+        params,
+        split_threshold,
+        budget,
+        algo_data,
+    )?
+    else {
+        return Ok(None);
+    };
     debug!("Found right formula {}", right_res);
 
     let res = FormulaTree::BinaryNode {
         op,
-        left: Rc::from(left_res),
-        right: Rc::from(right_res),
+        left: Arc::from(left_res),
+        right: Arc::from(right_res),
     };
     debug!("Found formula {}", res);
 
-    Some(res)
+    Ok(Some(res))
 }
 
 /// Split the largest of the negatives or the positive.
@@ -288,3 +704,189 @@ fn find_split(target: &[bool]) -> Option<(LtlBinaryOp, Vec<usize>, Vec<usize>)>
 
     Some((op, left, right))
 }
+
+#[cfg(test)]
+pub(crate) mod test {
+    use crate::{
+        algos::{budget::BudgetParams, set_cover::SetCoverParams},
+        cache::collisions::{collision_counts, enable_report, with_degenerate_hash, CacheKind},
+        ltl::trace::parse_traces,
+    };
+
+    use super::*;
+
+    /// Traces labelled by `(F p) & (G q)`.
+    pub(crate) const F_P_AND_G_Q: &str = "0,1;1,1;0,1\n1,1;0,1\n0,1;0,1;1,1;0,1\n---\n\
+        0,1;0,1;0,1\n1,1;1,0\n1,0;0,1;0,1\n0,0;0,0\n0,1;1,0;0,1\n---\n\
+        !,F,G,X,&,|,U\n---\np,q";
+
+    /// Boolean algorithm finding nothing, only checking the budget.
+    #[derive(Clone)]
+    struct NoBool;
+
+    impl BoolAlgoParams for NoBool {
+        type Data = ();
+
+        fn run(
+            &self,
+            _cache: InitialBoolCache,
+            _operators: Operators,
+            _target: &[bool],
+            _max_errors: usize,
+            budget: &Budget,
+        ) -> (Result<Option<FormulaTree>, Exhausted>, Self::Data) {
+            (budget.check(0).map(|_| None), ())
+        }
+
+        fn name() -> &'static str {
+            "no_bool"
+        }
+    }
+
+    #[test]
+    fn stops_when_budget_is_exhausted() {
+        let (traces, alphabet, target, operators) = parse_traces(F_P_AND_G_Q).unwrap();
+        let meta_params = MetaParams {
+            max_size_ltl: 3,
+            domin_nb: 4,
+            ..Default::default()
+        };
+        let run = |budget: BudgetParams| {
+            divide_conquer(
+                &traces,
+                alphabet.clone(),
+                operators.clone(),
+                target.clone(),
+                &meta_params,
+                NoBool,
+                &budget.start(),
+            )
+        };
+
+        let res = run(BudgetParams::default());
+        assert_eq!(res.exhausted(), None);
+
+        let res = run(BudgetParams {
+            timeout: Some(0.),
+            ..Default::default()
+        });
+        assert_eq!(res.exhausted(), Some(Exhausted::Time));
+        assert!(res.sol().is_none());
+        // Atoms are in the cache before the first check of the budget.
+        assert!(res.partial().is_some());
+
+        let res = run(BudgetParams {
+            max_cache_entries: Some(5),
+            ..Default::default()
+        });
+        assert_eq!(res.exhausted(), Some(Exhausted::Memory));
+        assert!(res.ltl_cache_sizes.len() <= 3);
+        assert!(res.partial().is_some());
+    }
+
+    #[test]
+    fn allowed_errors_from_count_or_rate() {
+        let params = |max_errors, max_error_rate| MetaParams {
+            max_size_ltl: 3,
+            domin_nb: 4,
+            max_errors,
+            max_error_rate,
+            ..Default::default()
+        };
+        assert_eq!(params(None, None).allowed_errors(10), 0);
+        assert_eq!(params(Some(2), None).allowed_errors(10), 2);
+        assert_eq!(params(None, Some(0.25)).allowed_errors(10), 2);
+        assert_eq!(params(None, Some(0.25)).allowed_errors(3), 0);
+
+        assert!(parse_rate("0.1").is_ok());
+        assert!(parse_rate("1.5").is_err());
+        assert!(parse_rate("-0.1").is_err());
+    }
+
+    #[test]
+    fn sacrifices_mislabelled_traces() {
+        let (traces, alphabet, mut target, operators) = parse_traces(F_P_AND_G_Q).unwrap();
+        // Mislabel the first positive trace.
+        target[0] = false;
+        let run = |max_size_ltl, max_errors, params: SetCoverParams| {
+            let meta_params = MetaParams {
+                max_size_ltl,
+                domin_nb: 4,
+                max_errors: Some(max_errors),
+                ..Default::default()
+            };
+            divide_conquer(
+                &traces,
+                alphabet.clone(),
+                operators.clone(),
+                target.clone(),
+                &meta_params,
+                params,
+                &Budget::unlimited(),
+            )
+        };
+
+        let exact = run(5, 0, SetCoverParams::new(4));
+        let tolerant = run(5, 1, SetCoverParams::new(4));
+        assert!(matches!(tolerant.result, MetaRes::FoundByLtl(_)));
+        assert!(tolerant.cost() < exact.cost().or(Some(usize::MAX)));
+        // A cheaper formula is only found by sacrificing a trace.
+        let f = tolerant.sol().unwrap();
+        assert_eq!(
+            tolerant.classification(),
+            Some(&f.classify(&traces, &target))
+        );
+        assert_eq!(tolerant.classification().unwrap().errors(), 1);
+
+        // Divide and conquer with set cover.
+        let res = run(2, 1, SetCoverParams::new(4));
+        assert!(matches!(res.result, MetaRes::FoundByBool(_)));
+        let f = res.sol().unwrap();
+        assert!(f.classify(&traces, &target).errors() <= 1);
+        assert_eq!(res.classification(), Some(&f.classify(&traces, &target)));
+    }
+
+    /// Learn a separating formula for `(F p) & (G q)`, with the usual hash and with a hash
+    /// keeping only 3 bits, and return both results after checking that they are correct
+    /// and that the LTL caches are the same.
+    pub(crate) fn run_with_degenerate_hash<P>(
+        params: P,
+    ) -> (MetaResult<P::Data>, MetaResult<P::Data>)
+    where
+        P: BoolAlgoParams + Clone,
+    {
+        let (traces, alphabet, target, operators) = parse_traces(F_P_AND_G_Q).unwrap();
+        let meta_params = MetaParams {
+            max_size_ltl: 2,
+            domin_nb: 4,
+            ..Default::default()
+        };
+        let run = || {
+            divide_conquer(
+                &traces,
+                alphabet.clone(),
+                operators.clone(),
+                target.clone(),
+                &meta_params,
+                params.clone(),
+                &Budget::unlimited(),
+            )
+        };
+
+        enable_report();
+        let ltl_collisions = || collision_counts()[CacheKind::Ltl as usize].1;
+        let before = ltl_collisions();
+        let normal = run();
+        let degenerate = with_degenerate_hash(3, run);
+        // Other tests may record collisions concurrently, so only check that counts increase.
+        assert!(ltl_collisions() > before);
+
+        assert_eq!(normal.ltl_cache_sizes, degenerate.ltl_cache_sizes);
+        for res in [&normal, &degenerate] {
+            assert!(matches!(res.result, MetaRes::FoundByBool(_)));
+            let f = res.sol().unwrap();
+            assert_eq!(f.eval(&traces).accepted_vec(), target);
+        }
+        (normal, degenerate)
+    }
+}
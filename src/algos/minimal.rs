@@ -0,0 +1,157 @@
+//! All the minimal separating LTL formulas.
+//!
+//! The LTL enumeration stops at the first separating formula, but several formulas of the
+//! same cost can separate the traces, which may reveal an ambiguity in the data.
+//! Here, the line of the first separating formula is finished, and all its separating
+//! formulas which are not equivalent on the traces are returned.
+use itertools::Itertools;
+
+use crate::{
+    formula::{rebuild_formula, tree::FormulaTree},
+    ltl::trace::{Operators, Trace},
+    traits::EqTarget,
+};
+
+use super::{
+    atoms,
+    budget::{Budget, Exhausted},
+    create_initial_cache,
+    enumeration::aux::enum_all_aux,
+};
+
+/// Result of an [`all_minimal`] search.
+#[derive(Debug, Clone)]
+pub struct MinimalFormulas {
+    /// Separating formulas of minimal cost, in the order of the enumeration.
+    pub formulas: Vec<FormulaTree>,
+    /// Cost of the formulas.
+    pub cost: Option<usize>,
+    /// Whether all the separating formulas of this cost were found,
+    /// i.e. neither the limit nor the budget was reached.
+    pub complete: bool,
+    /// Reason for which the search was stopped early, if it was.
+    pub exhausted: Option<Exhausted>,
+}
+
+impl MinimalFormulas {
+    /// Whether the minimal separating formula is unique, `None` if it is unknown.
+    pub fn is_unique(&self) -> Option<bool> {
+        match self.formulas.len() {
+            0 => None,
+            1 if self.complete => Some(true),
+            1 => None,
+            _ => Some(false),
+        }
+    }
+}
+
+/// Find the separating LTL formulas of minimal cost, if it is at most `max_size`,
+/// stopping after `limit` formulas if set.
+///
+/// Formulas which are equivalent on the traces are only returned once.
+#[allow(clippy::too_many_arguments)]
+pub fn all_minimal(
+    traces: &[Trace],
+    alphabet: Vec<String>,
+    operators: Operators,
+    target: Vec<bool>,
+    max_size: usize,
+    limit: Option<usize>,
+    threads: usize,
+    budget: &Budget,
+) -> MinimalFormulas {
+    let (atom, mut ltl_cache) =
+        create_initial_cache(atoms(traces, alphabet, &operators.costs), &target, 0);
+    // Separating atoms are added to the cache without being checked by the enumeration,
+    // which only needs to look for formulas as cheap as them.
+    let max_size = atom.map_or(max_size, |f| max_size.min(f.cost));
+    let found = enum_all_aux(
+        &mut ltl_cache,
+        &operators,
+        target.as_slice(),
+        0,
+        max_size,
+        limit,
+        threads,
+        budget,
+    );
+    let exhausted = found.err();
+
+    // The separating atoms are in the line of their cost along with the formulas found.
+    let Some((cost, formulas)) = (ltl_cache.lines.iter().enumerate())
+        .map(|(cost, l)| {
+            let formulas = (l.iter())
+                .filter(|f| f.eq_target(target.as_slice(), 0))
+                .collect_vec();
+            (cost, formulas)
+        })
+        .find(|(_, formulas)| !formulas.is_empty())
+    else {
+        return MinimalFormulas {
+            formulas: vec![],
+            cost: None,
+            complete: exhausted.is_none(),
+            exhausted,
+        };
+    };
+
+    MinimalFormulas {
+        complete: exhausted.is_none() && limit.is_none_or(|l| formulas.len() < l),
+        formulas: (formulas.into_iter())
+            .take(limit.unwrap_or(usize::MAX))
+            .map(|f| rebuild_formula(f, &ltl_cache))
+            .collect(),
+        cost: Some(cost),
+        exhausted,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::ltl::trace::parse_traces;
+
+    use super::*;
+
+    #[test]
+    fn finds_all_minimal_formulas() {
+        // Traces are labelled both by `p` and by `X q`.
+        let buf = "1,0;0,1\n1,1;0,1\n---\n0,0;0,0\n0,1;1,0\n---\nX,&,|\n---\np,q";
+        let (traces, alphabet, target, operators) = parse_traces(buf).unwrap();
+        let run = |operators: &Operators, limit| {
+            all_minimal(
+                &traces,
+                alphabet.clone(),
+                operators.clone(),
+                target.clone(),
+                3,
+                limit,
+                1,
+                &Budget::unlimited(),
+            )
+        };
+
+        let res = run(&operators, None);
+        assert_eq!(res.cost, Some(1));
+        assert_eq!(res.is_unique(), Some(true));
+        assert_eq!(res.formulas[0].to_string(), "p");
+
+        let operators = operators.with_weights(["p=2".parse().unwrap()]);
+        let res = run(&operators, None);
+        assert_eq!(res.cost, Some(2));
+        assert_eq!(res.is_unique(), Some(false));
+        let formulas = res
+            .formulas
+            .iter()
+            .map(|f| f.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(formulas, ["p", "X (q)"]);
+        for f in &res.formulas {
+            assert_eq!(f.eval(&traces).accepted_vec(), target);
+        }
+
+        let res = run(&operators, Some(1));
+        assert_eq!(res.formulas.len(), 1);
+        assert!(!res.complete);
+        assert_eq!(res.is_unique(), None);
+    }
+}
@@ -7,20 +7,28 @@
 //! This module contains the following meta-algorithms:
 //! - [Divide and conquer](self::meta)
 //!
+//! The LTL enumeration can also compute [all the minimal formulas](self::minimal)
+//! and the [Pareto front](self::pareto) of the cost of LTL formulas versus their accuracy.
+//! [Exact learning](self::sat) finds formulas of minimal cost beyond the reach of the enumeration.
+//!
 //! This module contains the following Boolean Synthesis algorithms:
 //! - [Set Cover](self::set_cover)
 //! - [Semantic Enumeration](self::enumeration)
 //! - [Beam Search](self::beam_search)
+//! - [Decision Tree](self::decision_tree)
+//! - [SAT-based exact synthesis](self::sat)
+//! - [Portfolio](self::portfolio), running several of the above
 //!
 //! Implementing a Boolean Synthesis for use with meta-algorithms is done via
 //! the [`BoolAlgoParams`] trait.
-use std::{ops::Not, rc::Rc};
+use std::{ops::Not, sync::Arc};
 
+use budget::{Budget, Exhausted};
 use meta::cache::InitialBoolCache;
 
 use crate::{
-    cache::{EnumFormulaCache, EnumFormulaCacheLine},
-    formula::{tree::FormulaTree, Formula},
+    cache::EnumFormulaCache,
+    formula::{cost::CostModel, tree::FormulaTree, Formula},
     ltl::{
         cache::LtlCache,
         charac::LtlCharac,
@@ -31,8 +39,14 @@ use crate::{
 };
 
 pub mod beam_search;
+pub mod budget;
+pub mod decision_tree;
 pub mod enumeration;
 pub mod meta;
+pub mod minimal;
+pub mod pareto;
+pub mod portfolio;
+pub mod sat;
 pub mod set_cover;
 
 /// Abstraction for the hyperparameters of Boolean Synthesis algo, used to launch multiple runs.
@@ -40,26 +54,35 @@ pub trait BoolAlgoParams {
     /// Additional info returned by a run, e.g. for collecting data for experiments.
     /// If unneeded, just use `()`.
     type Data;
-    /// Run the algorithm with the contained hyperparameters.
+    /// Run the algorithm with the contained hyperparameters,
+    /// accepting formulas misclassifying at most `max_errors` traces,
+    /// and stopping early if `budget` is exhausted.
     fn run(
         &self,
         cache: InitialBoolCache,
         operators: Operators,
         target: &[bool],
-    ) -> (Option<FormulaTree>, Self::Data);
+        max_errors: usize,
+        budget: &Budget,
+    ) -> (Result<Option<FormulaTree>, Exhausted>, Self::Data);
     /// Nickname of the algorithm, used for logging results.
     fn name() -> &'static str;
 }
 
-/// Return a [`Vec`] containing all size-1 LTL formulas: the predicates and their negation.
-fn atoms(traces: &[Trace], alphabet: Vec<String>) -> Vec<LtlFormula> {
+/// Return a [`Vec`] containing all size-1 LTL formulas: the predicates and their negation,
+/// whose cost is the weight of the predicate.
+pub(crate) fn atoms(traces: &[Trace], alphabet: Vec<String>, costs: &CostModel) -> Vec<LtlFormula> {
     let mut atoms = Vec::new();
     for (i, s) in alphabet.into_iter().enumerate() {
-        let charac = traces.iter().map(|t| t.alphabet[i]).collect::<LtlCharac>();
+        let cost = costs.atom(&s);
+        let charac = traces
+            .iter()
+            .map(|t| t.alphabet[i].clone())
+            .collect::<LtlCharac>();
         let f = Formula::new_base(
             charac,
-            1,
-            Rc::from(FormulaTree::Atom(Predicate(
+            cost,
+            Arc::from(FormulaTree::Atom(Predicate(
                 s.clone(),
                 PredicateForm::Positive(i),
             ))),
@@ -68,12 +91,12 @@ fn atoms(traces: &[Trace], alphabet: Vec<String>) -> Vec<LtlFormula> {
 
         let charac = traces
             .iter()
-            .map(|t| t.alphabet[i].not())
+            .map(|t| (&t.alphabet[i]).not())
             .collect::<LtlCharac>();
         let not_f = Formula::new_base(
             charac,
-            1,
-            Rc::from(FormulaTree::Atom(Predicate(
+            cost,
+            Arc::from(FormulaTree::Atom(Predicate(
                 format!("!{s}"),
                 PredicateForm::Negative(i),
             ))),
@@ -85,23 +108,24 @@ fn atoms(traces: &[Trace], alphabet: Vec<String>) -> Vec<LtlFormula> {
 }
 
 /// Create an [`LtlCache`] containing all formulas in `atoms`.
-fn create_initial_cache(atoms: Vec<LtlFormula>, target: &[bool]) -> (Option<LtlFormula>, LtlCache) {
-    let mut ltl_cache = LtlCache::new();
+///
+/// Atoms of cost 1 are added immediately, and the others when enumeration reaches their cost.
+/// Also returns the cheapest atom misclassifying at most `max_errors` traces of `target`, if any.
+pub(crate) fn create_initial_cache(
+    atoms: Vec<LtlFormula>,
+    target: &[bool],
+    max_errors: usize,
+) -> (Option<LtlFormula>, LtlCache) {
+    let found_atom = atoms
+        .iter()
+        .filter(|f| f.eq_target(target, max_errors))
+        .min_by_key(|f| f.cost)
+        .cloned();
+
+    let mut ltl_cache = LtlCache::new(atoms);
     // Add empty line for size 0 in cache
     ltl_cache.new_line(0);
-
-    let mut initial_line = ltl_cache.new_line(1);
-    for f in atoms {
-        initial_line.push(f);
-    }
-
-    let found_atom = ltl_cache.iter_size(1).find_map(|f| {
-        if f.eq_target(target) {
-            Some(f.clone())
-        } else {
-            None
-        }
-    });
+    ltl_cache.new_line(1);
 
     (found_atom, ltl_cache)
 }
@@ -0,0 +1,338 @@
+//! Pareto front of the cost of LTL formulas versus the number of traces they misclassify.
+//!
+//! The LTL enumeration is run one cost at a time. After each cost, the enumerated formula
+//! with the fewest misclassified traces is added to the front if no cheaper formula
+//! misclassifies as few traces. The enumeration stops once a separating formula is found,
+//! when `max_size` is reached or when the budget is exhausted.
+//!
+//! The Boolean algorithms combine the enumerated LTL formulas with `&` and `|` even when
+//! these are not among the operators. When they are not, the Boolean combinations of the
+//! formulas of the LTL cache are also enumerated up to `max_size` and added to the front.
+use itertools::Itertools;
+use log::debug;
+
+use crate::{
+    bool::{cache::BoolCache, cv::CharVec},
+    formula::{rebuild_formula, tree::FormulaTree},
+    ltl::{
+        cache::LtlCache,
+        trace::{Operators, Trace},
+    },
+    ops::binary::LtlBinaryOp,
+    traits::EqTarget,
+};
+
+use super::{
+    atoms,
+    budget::{Budget, Exhausted},
+    create_initial_cache,
+    enumeration::{
+        aux::{enum_aux, enum_seeded_aux},
+        convert_cache_enum,
+    },
+    meta::cache::InitialBoolCache,
+};
+
+/// Formula of the Pareto front: all cheaper formulas misclassify more traces.
+#[derive(Debug, Clone)]
+pub struct ParetoPoint {
+    pub formula: FormulaTree,
+    pub cost: usize,
+    /// Number of misclassified traces.
+    pub errors: usize,
+}
+
+/// Result of a [`pareto_front`] computation.
+#[derive(Debug, Clone)]
+pub struct ParetoFront {
+    /// Points of the front, by increasing cost and decreasing number of errors.
+    pub points: Vec<ParetoPoint>,
+    /// Reason for which the computation was stopped early, if it was.
+    pub exhausted: Option<Exhausted>,
+}
+
+/// Compute the Pareto front of the LTL formulas of cost at most `max_size`.
+pub fn pareto_front(
+    traces: &[Trace],
+    alphabet: Vec<String>,
+    operators: Operators,
+    target: Vec<bool>,
+    max_size: usize,
+    threads: usize,
+    budget: &Budget,
+) -> ParetoFront {
+    let (_, mut ltl_cache) =
+        create_initial_cache(atoms(traces, alphabet, &operators.costs), &target, 0);
+    let mut front = ParetoFront {
+        points: vec![],
+        exhausted: None,
+    };
+    let mut scanned = 0;
+    scan_lines(&mut front.points, &ltl_cache, &target, &mut scanned);
+
+    for size in ltl_cache.lines.len()..=max_size {
+        if front.points.last().is_some_and(|p| p.errors == 0) {
+            break;
+        }
+
+        debug!("Pareto front: LTL enumeration up to cost {size}");
+        let res = enum_aux(
+            &mut ltl_cache,
+            &operators,
+            &target,
+            0,
+            size,
+            threads,
+            budget,
+        );
+        // Formulas added to the cache before a separating one is found or the budget is
+        // exhausted can still be on the front.
+        scan_lines(&mut front.points, &ltl_cache, &target, &mut scanned);
+        match res {
+            Ok(Some(f)) => {
+                let formula = rebuild_formula(&f, &ltl_cache);
+                add_point(&mut front.points, formula, f.cost, 0);
+                break;
+            }
+            Ok(None) => (),
+            Err(e) => {
+                front.exhausted = Some(e);
+                return front;
+            }
+        }
+    }
+
+    // With both `&` and `|`, the LTL enumeration already reached all the Boolean
+    // combinations of its formulas.
+    let with_and_or = [LtlBinaryOp::And, LtlBinaryOp::Or]
+        .iter()
+        .all(|op| operators.binary.contains(op));
+    if !with_and_or {
+        let max_size = match front.points.last() {
+            Some(p) if p.errors == 0 => max_size.min(p.cost - 1),
+            _ => max_size,
+        };
+        front.exhausted = scan_boolean(
+            &mut front.points,
+            &ltl_cache,
+            operators,
+            &target,
+            max_size,
+            threads,
+            budget,
+        )
+        .err();
+    }
+
+    front
+}
+
+/// Add the best formula of each line of `cache` from `scanned` on to `points`,
+/// and mark all the lines as scanned.
+fn scan_lines(
+    points: &mut Vec<ParetoPoint>,
+    cache: &LtlCache,
+    target: &[bool],
+    scanned: &mut usize,
+) {
+    for (cost, line) in cache.lines.iter().enumerate().skip(*scanned) {
+        let best = line
+            .iter()
+            .map(|f| (f.errors(target), f))
+            .min_by_key(|(e, _)| *e);
+        if let Some((errors, f)) = best {
+            add_point(points, rebuild_formula(f, cache), cost, errors);
+        }
+    }
+    *scanned = cache.lines.len();
+}
+
+/// Enumerate the Boolean combinations of the formulas of `ltl_cache` with `&`, `|` and
+/// the Boolean operators of `operators`, up to cost `max_size`, and add the best
+/// combination of each cost to `points`.
+fn scan_boolean(
+    points: &mut Vec<ParetoPoint>,
+    ltl_cache: &LtlCache,
+    operators: Operators,
+    target: &[bool],
+    max_size: usize,
+    threads: usize,
+    budget: &Budget,
+) -> Result<(), Exhausted> {
+    let mut bool_operators = operators.filter_bool();
+    for op in [LtlBinaryOp::And, LtlBinaryOp::Or] {
+        if !bool_operators.binary.contains(&op) {
+            bool_operators.binary.push(op);
+        }
+    }
+    let bool_target: CharVec = target.iter().copied().collect();
+    // No domination, so that no combination is skipped.
+    let initial_cache = InitialBoolCache::from_ltl_cache(0, ltl_cache, target);
+    let seeds = convert_cache_enum(initial_cache, &bool_target, 0)
+        .iter_lines()
+        .into_iter()
+        .collect_vec();
+    let mut bool_cache = BoolCache::new(0);
+
+    debug!("Pareto front: Boolean enumeration up to cost {max_size}");
+    let res = enum_seeded_aux(
+        &mut bool_cache,
+        &seeds,
+        &bool_operators,
+        &bool_target,
+        0,
+        max_size,
+        threads,
+        budget,
+    );
+    for (cost, line) in bool_cache.lines().iter().enumerate() {
+        let best = line
+            .iter()
+            .map(|f| (f.errors(&bool_target), f))
+            .min_by_key(|(e, _)| *e);
+        if let Some((errors, f)) = best {
+            add_point(points, rebuild_formula(f, &bool_cache), cost, errors);
+        }
+    }
+    if let Some(f) = res? {
+        let formula = rebuild_formula(&f, &bool_cache);
+        add_point(points, formula, f.cost, 0);
+    }
+
+    Ok(())
+}
+
+/// Add a formula to `points` if no formula at most as expensive misclassifies as few
+/// traces, removing the points it dominates.
+fn add_point(points: &mut Vec<ParetoPoint>, formula: FormulaTree, cost: usize, errors: usize) {
+    if points.iter().any(|p| p.cost <= cost && p.errors <= errors) {
+        return;
+    }
+    points.retain(|p| p.cost < cost || p.errors < errors);
+    let index = points.partition_point(|p| p.cost < cost);
+    points.insert(
+        index,
+        ParetoPoint {
+            formula,
+            cost,
+            errors,
+        },
+    );
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{algos::meta::test::F_P_AND_G_Q, ltl::trace::parse_traces};
+
+    use super::*;
+
+    #[test]
+    fn front_is_non_dominated() {
+        let (traces, alphabet, target, operators) = parse_traces(F_P_AND_G_Q).unwrap();
+        let front = pareto_front(
+            &traces,
+            alphabet,
+            operators.clone(),
+            target.clone(),
+            6,
+            1,
+            &Budget::unlimited(),
+        );
+
+        assert!(front.exhausted.is_none());
+        for p in &front.points {
+            assert_eq!(p.formula.classify(&traces, &target).errors(), p.errors);
+            assert_eq!(p.formula.cost(&operators.costs), p.cost);
+        }
+        for (p, q) in front.points.iter().zip(&front.points[1..]) {
+            assert!(p.cost < q.cost);
+            assert!(p.errors > q.errors);
+        }
+        let last = front.points.last().unwrap();
+        assert_eq!(last.errors, 0);
+        assert_eq!(last.cost, 5);
+    }
+
+    /// [`F_P_AND_G_Q`] without the operators `&` and `|`.
+    fn without_and_or() -> String {
+        F_P_AND_G_Q.replace("&,|,", "")
+    }
+
+    #[test]
+    fn front_has_boolean_combinations_without_and_or() {
+        let (traces, alphabet, target, operators) = parse_traces(&without_and_or()).unwrap();
+        let front = pareto_front(
+            &traces,
+            alphabet,
+            operators.clone(),
+            target.clone(),
+            6,
+            1,
+            &Budget::unlimited(),
+        );
+
+        assert!(front.exhausted.is_none());
+        for p in &front.points {
+            assert_eq!(p.formula.classify(&traces, &target).errors(), p.errors);
+            assert_eq!(p.formula.cost(&operators.costs), p.cost);
+        }
+        for (p, q) in front.points.iter().zip(&front.points[1..]) {
+            assert!(p.cost < q.cost);
+            assert!(p.errors > q.errors);
+        }
+        let last = front.points.last().unwrap();
+        assert_eq!(last.errors, 0);
+        assert_eq!(last.cost, 5);
+    }
+
+    #[test]
+    fn boolean_combinations_do_not_improve_front() {
+        for buf in [F_P_AND_G_Q.to_string(), without_and_or()] {
+            let (traces, alphabet, target, operators) = parse_traces(&buf).unwrap();
+            let budget = Budget::unlimited();
+            let front = pareto_front(
+                &traces,
+                alphabet.clone(),
+                operators.clone(),
+                target.clone(),
+                6,
+                1,
+                &budget,
+            );
+
+            // No formula cheaper than a point, nor conjunction or disjunction of two such
+            // formulas, misclassifies fewer traces than the previous point.
+            for (p, q) in front.points.iter().zip(&front.points[1..]) {
+                let (_, mut ltl_cache) = create_initial_cache(
+                    atoms(&traces, alphabet.clone(), &operators.costs),
+                    &target,
+                    0,
+                );
+                let res = enum_aux(
+                    &mut ltl_cache,
+                    &operators,
+                    &target,
+                    0,
+                    q.cost - 1,
+                    1,
+                    &budget,
+                );
+                assert!(res.unwrap().is_none());
+                let bool_cache = InitialBoolCache::from_ltl_cache(0, &ltl_cache, &target);
+                let errors = |cv: &[bool]| cv.iter().zip(&target).filter(|(b, t)| b != t).count();
+                for (cv_f, _, cost_f) in bool_cache.iter_all() {
+                    assert!(errors(cv_f) >= p.errors);
+                    for (cv_g, _, cost_g) in bool_cache.iter_all() {
+                        if cost_f + cost_g + 1 >= q.cost {
+                            continue;
+                        }
+                        let and = cv_f.iter().zip(cv_g).map(|(f, g)| f & g).collect_vec();
+                        let or = cv_f.iter().zip(cv_g).map(|(f, g)| f | g).collect_vec();
+                        assert!(errors(&and) >= p.errors);
+                        assert!(errors(&or) >= p.errors);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,312 @@
+//! Portfolio of Boolean Synthesis algorithms.
+//!
+//! Runs several configured algorithms on the same instance, sequentially or in parallel,
+//! and keeps the cheapest formula found.
+use std::{
+    str::FromStr,
+    thread,
+    time::{Duration, Instant},
+};
+
+use clap::Args;
+use log::info;
+use thiserror::Error;
+
+use crate::{formula::tree::FormulaTree, ltl::trace::Operators};
+
+use super::{
+    beam_search::BeamSearchParams,
+    budget::{Budget, Exhausted},
+    enumeration::EnumParams,
+    meta::cache::InitialBoolCache,
+    set_cover::SetCoverParams,
+    BoolAlgoParams,
+};
+
+/// Algorithm of a portfolio, with its hyperparameters.
+///
+/// Written `enum:MAX_SIZE_BOOL:DOMIN_NB`, `set-cover:MAX_NB_FORMULAS`
+/// or `beam-search:BEAM_WIDTH:MAX_SIZE_BOOL`.
+#[derive(Clone, Copy)]
+pub enum Member {
+    Enum(EnumParams),
+    SetCover(SetCoverParams),
+    BeamSearch(BeamSearchParams),
+}
+
+impl Member {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Member::Enum(_) => EnumParams::name(),
+            Member::SetCover(_) => SetCoverParams::name(),
+            Member::BeamSearch(_) => BeamSearchParams::name(),
+        }
+    }
+
+    fn run(
+        &self,
+        cache: InitialBoolCache,
+        operators: Operators,
+        target: &[bool],
+        max_errors: usize,
+        budget: &Budget,
+    ) -> Result<Option<FormulaTree>, Exhausted> {
+        match self {
+            Member::Enum(p) => p.run(cache, operators, target, max_errors, budget).0,
+            Member::SetCover(p) => p.run(cache, operators, target, max_errors, budget).0,
+            Member::BeamSearch(p) => p.run(cache, operators, target, max_errors, budget).0,
+        }
+    }
+}
+
+#[derive(Debug, Error, PartialEq)]
+#[error("Invalid portfolio member '{}', expected 'enum:MAX_SIZE_BOOL:DOMIN_NB', 'set-cover:MAX_NB_FORMULAS' or 'beam-search:BEAM_WIDTH:MAX_SIZE_BOOL'.", .0)]
+pub struct InvalidMember(String);
+
+impl FromStr for Member {
+    type Err = InvalidMember;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || InvalidMember(s.to_owned());
+        let (name, args) = s.split_once(':').ok_or_else(err)?;
+        let args = args
+            .split(':')
+            .map(|a| a.trim().parse::<usize>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| err())?;
+        match (name.trim(), args.as_slice()) {
+            ("enum", &[max_size_bool, domin_nb]) => {
+                Ok(Member::Enum(EnumParams::new(max_size_bool, domin_nb)))
+            }
+            ("set-cover", &[max_nb_formulas]) => {
+                Ok(Member::SetCover(SetCoverParams::new(max_nb_formulas)))
+            }
+            ("beam-search", &[beam_width, max_size_bool]) => Ok(Member::BeamSearch(
+                BeamSearchParams::new(beam_width, max_size_bool),
+            )),
+            _ => Err(err()),
+        }
+    }
+}
+
+/// Runs each of its members, and returns the cheapest formula found.
+#[derive(Args, Clone)]
+pub struct PortfolioParams {
+    /// Algorithms to run, e.g. `set-cover:3 enum:5:4 beam-search:8:5`.
+    #[arg(required = true, value_name = "MEMBER")]
+    members: Vec<Member>,
+    /// Run the members in parallel, one thread each.
+    #[arg(long)]
+    parallel: bool,
+}
+
+impl PortfolioParams {
+    pub fn new(members: Vec<Member>, parallel: bool) -> Self {
+        Self { members, parallel }
+    }
+}
+
+/// Outcome of a portfolio run.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PortfolioData {
+    /// Index of the member which found the returned formula.
+    pub winner: Option<usize>,
+    /// Name and running time of each member, in order.
+    pub times: Vec<(&'static str, Duration)>,
+}
+
+impl BoolAlgoParams for PortfolioParams {
+    type Data = PortfolioData;
+
+    /// Run all the members on the same cache.
+    ///
+    /// In case of ties, the formula of the first member is kept.
+    /// If the budget is exhausted, the cheapest formula found by the other members is
+    /// returned if there is one.
+    fn run(
+        &self,
+        cache: InitialBoolCache,
+        operators: Operators,
+        target: &[bool],
+        max_errors: usize,
+        budget: &Budget,
+    ) -> (Result<Option<FormulaTree>, Exhausted>, Self::Data) {
+        let run_member = |m: &Member| {
+            let start = Instant::now();
+            let res = m.run(cache.clone(), operators.clone(), target, max_errors, budget);
+            (res, start.elapsed())
+        };
+        let results: Vec<_> = if self.parallel {
+            thread::scope(|s| {
+                let handles: Vec<_> = self
+                    .members
+                    .iter()
+                    .map(|m| s.spawn(|| run_member(m)))
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|h| h.join().expect("Portfolio thread panicked"))
+                    .collect()
+            })
+        } else {
+            self.members.iter().map(run_member).collect()
+        };
+
+        let mut data = PortfolioData::default();
+        let mut best: Option<(FormulaTree, usize)> = None;
+        let mut exhausted = None;
+        for (i, (m, (res, time))) in self.members.iter().zip(results).enumerate() {
+            data.times.push((m.name(), time));
+            match res {
+                Ok(Some(f)) => {
+                    let cost = f.cost(&operators.costs);
+                    if best.as_ref().is_none_or(|(_, c)| cost < *c) {
+                        best = Some((f, cost));
+                        data.winner = Some(i);
+                    }
+                }
+                Ok(None) => (),
+                Err(e) => exhausted = Some(e),
+            }
+        }
+
+        if let Some(i) = data.winner {
+            info!("Portfolio won by {}", self.members[i].name());
+        }
+        let res = match (best, exhausted) {
+            (Some((f, _)), _) => Ok(Some(f)),
+            (None, Some(e)) => Err(e),
+            (None, None) => Ok(None),
+        };
+        (res, data)
+    }
+
+    fn name() -> &'static str {
+        "portfolio"
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        algos::{
+            atoms, create_initial_cache,
+            enumeration::aux::enum_aux,
+            meta::{divide_conquer, test::F_P_AND_G_Q, MetaParams},
+        },
+        ltl::trace::parse_traces,
+    };
+
+    use super::*;
+
+    #[test]
+    fn parse_members() {
+        assert!(matches!("enum:5:4".parse(), Ok(Member::Enum(_))));
+        assert!(matches!("set-cover:3".parse(), Ok(Member::SetCover(_))));
+        assert!(matches!(
+            "beam-search:8:5".parse(),
+            Ok(Member::BeamSearch(_))
+        ));
+        for s in [
+            "enum",
+            "enum:5",
+            "set-cover:3:1",
+            "beam-search:a:5",
+            "sat:3",
+        ] {
+            assert!(s.parse::<Member>().is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn keeps_cheapest_formula() {
+        let (traces, alphabet, target, operators) = parse_traces(F_P_AND_G_Q).unwrap();
+        let budget = Budget::unlimited();
+        let (_, mut ltl_cache) =
+            create_initial_cache(atoms(&traces, alphabet, &operators.costs), &target, 0);
+        enum_aux(&mut ltl_cache, &operators, &target, 0, 2, 1, &budget).unwrap();
+        let cache = InitialBoolCache::from_ltl_cache(4, &ltl_cache, &target);
+
+        let members = ["set-cover:3", "beam-search:8:5", "enum:5:4"].map(|m| m.parse().unwrap());
+        let run = |parallel| {
+            PortfolioParams::new(members.to_vec(), parallel).run(
+                cache.clone(),
+                operators.clone(),
+                &target,
+                0,
+                &budget,
+            )
+        };
+
+        let (res, data) = run(false);
+        let f = res.unwrap().unwrap();
+        assert_eq!(f.eval(&traces).accepted_vec(), target);
+        let cheapest = members
+            .iter()
+            .filter_map(|m| {
+                m.run(cache.clone(), operators.clone(), &target, 0, &budget)
+                    .unwrap()
+            })
+            .map(|f| f.cost(&operators.costs))
+            .min();
+        assert_eq!(Some(f.cost(&operators.costs)), cheapest);
+        assert_eq!(data.times.len(), 3);
+        assert!(data.winner.is_some());
+
+        let (par_res, par_data) = run(true);
+        assert_eq!(par_res.unwrap().unwrap().to_string(), f.to_string());
+        assert_eq!(par_data.winner, data.winner);
+    }
+
+    #[test]
+    fn data_is_returned_by_divide_conquer() {
+        let (traces, alphabet, target, operators) = parse_traces(F_P_AND_G_Q).unwrap();
+        let meta_params = MetaParams {
+            max_size_ltl: 2,
+            domin_nb: 4,
+            ..Default::default()
+        };
+        let members = ["set-cover:3", "enum:5:4"].map(|m| m.parse().unwrap());
+        let res = divide_conquer(
+            &traces,
+            alphabet,
+            operators,
+            target,
+            &meta_params,
+            PortfolioParams::new(members.to_vec(), false),
+            &Budget::unlimited(),
+        );
+        assert!(res.sol().is_some());
+        let data = res.algo_data;
+        assert_eq!(data.len(), 1);
+        assert_eq!(data[0].times.len(), 2);
+        assert!(data[0].winner.is_some());
+    }
+
+    #[test]
+    fn data_of_every_run_is_returned() {
+        let (traces, alphabet, target, operators) = parse_traces(F_P_AND_G_Q).unwrap();
+        let meta_params = MetaParams {
+            max_size_ltl: 2,
+            domin_nb: 4,
+            ..Default::default()
+        };
+        let members = ["enum:1:4", "set-cover:2"].map(|m| m.parse().unwrap());
+        let res = divide_conquer(
+            &traces,
+            alphabet,
+            operators,
+            target,
+            &meta_params,
+            PortfolioParams::new(members.to_vec(), false),
+            &Budget::unlimited(),
+        );
+        assert!(res.sol().is_some());
+        // The first run finds nothing, and a subproblem of the split is solved by the set cover.
+        let data = res.algo_data;
+        assert!(data.len() > 1);
+        assert_eq!(data[0].winner, None);
+        assert!(data.iter().all(|d| d.times.len() == 2));
+        assert!(data.iter().any(|d| d.winner == Some(1)));
+    }
+}
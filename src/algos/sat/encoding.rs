@@ -0,0 +1,569 @@
+//! SAT encoding of the existence of a formula of a given size consistent with the traces.
+//!
+//! The formula is a syntax DAG of `size` nodes, numbered so that children have smaller
+//! indices than their parents, and whose root is the last node. Each node but the root has
+//! exactly one parent, so that the DAG is a tree and its number of nodes is the size of
+//! the formula.
+//!
+//! The variables are:
+//! - the label of each node, which is a leaf, a unary operator or a binary operator;
+//! - the left and right child of each node, if any;
+//! - the value of each node at each position of each trace, defined from the values of
+//!   its children under the assumption that the node has a given label and given children.
+//!
+//! The value of the root at the first position of each trace must match the target,
+//! except for at most `max_errors` traces, and the cost of the formula can be bounded.
+use std::{ops::Not, sync::Arc};
+
+use log::debug;
+
+use crate::{
+    algos::budget::{Budget, Exhausted},
+    formula::{cost::CostModel, tree::FormulaTree},
+    ltl::cs::Semantics,
+    ops::{binary::LtlBinaryOp, traits::Commutativity, unary::LtlUnaryOp},
+};
+
+use super::solver::{Lit, Solver};
+
+/// Maximum number of literals for which at-most-one constraints are encoded pairwise.
+const PAIRWISE_LIMIT: usize = 6;
+
+/// Formula which can label a leaf of the syntax DAG.
+#[derive(Debug, Clone)]
+pub(crate) struct Leaf {
+    pub(crate) tree: Arc<FormulaTree>,
+    pub(crate) cost: usize,
+    /// Value of the formula at each position of each trace.
+    pub(crate) values: Vec<Vec<bool>>,
+}
+
+/// Instance of exact learning, over traces described by the values of the leaves.
+pub(crate) struct Problem<'a> {
+    pub(crate) leaves: Vec<Leaf>,
+    /// Length of each trace.
+    pub(crate) lengths: Vec<usize>,
+    /// Semantics of each trace, which must be finite.
+    pub(crate) semantics: Vec<Semantics>,
+    /// Operators, which must not be bounded untils.
+    pub(crate) unary: Vec<LtlUnaryOp>,
+    pub(crate) binary: Vec<LtlBinaryOp>,
+    pub(crate) costs: &'a CostModel,
+    pub(crate) target: &'a [bool],
+    pub(crate) max_errors: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Label {
+    Leaf(usize),
+    Unary(LtlUnaryOp),
+    Binary(LtlBinaryOp),
+}
+
+/// Input of a gate: a constant or the value of a variable.
+#[derive(Debug, Clone, Copy)]
+enum Val {
+    Const(bool),
+    Lit(Lit),
+}
+
+impl Not for Val {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        match self {
+            Val::Const(b) => Val::Const(!b),
+            Val::Lit(l) => Val::Lit(!l),
+        }
+    }
+}
+
+struct Encoding<'a, 'b> {
+    problem: &'b Problem<'a>,
+    solver: Solver,
+    /// Possible labels of each node, with their variable.
+    labels: Vec<Vec<(Label, Lit)>>,
+    /// `left[i][j]` is set if node `j` is the left (or only) child of node `i`.
+    left: Vec<Vec<Lit>>,
+    /// `right[i][j]` is set if node `j` is the right child of node `i`.
+    right: Vec<Vec<Lit>>,
+    /// `values[i][k][t]` is the value of node `i` at position `t` of trace `k`.
+    values: Vec<Vec<Vec<Lit>>>,
+}
+
+/// Find a formula of `size` nodes consistent with the traces of `problem`, of cost at most
+/// `max_cost`, stopping early if `budget` is exhausted.
+///
+/// Returns the formula with its cost, or `None` if there is no such formula.
+pub(crate) fn solve(
+    problem: &Problem,
+    size: usize,
+    max_cost: usize,
+    budget: &Budget,
+) -> Result<Option<(FormulaTree, usize)>, Exhausted> {
+    if size == 0 || problem.leaves.is_empty() {
+        return Ok(None);
+    }
+    let mut enc = Encoding::new(problem, size);
+    enc.structure();
+    enc.semantics();
+    enc.target();
+    enc.cost(max_cost);
+    debug!(
+        "Encoding of size {size} has {} clauses",
+        enc.solver.nb_clauses()
+    );
+    if !enc.solver.solve(budget)? {
+        return Ok(None);
+    }
+    let (tree, cost) = enc.decode(size - 1);
+    Ok(Some((Arc::unwrap_or_clone(tree), cost)))
+}
+
+impl<'a, 'b> Encoding<'a, 'b> {
+    fn new(problem: &'b Problem<'a>, size: usize) -> Self {
+        let mut solver = Solver::new();
+        let labels = (0..size)
+            .map(|i| {
+                let leaves = (0..problem.leaves.len()).map(Label::Leaf);
+                let unary = problem.unary.iter().map(|&op| Label::Unary(op));
+                let binary = problem.binary.iter().map(|&op| Label::Binary(op));
+                let labels = match i {
+                    0 => leaves.collect::<Vec<_>>(),
+                    1 => leaves.chain(unary).collect(),
+                    _ => leaves.chain(unary).chain(binary).collect(),
+                };
+                (labels.into_iter())
+                    .map(|label| (label, solver.new_var()))
+                    .collect()
+            })
+            .collect();
+        let mut children = || {
+            (0..size)
+                .map(|i| (0..i).map(|_| solver.new_var()).collect())
+                .collect()
+        };
+        let left = children();
+        let right = children();
+        let values = (0..size)
+            .map(|_| {
+                (problem.lengths.iter())
+                    .map(|&len| (0..len).map(|_| solver.new_var()).collect())
+                    .collect()
+            })
+            .collect();
+        Encoding {
+            problem,
+            solver,
+            labels,
+            left,
+            right,
+            values,
+        }
+    }
+
+    fn weight(&self, label: Label) -> usize {
+        match label {
+            Label::Leaf(k) => self.problem.leaves[k].cost,
+            Label::Unary(op) => self.problem.costs.unary(op),
+            Label::Binary(op) => self.problem.costs.binary(op),
+        }
+    }
+
+    /// Shape of the tree: one label per node, children matching the arity of the label,
+    /// and one parent per node but the root.
+    fn structure(&mut self) {
+        let size = self.labels.len();
+        for i in 0..size {
+            let labels = self.labels[i].iter().map(|&(_, x)| x).collect::<Vec<_>>();
+            self.solver.add_clause(&labels);
+            at_most(&mut self.solver, &labels, 1);
+            at_most(&mut self.solver, &self.left[i], 1);
+            at_most(&mut self.solver, &self.right[i], 1);
+
+            let ops = (self.labels[i].iter())
+                .filter(|(label, _)| !matches!(label, Label::Leaf(_)))
+                .map(|&(_, x)| x)
+                .collect::<Vec<_>>();
+            let binary = (self.labels[i].iter())
+                .filter(|(label, _)| matches!(label, Label::Binary(_)))
+                .map(|&(_, x)| x)
+                .collect::<Vec<_>>();
+            for &(label, x) in &self.labels[i] {
+                let has_child = |children: &[Lit]| [&[!x], children].concat();
+                match label {
+                    Label::Leaf(_) => (),
+                    Label::Unary(_) => self.solver.add_clause(&has_child(&self.left[i])),
+                    Label::Binary(op) => {
+                        self.solver.add_clause(&has_child(&self.left[i]));
+                        self.solver.add_clause(&has_child(&self.right[i]));
+                        if op.commutes() {
+                            // The left child comes first.
+                            for (j, &l) in self.left[i].iter().enumerate() {
+                                for &r in &self.right[i][..j] {
+                                    self.solver.add_clause(&[!x, !l, !r]);
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+            for &l in &self.left[i] {
+                self.solver.add_clause(&[&[!l], &ops[..]].concat());
+            }
+            for &r in &self.right[i] {
+                self.solver.add_clause(&[&[!r], &binary[..]].concat());
+            }
+        }
+
+        for j in 0..size - 1 {
+            let parents = (j + 1..size)
+                .flat_map(|i| [self.left[i][j], self.right[i][j]])
+                .collect::<Vec<_>>();
+            self.solver.add_clause(&parents);
+            at_most(&mut self.solver, &parents, 1);
+        }
+    }
+
+    /// Values of the nodes, according to their label and children.
+    fn semantics(&mut self) {
+        let problem = self.problem;
+        for i in 0..self.labels.len() {
+            for (label, x) in self.labels[i].clone() {
+                match label {
+                    Label::Leaf(k) => {
+                        let leaf = &problem.leaves[k];
+                        for (values, lits) in leaf.values.iter().zip(&self.values[i]) {
+                            for (&v, &y) in values.iter().zip(lits) {
+                                self.solver.add_clause(&[!x, if v { y } else { !y }]);
+                            }
+                        }
+                    }
+                    Label::Unary(op) => {
+                        for j in 0..i {
+                            let guard = [x, self.left[i][j]];
+                            self.unary(&guard, op, i, j);
+                        }
+                    }
+                    Label::Binary(op) => {
+                        for j in 0..i {
+                            for k in 0..i {
+                                if j == k {
+                                    continue;
+                                }
+                                let guard = [x, self.left[i][j], self.right[i][k]];
+                                self.binary(&guard, op, i, j, k);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Value of node `i` given by unary operator `op` applied to node `j`.
+    fn unary(&mut self, guard: &[Lit], op: LtlUnaryOp, i: usize, j: usize) {
+        for k in 0..self.problem.lengths.len() {
+            let len = self.problem.lengths[k];
+            let semantics = self.problem.semantics[k];
+            let (ex_end, univ_end) = (semantics.existential_end(), semantics.universal_end());
+            let y = |t: usize| self.values[i][k][t];
+            let c = |t: usize, end: bool| match self.values[j][k].get(t) {
+                Some(&l) => Val::Lit(l),
+                None => Val::Const(end),
+            };
+            let next = |t: usize, end: bool| match self.values[i][k].get(t + 1) {
+                Some(&l) => Val::Lit(l),
+                None => Val::Const(end),
+            };
+            let prev = |t: usize, start: bool| match t {
+                0 => Val::Const(start),
+                _ => Val::Lit(self.values[i][k][t - 1]),
+            };
+            let before = |t: usize, start: bool| match t {
+                0 => Val::Const(start),
+                _ => c(t - 1, start),
+            };
+            let mut gates = vec![];
+            for t in 0..len {
+                let gate = match op {
+                    LtlUnaryOp::Not => Gate::Table(vec![c(t, false)], |a| !a[0]),
+                    LtlUnaryOp::Next => Gate::Table(vec![c(t + 1, ex_end)], |a| a[0]),
+                    LtlUnaryOp::WeakNext => Gate::Table(vec![c(t + 1, true)], |a| a[0]),
+                    LtlUnaryOp::Finally => {
+                        Gate::Table(vec![c(t, false), next(t, ex_end)], |a| a[0] || a[1])
+                    }
+                    LtlUnaryOp::Globally => {
+                        Gate::Table(vec![c(t, false), next(t, univ_end)], |a| a[0] && a[1])
+                    }
+                    LtlUnaryOp::Yesterday => Gate::Table(vec![before(t, false)], |a| a[0]),
+                    LtlUnaryOp::WeakYesterday => Gate::Table(vec![before(t, true)], |a| a[0]),
+                    LtlUnaryOp::Once => {
+                        Gate::Table(vec![c(t, false), prev(t, false)], |a| a[0] || a[1])
+                    }
+                    LtlUnaryOp::Historically => {
+                        Gate::Table(vec![c(t, false), prev(t, true)], |a| a[0] && a[1])
+                    }
+                    LtlUnaryOp::BoundedFinally(interval) => Gate::Or(
+                        (interval.lo..=interval.hi)
+                            .map(|d| c(t + d, ex_end))
+                            .collect(),
+                    ),
+                    LtlUnaryOp::BoundedGlobally(interval) => Gate::And(
+                        (interval.lo..=interval.hi)
+                            .map(|d| c(t + d, univ_end))
+                            .collect(),
+                    ),
+                };
+                gates.push((y(t), gate));
+            }
+            for (y, gate) in gates {
+                gate.define(&mut self.solver, guard, y);
+            }
+        }
+    }
+
+    /// Value of node `i` given by binary operator `op` applied to nodes `j` and `k`.
+    fn binary(&mut self, guard: &[Lit], op: LtlBinaryOp, i: usize, j: usize, k: usize) {
+        for tr in 0..self.problem.lengths.len() {
+            let semantics = self.problem.semantics[tr];
+            let (ex_end, univ_end) = (semantics.existential_end(), semantics.universal_end());
+            let mut gates = vec![];
+            for t in 0..self.problem.lengths[tr] {
+                let c = Val::Lit(self.values[j][tr][t]);
+                let d = Val::Lit(self.values[k][tr][t]);
+                let next = |end: bool| match self.values[i][tr].get(t + 1) {
+                    Some(&l) => Val::Lit(l),
+                    None => Val::Const(end),
+                };
+                let prev = match t {
+                    0 => Val::Const(false),
+                    _ => Val::Lit(self.values[i][tr][t - 1]),
+                };
+                let gate = match op {
+                    LtlBinaryOp::Or => Gate::Table(vec![c, d], |a| a[0] || a[1]),
+                    LtlBinaryOp::And => Gate::Table(vec![c, d], |a| a[0] && a[1]),
+                    LtlBinaryOp::Implies => Gate::Table(vec![c, d], |a| !a[0] || a[1]),
+                    LtlBinaryOp::Equiv => Gate::Table(vec![c, d], |a| a[0] == a[1]),
+                    LtlBinaryOp::Until => {
+                        Gate::Table(vec![c, d, next(ex_end)], |a| a[1] || (a[0] && a[2]))
+                    }
+                    LtlBinaryOp::WeakUntil => {
+                        Gate::Table(vec![c, d, next(univ_end)], |a| a[1] || (a[0] && a[2]))
+                    }
+                    LtlBinaryOp::Release => {
+                        Gate::Table(vec![c, d, next(univ_end)], |a| a[1] && (a[0] || a[2]))
+                    }
+                    LtlBinaryOp::Since => Gate::Table(vec![c, d, prev], |a| a[1] || (a[0] && a[2])),
+                    LtlBinaryOp::BoundedUntil(_) => {
+                        unreachable!("bounded untils are rejected before encoding")
+                    }
+                };
+                gates.push((self.values[i][tr][t], gate));
+            }
+            for (y, gate) in gates {
+                gate.define(&mut self.solver, guard, y);
+            }
+        }
+    }
+
+    /// Value of the root at the first position of each trace, up to `max_errors` errors.
+    fn target(&mut self) {
+        let root = self.values.len() - 1;
+        let mut errors = vec![];
+        for (values, &b) in self.values[root].iter().zip(self.problem.target) {
+            let y = if b { values[0] } else { !values[0] };
+            if self.problem.max_errors == 0 {
+                self.solver.add_clause(&[y]);
+            } else {
+                let e = self.solver.new_var();
+                self.solver.add_clause(&[e, y]);
+                errors.push(e);
+            }
+        }
+        at_most(&mut self.solver, &errors, self.problem.max_errors);
+    }
+
+    /// Cost of the formula at most `max_cost`, with a counter of the cost of the first nodes.
+    fn cost(&mut self, max_cost: usize) {
+        let max_weights = (self.labels.iter())
+            .map(|labels| {
+                labels
+                    .iter()
+                    .map(|&(l, _)| self.weight(l))
+                    .max()
+                    .unwrap_or(0)
+            })
+            .sum::<usize>();
+        if max_weights <= max_cost {
+            return;
+        }
+
+        // `reached[c - 1]` is set when the previous nodes, or some of the first ones, cost `c`.
+        let mut reached: Vec<Lit> = vec![];
+        for i in 0..self.labels.len() {
+            let sums = (0..max_cost)
+                .map(|_| self.solver.new_var())
+                .collect::<Vec<_>>();
+            for (&before, &after) in reached.iter().zip(&sums) {
+                self.solver.add_clause(&[!before, after]);
+            }
+            for (label, x) in self.labels[i].clone() {
+                let w = self.weight(label);
+                let previous = [None].into_iter().chain(reached.iter().copied().map(Some));
+                for (c, before) in previous.enumerate() {
+                    let guard = [Some(!x), before.map(|l| !l)].into_iter().flatten();
+                    let clause = match sums.get(c + w - 1) {
+                        Some(&after) => guard.chain([after]).collect::<Vec<_>>(),
+                        None => guard.collect(),
+                    };
+                    self.solver.add_clause(&clause);
+                }
+            }
+            reached = sums;
+        }
+    }
+
+    /// Formula rooted at node `i` in the model, with its cost.
+    fn decode(&self, i: usize) -> (Arc<FormulaTree>, usize) {
+        let child = |children: &[Lit]| {
+            let j = (children.iter())
+                .position(|&l| self.solver.model_value(l))
+                .expect("Operators have children");
+            self.decode(j)
+        };
+        let &(label, _) = (self.labels[i].iter())
+            .find(|&&(_, x)| self.solver.model_value(x))
+            .expect("Nodes have a label");
+        let (tree, cost) = match label {
+            Label::Leaf(k) => (self.problem.leaves[k].tree.clone(), 0),
+            Label::Unary(op) => {
+                let (child, cost) = child(&self.left[i]);
+                (Arc::new(FormulaTree::UnaryNode { op, child }), cost)
+            }
+            Label::Binary(op) => {
+                let (left, left_cost) = child(&self.left[i]);
+                let (right, right_cost) = child(&self.right[i]);
+                let tree = FormulaTree::BinaryNode { op, left, right };
+                (Arc::new(tree), left_cost + right_cost)
+            }
+        };
+        (tree, cost + self.weight(label))
+    }
+}
+
+/// Definition of a variable from its inputs.
+enum Gate {
+    /// Boolean function of at most a few inputs.
+    Table(Vec<Val>, fn(&[bool]) -> bool),
+    Or(Vec<Val>),
+    And(Vec<Val>),
+}
+
+impl Gate {
+    /// Add clauses such that `out` is the output of the gate whenever all of `guard` holds.
+    fn define(self, solver: &mut Solver, guard: &[Lit], out: Lit) {
+        let negated_guard = guard.iter().map(|&g| !g);
+        match self {
+            Gate::Table(inputs, f) => {
+                let lits = (inputs.iter())
+                    .filter_map(|&v| match v {
+                        Val::Lit(l) => Some(l),
+                        Val::Const(_) => None,
+                    })
+                    .collect::<Vec<_>>();
+                for assignment in 0..1usize << lits.len() {
+                    let mut next = 0;
+                    let args = (inputs.iter())
+                        .map(|&v| match v {
+                            Val::Const(b) => b,
+                            Val::Lit(_) => {
+                                next += 1;
+                                (assignment >> (next - 1)) & 1 == 1
+                            }
+                        })
+                        .collect::<Vec<_>>();
+                    // The inputs differ from the assignment, or the output is as expected.
+                    let differ = (lits.iter().enumerate()).map(|(n, &l)| {
+                        if (assignment >> n) & 1 == 1 {
+                            !l
+                        } else {
+                            l
+                        }
+                    });
+                    let result = if f(&args) { out } else { !out };
+                    let clause = (negated_guard.clone())
+                        .chain(differ)
+                        .chain([result])
+                        .collect::<Vec<_>>();
+                    solver.add_clause(&clause);
+                }
+            }
+            Gate::Or(inputs) => {
+                if inputs.iter().any(|v| matches!(v, Val::Const(true))) {
+                    let clause = negated_guard.chain([out]).collect::<Vec<_>>();
+                    solver.add_clause(&clause);
+                    return;
+                }
+                let lits = (inputs.iter()).filter_map(|&v| match v {
+                    Val::Lit(l) => Some(l),
+                    Val::Const(_) => None,
+                });
+                let clause = (negated_guard.clone())
+                    .chain([!out])
+                    .chain(lits.clone())
+                    .collect::<Vec<_>>();
+                solver.add_clause(&clause);
+                for l in lits {
+                    let clause = (negated_guard.clone()).chain([!l, out]).collect::<Vec<_>>();
+                    solver.add_clause(&clause);
+                }
+            }
+            Gate::And(inputs) => {
+                let inputs = inputs.into_iter().map(Val::not).collect();
+                Gate::Or(inputs).define(solver, guard, !out);
+            }
+        }
+    }
+}
+
+/// Add clauses such that at most `k` of `lits` hold, with a sequential counter.
+fn at_most(solver: &mut Solver, lits: &[Lit], k: usize) {
+    if lits.len() <= k {
+        return;
+    }
+    if k == 0 {
+        lits.iter().for_each(|&l| solver.add_clause(&[!l]));
+        return;
+    }
+    if k == 1 && lits.len() <= PAIRWISE_LIMIT {
+        for (n, &l) in lits.iter().enumerate() {
+            for &m in &lits[..n] {
+                solver.add_clause(&[!l, !m]);
+            }
+        }
+        return;
+    }
+
+    // `counts[j]` is set if more than `j` of the previous literals hold.
+    let mut counts: Vec<Lit> = vec![];
+    for (n, &l) in lits.iter().enumerate() {
+        if let Some(&full) = counts.get(k - 1) {
+            solver.add_clause(&[!l, !full]);
+        }
+        if n == lits.len() - 1 {
+            break;
+        }
+        let next = (0..k).map(|_| solver.new_var()).collect::<Vec<_>>();
+        solver.add_clause(&[!l, next[0]]);
+        for j in 0..k {
+            if let Some(&before) = counts.get(j) {
+                solver.add_clause(&[!before, next[j]]);
+            }
+            if let (Some(&before), Some(&after)) = (counts.get(j), next.get(j + 1)) {
+                solver.add_clause(&[!l, !before, after]);
+            }
+        }
+        counts = next;
+    }
+}
@@ -0,0 +1,371 @@
+//! Exact learning of formulas of minimal cost with a SAT solver.
+//!
+//! The existence of a formula of `n` nodes consistent with the traces is [encoded](encoding)
+//! as a SAT problem, which is solved by an embedded [CDCL solver](solver), for `n = 1, 2, ...`
+//! Unlike the meta-algorithms, which fall back to heuristics after the LTL enumeration,
+//! the formula found is guaranteed to be of minimal cost, but the size of the encoding
+//! grows quickly with the size of the formula and the length of the traces.
+//!
+//! With non-unit weights, the formulas of each size are searched for with decreasing
+//! bounds on their cost, until no cheaper formula can be found.
+//!
+//! The encoding can also serve as a Boolean Synthesis algorithm ([`SatParams`]),
+//! whose leaves are the LTL formulas of the [`InitialBoolCache`] and each trace a single position.
+//!
+//! Lassos are not supported, nor are bounded untils.
+mod encoding;
+mod solver;
+
+use std::sync::Arc;
+
+use clap::Args;
+use encoding::{Leaf, Problem};
+use log::info;
+use thiserror::Error;
+
+use crate::{
+    formula::{tree::FormulaTree, FormulaNode},
+    ltl::{
+        cs::Semantics,
+        trace::{Operators, Trace},
+    },
+    ops::binary::LtlBinaryOp,
+};
+
+use super::{
+    atoms,
+    budget::{Budget, Exhausted},
+    meta::cache::InitialBoolCache,
+    BoolAlgoParams,
+};
+
+#[derive(Debug, Error, PartialEq)]
+pub enum SatError {
+    #[error("lasso traces are not supported by exact learning")]
+    Lasso,
+    #[error("operator '{0}' is not supported by exact learning")]
+    Unsupported(LtlBinaryOp),
+}
+
+/// Result of an [`exact`] search.
+#[derive(Debug, Clone)]
+pub struct ExactResult {
+    /// Formula of minimal cost, or the cheapest one found if the search was stopped early.
+    pub formula: Option<FormulaTree>,
+    pub cost: Option<usize>,
+    /// Reason for which the search was stopped early, if it was.
+    pub exhausted: Option<Exhausted>,
+}
+
+/// Parameters of an [`exact`] search.
+#[derive(Args, Clone, Copy, Debug)]
+pub struct ExactParams {
+    /// Search for formulas up to this cost.
+    pub max_size: usize,
+    /// Accept formulas misclassifying at most this many traces.
+    #[arg(long, default_value_t = 0)]
+    pub max_errors: usize,
+}
+
+/// Find an LTL formula of minimal cost misclassifying at most `params.max_errors` traces,
+/// if its cost is at most `params.max_size`.
+pub fn exact(
+    traces: &[Trace],
+    alphabet: Vec<String>,
+    operators: Operators,
+    target: &[bool],
+    params: ExactParams,
+    budget: &Budget,
+) -> Result<ExactResult, SatError> {
+    if traces.iter().any(Trace::is_lasso) {
+        return Err(SatError::Lasso);
+    }
+    if let Some(&op) =
+        (operators.binary.iter()).find(|op| matches!(op, LtlBinaryOp::BoundedUntil(_)))
+    {
+        return Err(SatError::Unsupported(op));
+    }
+
+    let lengths = (traces.iter())
+        .map(|t| t.alphabet.first().map_or(0, |cs| cs.len()))
+        .collect::<Vec<_>>();
+    let leaves = (atoms(traces, alphabet, &operators.costs).into_iter())
+        .map(|f| {
+            let FormulaNode::Base(tree) = f.node else {
+                unreachable!("Atoms are base formulas")
+            };
+            let values = (f.charac.cm.seqs.iter())
+                .map(|cs| (0..cs.len()).map(|t| cs.get(t)).collect())
+                .collect();
+            Leaf {
+                tree,
+                cost: f.cost,
+                values,
+            }
+        })
+        .collect();
+    let semantics = (traces.iter())
+        .map(|t| (t.alphabet.first()).map_or(Semantics::default(), |cs| cs.semantics().unwrap()))
+        .collect();
+    let problem = Problem {
+        leaves,
+        lengths,
+        semantics,
+        unary: operators.unary.clone(),
+        binary: operators.binary.clone(),
+        costs: &operators.costs,
+        target,
+        max_errors: params.max_errors,
+    };
+
+    let (best, exhausted) = minimise(&problem, params.max_size, budget);
+    let (formula, cost) = best.unzip();
+    Ok(ExactResult {
+        formula,
+        cost,
+        exhausted,
+    })
+}
+
+/// Find a formula of minimal cost consistent with `problem`, if its cost is at most `max_cost`,
+/// by increasing size.
+///
+/// If `budget` is exhausted, returns the cheapest formula found so far.
+fn minimise(
+    problem: &Problem,
+    max_cost: usize,
+    budget: &Budget,
+) -> (Option<(FormulaTree, usize)>, Option<Exhausted>) {
+    let mut best: Option<(FormulaTree, usize)> = None;
+    for size in 1..=max_cost {
+        // Formulas of `size` nodes cost at least `size`.
+        let bound = best.as_ref().map_or(max_cost, |&(_, cost)| cost - 1);
+        if bound < size {
+            break;
+        }
+        if let Err(e) = budget.check(0) {
+            return (best, Some(e));
+        }
+        info!("Searching for formulas of size {size} and cost at most {bound}");
+        let mut bound = bound;
+        while bound >= size {
+            match encoding::solve(problem, size, bound, budget) {
+                Ok(Some((f, cost))) => {
+                    info!("Found formula of cost {cost}: {f}");
+                    bound = cost - 1;
+                    best = Some((f, cost));
+                }
+                Ok(None) => break,
+                Err(e) => return (best, Some(e)),
+            }
+        }
+    }
+    (best, None)
+}
+
+#[derive(Args, Clone, Copy)]
+pub struct SatParams {
+    /// Number of the cheapest LTL formulas used as leaves of the Boolean formula.
+    max_features: usize,
+    /// Maximum cost of the Boolean formula.
+    max_size_bool: usize,
+}
+
+impl SatParams {
+    pub fn new(max_features: usize, max_size_bool: usize) -> Self {
+        Self {
+            max_features,
+            max_size_bool,
+        }
+    }
+}
+
+impl BoolAlgoParams for SatParams {
+    type Data = ();
+
+    fn run(
+        &self,
+        cache: InitialBoolCache,
+        operators: Operators,
+        target: &[bool],
+        max_errors: usize,
+        budget: &Budget,
+    ) -> (Result<Option<FormulaTree>, Exhausted>, Self::Data) {
+        let operators = operators.filter_bool();
+        // The cache is sorted by cost.
+        let leaves = (cache.iter_all().into_iter())
+            .take(self.max_features)
+            .map(|(cv, tree, cost)| Leaf {
+                tree: Arc::clone(tree),
+                cost: *cost,
+                values: cv.iter().map(|&b| vec![b]).collect(),
+            })
+            .collect();
+        let problem = Problem {
+            leaves,
+            lengths: vec![1; target.len()],
+            semantics: vec![Semantics::default(); target.len()],
+            unary: operators.unary.clone(),
+            binary: operators.binary.clone(),
+            costs: &operators.costs,
+            target,
+            max_errors,
+        };
+
+        let res = match minimise(&problem, self.max_size_bool, budget) {
+            (Some((f, _)), _) => Ok(Some(f)),
+            (None, Some(e)) => Err(e),
+            (None, None) => Ok(None),
+        };
+        (res, ())
+    }
+
+    fn name() -> &'static str {
+        "sat"
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        algos::{
+            meta::{divide_conquer, test::F_P_AND_G_Q, MetaParams},
+            pareto::pareto_front,
+        },
+        formula::cost::Weight,
+        ltl::trace::parse_traces,
+    };
+
+    use super::*;
+
+    #[test]
+    fn exact_matches_enumeration() {
+        let files = [
+            F_P_AND_G_Q,
+            // Past-time operators.
+            "1,0;0,1\n0,0;1,0;0,1\n---\n0,1;0,1\n1,0;0,0;0,1\n---\n!,Y,O,H,&,|,S,N\n---\np,q",
+            // Bounded and weak temporal operators.
+            "1;0;1\n1;1;0\n---\n0;1;1\n1;0;0;1\n---\n!,X,F[1,2],G[0,1],R,W,->\n---\np",
+        ];
+        for buf in files {
+            let (traces, alphabet, target, operators) = parse_traces(buf).unwrap();
+            let front = pareto_front(
+                &traces,
+                alphabet.clone(),
+                operators.clone(),
+                target.clone(),
+                6,
+                1,
+                &Budget::unlimited(),
+            );
+            for max_errors in [0, 1] {
+                let res = exact(
+                    &traces,
+                    alphabet.clone(),
+                    operators.clone(),
+                    &target,
+                    ExactParams {
+                        max_size: 6,
+                        max_errors,
+                    },
+                    &Budget::unlimited(),
+                )
+                .unwrap();
+                let expected = (front.points.iter())
+                    .find(|p| p.errors <= max_errors)
+                    .map(|p| p.cost);
+                assert_eq!(res.cost, expected);
+                if let Some(f) = res.formula {
+                    assert_eq!(Some(f.cost(&operators.costs)), res.cost);
+                    assert!(f.classify(&traces, &target).errors() <= max_errors);
+                }
+            }
+
+            // Expensive temporal operators.
+            let operators = operators.with_weights(
+                ["U=3", "F=2", "S=3", "F[1,2]=4", "p=2"].map(|w| w.parse::<Weight>().unwrap()),
+            );
+            let front = pareto_front(
+                &traces,
+                alphabet.clone(),
+                operators.clone(),
+                target.clone(),
+                8,
+                1,
+                &Budget::unlimited(),
+            );
+            let res = exact(
+                &traces,
+                alphabet,
+                operators,
+                &target,
+                ExactParams {
+                    max_size: 8,
+                    max_errors: 0,
+                },
+                &Budget::unlimited(),
+            )
+            .unwrap();
+            let expected = (front.points.iter())
+                .find(|p| p.errors == 0)
+                .map(|p| p.cost);
+            assert_eq!(res.cost, expected);
+        }
+    }
+
+    #[test]
+    fn rejects_unsupported_traces() {
+        let buf = "1;0::1\n---\n0;1::0\n---\nX,U[1,2]\n---\np";
+        let (traces, alphabet, target, operators) = parse_traces(buf).unwrap();
+        let res = exact(
+            &traces,
+            alphabet,
+            operators,
+            &target,
+            ExactParams {
+                max_size: 3,
+                max_errors: 0,
+            },
+            &Budget::unlimited(),
+        );
+        assert_eq!(res.unwrap_err(), SatError::Lasso);
+
+        let buf = "1;0\n---\n0;1\n---\nX,U[1,2]\n---\np";
+        let (traces, alphabet, target, operators) = parse_traces(buf).unwrap();
+        let res = exact(
+            &traces,
+            alphabet,
+            operators,
+            &target,
+            ExactParams {
+                max_size: 3,
+                max_errors: 0,
+            },
+            &Budget::unlimited(),
+        );
+        let op = LtlBinaryOp::try_from("U[1,2]").unwrap();
+        assert_eq!(res.unwrap_err(), SatError::Unsupported(op));
+    }
+
+    #[test]
+    fn sat_boolean_synthesis() {
+        let (traces, alphabet, target, operators) = parse_traces(F_P_AND_G_Q).unwrap();
+        let meta_params = MetaParams {
+            max_size_ltl: 2,
+            domin_nb: 8,
+            no_minimise: true,
+            ..Default::default()
+        };
+        let res = divide_conquer(
+            &traces,
+            alphabet,
+            operators,
+            target.clone(),
+            &meta_params,
+            SatParams::new(16, 8),
+            &Budget::unlimited(),
+        );
+        let f = res.sol().unwrap();
+        assert_eq!(f.classify(&traces, &target).errors(), 0);
+    }
+}
@@ -0,0 +1,403 @@
+//! Small CDCL SAT solver used by the [SAT encodings](super).
+//!
+//! Conflicts are analysed up to the first unique implication point, the learnt clauses are
+//! kept forever, and decisions follow the VSIDS heuristic with phase saving.
+//! The search restarts after a number of conflicts following the Luby sequence.
+//! This is enough for the small instances of exact learning, and avoids depending on
+//! an external solver.
+use std::{collections::BinaryHeap, mem, ops::Not};
+
+use ordered_float::OrderedFloat;
+
+use crate::algos::budget::{Budget, Exhausted};
+
+/// Number of conflicts between two checks of the budget.
+const BUDGET_CHECK_INTERVAL: usize = 256;
+/// Number of conflicts of the unit run of the restart sequence.
+const RESTART_UNIT: usize = 128;
+/// Decay factor of the activity of variables.
+const VAR_DECAY: f64 = 0.95;
+
+/// Literal: a variable or its negation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct Lit(u32);
+
+impl Lit {
+    fn new(var: usize, negated: bool) -> Self {
+        Lit(((var as u32) << 1) | negated as u32)
+    }
+
+    fn var(self) -> usize {
+        (self.0 >> 1) as usize
+    }
+
+    fn is_negated(self) -> bool {
+        self.0 & 1 == 1
+    }
+
+    fn index(self) -> usize {
+        self.0 as usize
+    }
+}
+
+impl Not for Lit {
+    type Output = Self;
+
+    fn not(self) -> Self {
+        Lit(self.0 ^ 1)
+    }
+}
+
+#[derive(Default)]
+pub(crate) struct Solver {
+    clauses: Vec<Vec<Lit>>,
+    /// Clauses watching the negation of each literal, which are visited when it becomes true.
+    watches: Vec<Vec<usize>>,
+    values: Vec<Option<bool>>,
+    levels: Vec<usize>,
+    /// Clause which implied the value of each variable, whose first literal is the implied one.
+    reasons: Vec<Option<usize>>,
+    trail: Vec<Lit>,
+    /// Start of each decision level in the trail.
+    trail_limits: Vec<usize>,
+    /// Next literal of the trail to propagate.
+    propagated: usize,
+    activity: Vec<f64>,
+    activity_inc: f64,
+    /// Candidate decisions by activity, possibly outdated or already assigned.
+    order: BinaryHeap<(OrderedFloat<f64>, usize)>,
+    /// Last value of each variable, tried first when deciding on it.
+    phases: Vec<bool>,
+    seen: Vec<bool>,
+    /// Whether an empty clause was derived.
+    unsat: bool,
+}
+
+impl Solver {
+    pub(crate) fn new() -> Self {
+        Self {
+            activity_inc: 1.,
+            ..Default::default()
+        }
+    }
+
+    pub(crate) fn new_var(&mut self) -> Lit {
+        let var = self.values.len();
+        self.values.push(None);
+        self.levels.push(0);
+        self.reasons.push(None);
+        self.activity.push(0.);
+        self.phases.push(false);
+        self.seen.push(false);
+        self.watches.push(vec![]);
+        self.watches.push(vec![]);
+        self.order.push((OrderedFloat(0.), var));
+        Lit::new(var, false)
+    }
+
+    pub(crate) fn nb_clauses(&self) -> usize {
+        self.clauses.len()
+    }
+
+    fn value(&self, lit: Lit) -> Option<bool> {
+        self.values[lit.var()].map(|v| v != lit.is_negated())
+    }
+
+    /// Value of `lit` in the model found by the last successful [`Solver::solve`].
+    pub(crate) fn model_value(&self, lit: Lit) -> bool {
+        self.value(lit) == Some(true)
+    }
+
+    /// Add a clause, before solving.
+    pub(crate) fn add_clause(&mut self, lits: &[Lit]) {
+        debug_assert!(self.trail_limits.is_empty());
+        let mut clause = Vec::with_capacity(lits.len());
+        for &lit in lits {
+            match self.value(lit) {
+                Some(true) => return,
+                Some(false) => (),
+                None if clause.contains(&!lit) => return,
+                None if !clause.contains(&lit) => clause.push(lit),
+                None => (),
+            }
+        }
+        match clause[..] {
+            [] => self.unsat = true,
+            [lit] => self.assign(lit, None),
+            _ => {
+                self.attach(clause);
+            }
+        }
+    }
+
+    fn attach(&mut self, clause: Vec<Lit>) -> usize {
+        let id = self.clauses.len();
+        self.watches[(!clause[0]).index()].push(id);
+        self.watches[(!clause[1]).index()].push(id);
+        self.clauses.push(clause);
+        id
+    }
+
+    fn assign(&mut self, lit: Lit, reason: Option<usize>) {
+        let var = lit.var();
+        self.values[var] = Some(!lit.is_negated());
+        self.levels[var] = self.trail_limits.len();
+        self.reasons[var] = reason;
+        self.trail.push(lit);
+    }
+
+    /// Propagate the assigned literals, returning a conflicting clause if there is one.
+    fn propagate(&mut self) -> Option<usize> {
+        while self.propagated < self.trail.len() {
+            let lit = self.trail[self.propagated];
+            self.propagated += 1;
+            let false_lit = !lit;
+            let mut watching = mem::take(&mut self.watches[lit.index()]);
+            let mut kept = 0;
+            let mut conflict = None;
+            let mut i = 0;
+            while i < watching.len() {
+                let id = watching[i];
+                i += 1;
+                let clause = &mut self.clauses[id];
+                if clause[0] == false_lit {
+                    clause.swap(0, 1);
+                }
+                let first = clause[0];
+                let first_value = self.values[first.var()].map(|v| v != first.is_negated());
+                if first_value == Some(true) {
+                    watching[kept] = id;
+                    kept += 1;
+                    continue;
+                }
+                // Look for another literal to watch.
+                let values = &self.values;
+                let other = (2..clause.len()).find(|&k| {
+                    let l = clause[k];
+                    values[l.var()].map(|v| v != l.is_negated()) != Some(false)
+                });
+                if let Some(k) = other {
+                    clause.swap(1, k);
+                    let watched = !clause[1];
+                    self.watches[watched.index()].push(id);
+                    continue;
+                }
+
+                watching[kept] = id;
+                kept += 1;
+                if first_value == Some(false) {
+                    conflict = Some(id);
+                    while i < watching.len() {
+                        watching[kept] = watching[i];
+                        kept += 1;
+                        i += 1;
+                    }
+                } else {
+                    self.assign(first, Some(id));
+                }
+            }
+            watching.truncate(kept);
+            self.watches[lit.index()] = watching;
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+        None
+    }
+
+    /// Learn a clause from a conflict, returning it with the level to backtrack to.
+    ///
+    /// The first literal of the clause is the one to assert after backtracking.
+    fn analyze(&mut self, conflict: usize) -> (Vec<Lit>, usize) {
+        let level = self.trail_limits.len();
+        let mut learnt = vec![Lit(0)];
+        let mut pending = 0;
+        let mut index = self.trail.len();
+        let mut reason = conflict;
+        let mut asserted = None;
+        loop {
+            let skip = usize::from(asserted.is_some());
+            for k in skip..self.clauses[reason].len() {
+                let lit = self.clauses[reason][k];
+                let var = lit.var();
+                if self.seen[var] || self.levels[var] == 0 {
+                    continue;
+                }
+                self.seen[var] = true;
+                self.bump(var);
+                if self.levels[var] == level {
+                    pending += 1;
+                } else {
+                    learnt.push(lit);
+                }
+            }
+            // Next literal of the current level to explain.
+            loop {
+                index -= 1;
+                if self.seen[self.trail[index].var()] {
+                    break;
+                }
+            }
+            let lit = self.trail[index];
+            self.seen[lit.var()] = false;
+            asserted = Some(lit);
+            pending -= 1;
+            if pending == 0 {
+                break;
+            }
+            reason = self.reasons[lit.var()].expect("Only decisions have no reason");
+        }
+        learnt[0] = !asserted.unwrap();
+        for lit in &learnt[1..] {
+            self.seen[lit.var()] = false;
+        }
+
+        // Watch a literal of the backtrack level second.
+        let mut backtrack = 0;
+        for k in 1..learnt.len() {
+            let lit_level = self.levels[learnt[k].var()];
+            if lit_level > backtrack {
+                backtrack = lit_level;
+                learnt.swap(1, k);
+            }
+        }
+        (learnt, backtrack)
+    }
+
+    fn bump(&mut self, var: usize) {
+        self.activity[var] += self.activity_inc;
+        if self.activity[var] > 1e100 {
+            self.activity.iter_mut().for_each(|a| *a *= 1e-100);
+            self.activity_inc *= 1e-100;
+            self.order = (0..self.values.len())
+                .map(|v| (OrderedFloat(self.activity[v]), v))
+                .collect();
+        }
+        self.order.push((OrderedFloat(self.activity[var]), var));
+    }
+
+    fn backtrack(&mut self, level: usize) {
+        let Some(&start) = self.trail_limits.get(level) else {
+            return;
+        };
+        for lit in self.trail.drain(start..) {
+            let var = lit.var();
+            self.values[var] = None;
+            self.reasons[var] = None;
+            self.phases[var] = !lit.is_negated();
+            self.order.push((OrderedFloat(self.activity[var]), var));
+        }
+        self.trail_limits.truncate(level);
+        self.propagated = start;
+    }
+
+    /// Unassigned variable of highest activity, if any.
+    fn pick(&mut self) -> Option<usize> {
+        while let Some((_, var)) = self.order.pop() {
+            if self.values[var].is_none() {
+                return Some(var);
+            }
+        }
+        None
+    }
+
+    /// Whether the clauses are satisfiable, stopping early if `budget` is exhausted.
+    ///
+    /// The model can then be read with [`Solver::model_value`].
+    pub(crate) fn solve(&mut self, budget: &Budget) -> Result<bool, Exhausted> {
+        if self.unsat {
+            return Ok(false);
+        }
+        let mut conflicts = 0;
+        let mut restart = 1;
+        let mut next_restart = RESTART_UNIT;
+        loop {
+            if let Some(conflict) = self.propagate() {
+                if self.trail_limits.is_empty() {
+                    self.unsat = true;
+                    return Ok(false);
+                }
+                conflicts += 1;
+                if conflicts % BUDGET_CHECK_INTERVAL == 0 {
+                    budget.check(0)?;
+                }
+                let (learnt, level) = self.analyze(conflict);
+                self.backtrack(level);
+                let asserted = learnt[0];
+                if learnt.len() == 1 {
+                    self.assign(asserted, None);
+                } else {
+                    let id = self.attach(learnt);
+                    self.assign(asserted, Some(id));
+                }
+                self.activity_inc /= VAR_DECAY;
+                continue;
+            }
+
+            if conflicts >= next_restart {
+                restart += 1;
+                next_restart = conflicts + RESTART_UNIT * luby(restart);
+                self.backtrack(0);
+            }
+            let Some(var) = self.pick() else {
+                return Ok(true);
+            };
+            self.trail_limits.push(self.trail.len());
+            self.assign(Lit::new(var, !self.phases[var]), None);
+        }
+    }
+}
+
+/// `i`-th term of the Luby sequence 1, 1, 2, 1, 1, 2, 4, 1, ..., starting from 1.
+fn luby(i: usize) -> usize {
+    let mut i = i;
+    loop {
+        // Smallest `k` such that `i <= 2^k - 1`.
+        let k = usize::BITS - i.leading_zeros();
+        if i == (1 << k) - 1 {
+            return 1 << (k - 1);
+        }
+        i -= (1 << (k - 1)) - 1;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn luby_sequence() {
+        let seq = (1..=15).map(luby).collect::<Vec<_>>();
+        assert_eq!(seq, [1, 1, 2, 1, 1, 2, 4, 1, 1, 2, 1, 1, 2, 4, 8]);
+    }
+
+    #[test]
+    fn pigeonhole() {
+        // `pigeons` pigeons in `holes` holes, each hole holding at most one pigeon.
+        let solve = |pigeons: usize, holes: usize| {
+            let mut s = Solver::new();
+            let x = (0..pigeons)
+                .map(|_| (0..holes).map(|_| s.new_var()).collect::<Vec<_>>())
+                .collect::<Vec<_>>();
+            for p in &x {
+                s.add_clause(p);
+            }
+            for h in 0..holes {
+                for (p, xp) in x.iter().enumerate() {
+                    for xq in &x[..p] {
+                        s.add_clause(&[!xp[h], !xq[h]]);
+                    }
+                }
+            }
+            let sat = s.solve(&Budget::unlimited()).unwrap();
+            if sat {
+                for p in &x {
+                    assert!(p.iter().any(|&l| s.model_value(l)));
+                }
+            }
+            sat
+        };
+        assert!(solve(5, 5));
+        assert!(!solve(6, 5));
+    }
+}
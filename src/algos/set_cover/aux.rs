@@ -1,18 +1,30 @@
+use std::cmp::Reverse;
+
 use fxhash::FxHashSet;
 use log::debug;
 
-use crate::{bool::BoolFormula, formula::apply_binary, ops::binary::LtlBinaryOp};
+use crate::{
+    algos::budget::{Budget, Exhausted},
+    bool::BoolFormula,
+    cache::FormulaCache,
+    formula::apply_binary,
+    ops::binary::LtlBinaryOp,
+};
 
 use super::cache::ScCache;
 
+/// Greedily extend formulas with `op`, maximising `sat_fn`, until they are `covered`.
+#[allow(clippy::too_many_arguments)]
 pub(super) fn aux_set_cover<F>(
     cache: &mut ScCache,
     formulas: Vec<BoolFormula>,
     sat_fn: F,
-    target_sat: usize,
+    covered: impl Fn(&BoolFormula) -> bool,
     op: LtlBinaryOp,
+    op_cost: usize,
     max_nb_formulas: usize,
-) -> Vec<BoolFormula>
+    budget: &Budget,
+) -> Result<Vec<BoolFormula>, Exhausted>
 where
     F: Fn(&BoolFormula) -> usize + Copy,
 {
@@ -20,35 +32,34 @@ where
     let mut formulas: FxHashSet<BoolFormula> = formulas.into_iter().collect();
 
     'run: while !formulas.is_empty() && res.len() < max_nb_formulas {
-        let mut best = take_max_by_key(&mut formulas, |f| sat_fn(f)).unwrap();
+        // Ties are broken in favor of the cheapest formulas.
+        let mut best = take_max_by_key(&mut formulas, |f| (sat_fn(f), Reverse(f.cost))).unwrap();
 
-        while sat_fn(&best) < target_sat {
+        while !covered(&best) {
+            budget.check(cache.len())?;
             if formulas.is_empty() {
                 break 'run;
             }
 
             let (new_best, f) = formulas
                 .iter()
-                .map(|f| (apply_binary(op, &best, f), f))
-                .max_by_key(|(new, _f)| sat_fn(new))
+                .map(|f| (apply_binary(op, op_cost, &best, f), f))
+                .max_by_key(|(new, _f)| (sat_fn(new), Reverse(new.cost)))
                 .unwrap();
             formulas.remove(&f.clone());
             // If no progress has been made, abort.
             if sat_fn(&new_best) == sat_fn(&best) {
                 break 'run;
             }
-            cache.push(best);
-            best = new_best;
+            best = cache.insert(new_best);
         }
 
-        assert_eq!(sat_fn(&best), target_sat);
-        cache.push(best.clone());
         res.push(best);
     }
 
     debug!("Found {} formulas with aux_set_cover", res.len());
 
-    res
+    Ok(res)
 }
 
 fn take_max_by_key<T>(
@@ -1,11 +1,11 @@
-use std::collections::hash_map::{Entry, Values};
+use std::collections::hash_map::Values;
 
 use fxhash::FxHashMap;
 
 use crate::{
     bool::{charac::BoolCharac, BoolFormula},
-    cache::FormulaCache,
-    traits::Hashed,
+    cache::{collisions::CacheKind, find_slot, FormulaCache},
+    traits::{Hashed, Rehash},
 };
 
 pub struct ScCache {
@@ -19,20 +19,24 @@ impl ScCache {
         }
     }
 
-    pub(crate) fn push(&mut self, f: BoolFormula) -> bool {
-        match self.entries.entry(f.hashed()) {
-            Entry::Occupied(mut e) => {
-                let sz = e.get().size;
-                if sz > f.size {
-                    e.insert(f);
-                    true
-                } else {
-                    false
-                }
+    /// Add a formula to the cache, unless it contains a cheaper equal one,
+    /// and return the formula stored for its characteristic vector.
+    ///
+    /// The returned formula may be used as a child of new formulas.
+    pub(crate) fn insert(&mut self, mut f: BoolFormula) -> BoolFormula {
+        let equal = find_slot(&mut f, CacheKind::SetCover, |hash| {
+            self.entries.get(hash).map(|g| &g.charac)
+        });
+        match equal {
+            Some(hash) if self.entries[&hash].cost <= f.cost => self.entries[&hash].clone(),
+            Some(hash) => {
+                f.charac.set_hash(hash);
+                self.entries.insert(hash, f.clone());
+                f
             }
-            Entry::Vacant(e) => {
-                e.insert(f);
-                false
+            None => {
+                self.entries.insert(f.hashed(), f.clone());
+                f
             }
         }
     }
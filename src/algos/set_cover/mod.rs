@@ -12,12 +12,17 @@ use log::info;
 
 use crate::{
     bool::{charac::BoolCharac, cv::CharVec, BoolFormula},
-    formula::{rebuild_formula, tree::FormulaTree},
+    formula::{apply_unary, cost::CostModel, rebuild_formula, tree::FormulaTree},
     ltl::trace::Operators,
-    ops::binary::LtlBinaryOp,
+    ops::{binary::LtlBinaryOp, unary::LtlUnaryOp},
+    traits::EqTarget,
 };
 
-use super::{meta::cache::InitialBoolCache, BoolAlgoParams};
+use super::{
+    budget::{Budget, Exhausted},
+    meta::cache::InitialBoolCache,
+    BoolAlgoParams,
+};
 
 #[derive(Args, Clone, Copy)]
 pub struct SetCoverParams {
@@ -27,19 +32,41 @@ pub struct SetCoverParams {
     placeholder: usize,
 }
 
+impl SetCoverParams {
+    pub fn new(max_nb_formulas: usize) -> Self {
+        Self {
+            max_nb_formulas,
+            placeholder: 0,
+        }
+    }
+}
+
 impl BoolAlgoParams for SetCoverParams {
     type Data = ();
 
     fn run(
         &self,
         cache: InitialBoolCache,
-        _operators: Operators,
+        operators: Operators,
         target: &[bool],
-    ) -> (Option<FormulaTree>, Self::Data) {
-        let target_cv = target.iter().copied().collect();
-        let mut sc_cache = convert_cache_sc(cache, target_cv);
-        let f = set_cover_bool(&mut sc_cache, target, self.max_nb_formulas);
-        let f_str = f.map(|f| rebuild_formula(&f, &sc_cache));
+        max_errors: usize,
+        budget: &Budget,
+    ) -> (Result<Option<FormulaTree>, Exhausted>, Self::Data) {
+        let target_cv: CharVec = target.iter().copied().collect();
+        let negate = operators
+            .unary
+            .contains(&LtlUnaryOp::Not)
+            .then(|| operators.costs.unary(LtlUnaryOp::Not));
+        let mut sc_cache = convert_cache_sc(cache, &target_cv, negate);
+        let f = set_cover_bool(
+            &mut sc_cache,
+            &target_cv,
+            &operators.costs,
+            self.max_nb_formulas,
+            max_errors,
+            budget,
+        );
+        let f_str = f.map(|f| f.map(|f| rebuild_formula(&f, &sc_cache)));
         (f_str, ())
     }
 
@@ -48,69 +75,136 @@ impl BoolAlgoParams for SetCoverParams {
     }
 }
 
+/// Greedy covers of the positive traces, then of the negative ones, and conversely.
+///
+/// A cover is complete once all the traces of its kind are satisfied,
+/// or once the formula misclassifies at most `max_errors` traces of `target`.
 fn set_cover_bool(
     cache: &mut ScCache,
-    target: &[bool],
+    target: &CharVec,
+    costs: &CostModel,
     max_nb_formulas: usize,
-) -> Option<BoolFormula> {
-    let positive_count = target.iter().filter(|b| **b).count();
+    max_errors: usize,
+    budget: &Budget,
+) -> Result<Option<BoolFormula>, Exhausted> {
+    let positive_count = target.count_ones();
     let negative_count = target.len() - positive_count;
+    let positives_covered = |f: &BoolFormula| {
+        f.sat_positive_count() == positive_count || f.eq_target(target, max_errors)
+    };
+    let negatives_covered = |f: &BoolFormula| {
+        f.sat_negative_count() == negative_count || f.eq_target(target, max_errors)
+    };
 
     let formulas: Vec<_> = cache.into_iter().cloned().collect();
 
     info!("Computing C_p");
-    let cp = positive_set_cover(cache, formulas.clone(), positive_count, max_nb_formulas);
+    let cp = positive_set_cover(
+        cache,
+        formulas.clone(),
+        positives_covered,
+        costs,
+        max_nb_formulas,
+        budget,
+    )?;
     info!("Computing C_p,n");
-    let cpn = negative_set_cover(cache, cp, negative_count, max_nb_formulas);
+    let cpn = negative_set_cover(cache, cp, negatives_covered, costs, max_nb_formulas, budget)?;
 
     info!("Computing C_n");
-    let cn = negative_set_cover(cache, formulas, negative_count, max_nb_formulas);
+    let cn = negative_set_cover(
+        cache,
+        formulas,
+        negatives_covered,
+        costs,
+        max_nb_formulas,
+        budget,
+    )?;
     info!("Computing C_n,p");
-    let cnp = positive_set_cover(cache, cn, positive_count, max_nb_formulas);
-
-    cpn.into_iter().chain(cnp).min_by_key(|f| f.size)
+    let cnp = positive_set_cover(cache, cn, positives_covered, costs, max_nb_formulas, budget)?;
+
+    // Merging formulas which each misclassify a few traces can exceed `max_errors`.
+    Ok(cpn
+        .into_iter()
+        .chain(cnp)
+        .filter(|f| f.eq_target(target, max_errors))
+        .min_by_key(|f| f.cost))
 }
 
 fn positive_set_cover(
     cache: &mut ScCache,
     formulas: Vec<BoolFormula>,
-    positive_count: usize,
+    covered: impl Fn(&BoolFormula) -> bool + Copy,
+    costs: &CostModel,
     max_nb_formulas: usize,
-) -> Vec<BoolFormula> {
+    budget: &Budget,
+) -> Result<Vec<BoolFormula>, Exhausted> {
     aux_set_cover(
         cache,
         formulas,
         |f| f.sat_positive_count(),
-        positive_count,
+        covered,
         LtlBinaryOp::Or,
+        costs.binary(LtlBinaryOp::Or),
         max_nb_formulas,
+        budget,
     )
 }
 
 fn negative_set_cover(
     cache: &mut ScCache,
     formulas: Vec<BoolFormula>,
-    negative_count: usize,
+    covered: impl Fn(&BoolFormula) -> bool + Copy,
+    costs: &CostModel,
     max_nb_formulas: usize,
-) -> Vec<BoolFormula> {
+    budget: &Budget,
+) -> Result<Vec<BoolFormula>, Exhausted> {
     aux_set_cover(
         cache,
         formulas,
         |f| f.sat_negative_count(),
-        negative_count,
+        covered,
         LtlBinaryOp::And,
+        costs.binary(LtlBinaryOp::And),
         max_nb_formulas,
+        budget,
     )
 }
 
-fn convert_cache_sc(bool_cache: InitialBoolCache, target: CharVec) -> ScCache {
+/// Build the initial cache of the set cover algorithm.
+///
+/// If `negate` is set to the weight of the negation, the negation of each formula is also added,
+/// so that formulas rejecting most positive (resp. accepting most negative) traces
+/// can be used for the covers.
+fn convert_cache_sc(
+    bool_cache: InitialBoolCache,
+    target: &CharVec,
+    negate: Option<usize>,
+) -> ScCache {
     let mut sc_cache = ScCache::new();
 
     for (cv, t, size) in bool_cache {
         let cv = cv.into_iter().collect();
         let f = BoolFormula::new_base(BoolCharac::from_cv(cv, target), size, t);
-        sc_cache.push(f);
+        let f = sc_cache.insert(f);
+        if let Some(cost) = negate {
+            sc_cache.insert(apply_unary(LtlUnaryOp::Not, cost, &f));
+        }
     }
 
     sc_cache
 }
+
+#[cfg(test)]
+mod test {
+    use crate::algos::meta::test::run_with_degenerate_hash;
+
+    use super::*;
+
+    #[test]
+    fn learns_with_hash_collisions() {
+        run_with_degenerate_hash(SetCoverParams {
+            max_nb_formulas: 4,
+            placeholder: 0,
+        });
+    }
+}
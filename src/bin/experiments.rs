@@ -1,89 +1,125 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, process};
 
 use clap::{Parser, Subcommand};
 
+use itertools::Itertools;
 use log::info;
 use ltl_rs::{
     algos::{
-        beam_search::BeamSearchParams, enumeration::EnumParams, meta::divide_conquer,
-        set_cover::SetCoverParams, BoolAlgoParams,
+        beam_search::BeamSearchParams,
+        budget::{Budget, BudgetParams},
+        enumeration::EnumParams,
+        meta::{divide_conquer, MetaParams},
+        portfolio::{PortfolioData, PortfolioParams},
+        set_cover::SetCoverParams,
+        BoolAlgoParams,
+    },
+    formula::{cost::Weight, tree::FormulaTree},
+    ltl::{
+        cs::Semantics,
+        trace::{traces_from_file, ParsedTraces},
     },
-    formula::tree::FormulaTree,
-    ltl::trace::{traces_from_file, Operators, Trace},
 };
 
 fn main() {
     env_logger::init();
 
     let args = CliArgs::parse();
-    let (traces, alphabet, target, operators) = traces_from_file(&args.input_filename);
+    let (traces, alphabet, target, operators) = traces_from_file(&args.input_filename)
+        .unwrap_or_else(|e| {
+            eprintln!("{}: {e}", args.input_filename.display());
+            process::exit(1)
+        });
+    let traces = traces
+        .into_iter()
+        .map(|t| t.with_semantics(args.semantics))
+        .collect();
+    let operators = operators.with_weights(args.costs.iter().cloned());
+    let budget = args.budget.start();
+    let parsed = (traces, alphabet, target, operators);
 
-    let (time, sol, name) = match args.command {
-        AlgoCommand::Enum(p) => get_name_time_sol(
-            traces,
-            alphabet,
-            operators,
-            target,
-            args.max_size_ltl,
-            args.domin_nb,
-            p,
-        ),
-        AlgoCommand::SetCover(p) => get_name_time_sol(
-            traces,
-            alphabet,
-            operators,
-            target,
-            args.max_size_ltl,
-            args.domin_nb,
-            p,
-        ),
-        AlgoCommand::BeamSearch(p) => get_name_time_sol(
-            traces,
-            alphabet,
-            operators,
-            target,
-            args.max_size_ltl,
-            args.domin_nb,
-            p,
-        ),
+    let (time, sol, name, columns) = match args.command {
+        AlgoCommand::Enum(p) => get_name_time_sol(parsed, &args.meta_params, p, &budget, |_| None),
+        AlgoCommand::SetCover(p) => {
+            get_name_time_sol(parsed, &args.meta_params, p, &budget, |_| None)
+        }
+        AlgoCommand::BeamSearch(p) => {
+            get_name_time_sol(parsed, &args.meta_params, p, &budget, |_| None)
+        }
+        AlgoCommand::Portfolio(p) => {
+            get_name_time_sol(parsed, &args.meta_params, p, &budget, portfolio_columns)
+        }
     };
 
     println!(
-        "rust_{}, {}, {:.5}, {}, {}",
+        "rust_{}, {}, {:.5}, {}, {}{}",
         name,
         args.input_filename.to_string_lossy(),
         time,
         sol.as_ref().map_or(-1, |f| f.size() as isize),
+        columns.map_or(String::new(), |c| format!("{c}, ")),
         sol.map_or(String::new(), |f| format!("{f}"))
     )
 }
 
+/// Columns describing the runs of a portfolio: the names of the members which found a formula
+/// in each run, or `none`, and the total running time of each member over all the runs,
+/// e.g. `set_cover bool_enum, set_cover:0.00100 bool_enum:0.12000`.
+///
+/// There are no columns if the portfolio was not run.
+fn portfolio_columns(runs: &[PortfolioData]) -> Option<String> {
+    let members = runs.first()?.times.iter().map(|&(name, _)| name);
+    let winners = runs
+        .iter()
+        .filter_map(|data| data.winner.map(|i| data.times[i].0))
+        .join(" ");
+    let times = members
+        .enumerate()
+        .map(|(i, name)| {
+            let time: f64 = runs.iter().map(|data| data.times[i].1.as_secs_f64()).sum();
+            format!("{name}:{time:.5}")
+        })
+        .join(" ");
+    let winners = if winners.is_empty() {
+        "none".to_owned()
+    } else {
+        winners
+    };
+    Some(format!("{winners}, {times}"))
+}
+
+/// Learn a formula, with extra columns given by `columns` from the data of the
+/// runs of the Boolean algorithm.
 fn get_name_time_sol<P: BoolAlgoParams + Clone>(
-    traces: Vec<Trace>,
-    alphabet: Vec<String>,
-    operators: Operators,
-    target: Vec<bool>,
-    max_size_ltl: usize,
-    domin_nb: usize,
+    (traces, alphabet, target, operators): ParsedTraces,
+    meta_params: &MetaParams,
     params: P,
-) -> (f64, Option<FormulaTree>, &'static str) {
+    budget: &Budget,
+    columns: impl Fn(&[P::Data]) -> Option<String>,
+) -> (f64, Option<FormulaTree>, &'static str, Option<String>) {
     let res = divide_conquer(
         &traces,
         alphabet,
         operators,
         target.clone(),
-        max_size_ltl,
-        domin_nb,
+        meta_params,
         params,
+        budget,
     );
 
+    let max_errors = meta_params.allowed_errors(target.len());
     if let Some(t) = res.sol() {
-        let actual_value = t.eval(&traces).accepted_vec();
-        assert_eq!(actual_value, target);
+        let errors = t.classify(&traces, &target).errors();
+        assert!(errors <= max_errors);
         info!("Correctness check OK!");
     }
 
-    (res.total_time_sec(), res.sol(), P::name())
+    (
+        res.total_time_sec(),
+        res.sol(),
+        P::name(),
+        columns(&res.algo_data),
+    )
 }
 
 #[derive(Parser)]
@@ -91,12 +127,16 @@ fn get_name_time_sol<P: BoolAlgoParams + Clone>(
 struct CliArgs {
     /// Name of the .trace file to read.
     input_filename: PathBuf,
-    /// Run LTL enumeration until `max_size_ltl`
-    /// before switching to boolean algorithm.
-    max_size_ltl: usize,
-    /// Number of candidates to use for domination checking
-    /// in the step that converts LTL formulas to boolean formulas.
-    domin_nb: usize,
+    /// Interpretation of temporal operators at the end of finite traces.
+    #[arg(long, value_enum, default_value_t)]
+    semantics: Semantics,
+    /// Weight of an operator or predicate in the cost of formulas, e.g. `U=3` or `p=2`.
+    #[arg(long = "cost", value_name = "NAME=WEIGHT")]
+    costs: Vec<Weight>,
+    #[command(flatten)]
+    budget: BudgetParams,
+    #[command(flatten)]
+    meta_params: MetaParams,
     #[command(subcommand)]
     command: AlgoCommand,
 }
@@ -109,4 +149,6 @@ enum AlgoCommand {
     SetCover(SetCoverParams),
     /// Bottom-up beam search
     BeamSearch(BeamSearchParams),
+    /// Run several of the above algorithms and keep the best result
+    Portfolio(PortfolioParams),
 }
@@ -0,0 +1,141 @@
+//! Fixed-length bit vectors backing characteristic and satisfiability vectors.
+use std::ops::Not;
+
+/// Number of bits stored in a single block of [`Bits`].
+pub(super) const BLOCK_BITS: usize = u128::BITS as usize;
+
+/// Bits of a [`CharVec`](super::cv::CharVec) or a [`SatVec`](super::sv::SatVec),
+/// least significant block first.
+///
+/// Invariant: [`Bits::Inline`] is used if and only if the vector has at most 128 bits,
+/// so that the derived equality and hash only depend on the bits.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub(super) enum Bits {
+    /// Vectors of at most 128 bits, stored without allocation.
+    Inline(u128),
+    /// Longer vectors.
+    Blocks(Box<[u128]>),
+}
+
+/// Mask of the bits of the last block of a vector of length `length`.
+#[inline]
+fn last_block_mask(length: usize) -> u128 {
+    match length % BLOCK_BITS {
+        0 if length > 0 => u128::MAX,
+        r => (1u128 << r) - 1,
+    }
+}
+
+impl Bits {
+    /// Collect the bits of `iter`, and return them together with their number.
+    pub(super) fn collect_with_len(iter: impl IntoIterator<Item = bool>) -> (Self, usize) {
+        let mut blocks = vec![0u128];
+        let mut len = 0;
+        for b in iter {
+            if len > 0 && len % BLOCK_BITS == 0 {
+                blocks.push(0);
+            }
+            if b {
+                *blocks.last_mut().unwrap() |= 1 << (len % BLOCK_BITS);
+            }
+            len += 1;
+        }
+        let bits = if len <= BLOCK_BITS {
+            Bits::Inline(blocks[0])
+        } else {
+            Bits::Blocks(blocks.into_boxed_slice())
+        };
+        (bits, len)
+    }
+
+    pub(super) fn blocks(&self) -> &[u128] {
+        match self {
+            Bits::Inline(x) => std::slice::from_ref(x),
+            Bits::Blocks(xs) => xs,
+        }
+    }
+
+    fn blocks_mut(&mut self) -> &mut [u128] {
+        match self {
+            Bits::Inline(x) => std::slice::from_mut(x),
+            Bits::Blocks(xs) => xs,
+        }
+    }
+
+    /// Whether bit `i` is set.
+    #[inline]
+    pub(super) fn get(&self, i: usize) -> bool {
+        (self.blocks()[i / BLOCK_BITS] >> (i % BLOCK_BITS)) & 1 == 1
+    }
+
+    /// Apply `f` block by block to `self` and `rhs`.
+    #[inline]
+    pub(super) fn zip_with(&self, rhs: &Self, f: impl Fn(u128, u128) -> u128) -> Self {
+        match (self, rhs) {
+            (&Bits::Inline(x), &Bits::Inline(y)) => Bits::Inline(f(x, y)),
+            _ => {
+                assert_eq!(self.blocks().len(), rhs.blocks().len());
+                let blocks = self
+                    .blocks()
+                    .iter()
+                    .zip(rhs.blocks())
+                    .map(|(&x, &y)| f(x, y))
+                    .collect();
+                Bits::Blocks(blocks)
+            }
+        }
+    }
+
+    /// Number of set bits in the result of `f` applied block by block to `self` and `rhs`,
+    /// without allocating the result.
+    #[inline]
+    pub(super) fn count_ones_with(&self, rhs: &Self, f: impl Fn(u128, u128) -> u128) -> usize {
+        match (self, rhs) {
+            (&Bits::Inline(x), &Bits::Inline(y)) => f(x, y).count_ones() as usize,
+            _ => self
+                .blocks()
+                .iter()
+                .zip(rhs.blocks())
+                .map(|(&x, &y)| f(x, y).count_ones() as usize)
+                .sum(),
+        }
+    }
+
+    /// Whether `f` applied block by block to `self` and `rhs` is zero everywhere.
+    #[inline]
+    pub(super) fn all_zero_with(&self, rhs: &Self, f: impl Fn(u128, u128) -> u128) -> bool {
+        match (self, rhs) {
+            (&Bits::Inline(x), &Bits::Inline(y)) => f(x, y) == 0,
+            _ => self
+                .blocks()
+                .iter()
+                .zip(rhs.blocks())
+                .all(|(&x, &y)| f(x, y) == 0),
+        }
+    }
+
+    pub(super) fn count_ones(&self) -> usize {
+        self.blocks().iter().map(|x| x.count_ones() as usize).sum()
+    }
+
+    /// Complement of the first `length` bits, leaving the others unset.
+    #[inline]
+    pub(super) fn not(&self, length: usize) -> Self {
+        let mut res = self.clone();
+        let blocks = res.blocks_mut();
+        blocks.iter_mut().for_each(|x| *x = x.not());
+        if let Some(last) = blocks.last_mut() {
+            *last &= last_block_mask(length);
+        }
+        res
+    }
+
+    /// Unset the bits after the first `length` ones.
+    #[inline]
+    pub(super) fn truncate(mut self, length: usize) -> Self {
+        if let Some(last) = self.blocks_mut().last_mut() {
+            *last &= last_block_mask(length);
+        }
+        self
+    }
+}
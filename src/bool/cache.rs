@@ -1,13 +1,14 @@
-use std::{
-    cmp::Ordering,
-    collections::{hash_map::Entry, BinaryHeap},
-};
+use std::{cmp::Ordering, collections::BinaryHeap};
 
 use fxhash::FxHashMap;
 use itertools::Itertools;
 
 use crate::{
-    cache::{EnumFormulaCache, EnumFormulaCacheLine, FormulaCache},
+    cache::{
+        collisions::CacheKind,
+        enum_cache::{binary_child_lines, unary_child_lines},
+        find_slot, EnumFormulaCache, EnumFormulaCacheLine, FormulaCache,
+    },
     traits::Hashed,
 };
 
@@ -34,6 +35,11 @@ impl BoolCache {
     pub(crate) fn iter_lines(self) -> impl IntoIterator<Item = Vec<BoolFormula>> {
         self.lines
     }
+
+    /// Formulas of the cache, by cost.
+    pub(crate) fn lines(&self) -> &[Vec<BoolFormula>] {
+        &self.lines
+    }
 }
 
 impl FormulaCache<BoolCharac> for BoolCache {
@@ -50,16 +56,11 @@ impl FormulaCache<BoolCharac> for BoolCache {
 impl EnumFormulaCache<BoolCharac> for BoolCache {
     type CacheLine<'a> = BoolCacheLine<'a>;
 
-    fn iter_size<'a>(&'a self, size: usize) -> impl Iterator<Item = &'a BoolFormula>
-    where
-        BoolCharac: 'a,
-    {
-        self.lines[size].iter()
-    }
-
     fn new_line_and_iter_size<'a>(
         &'a mut self,
         size: usize,
+        unary_costs: &[usize],
+        binary_costs: &[usize],
     ) -> (
         impl Iterator<Item = &'a crate::formula::Formula<BoolCharac>>,
         impl Iterator<
@@ -75,27 +76,27 @@ impl EnumFormulaCache<BoolCharac> for BoolCache {
     {
         self.lines.push(vec![]);
         let (old_lines, new) = self.lines.split_at_mut(size);
+        let old_lines: &'a [_] = old_lines;
         self.best_sv.push(BinaryHeap::new());
         let (old_heaps, new_heap) = self.best_sv.split_at_mut(size);
 
         let new_line = BoolCacheLine {
             size_index: size,
             entries: &mut new[0],
+            old_lines,
             hashes: &mut self.hash_to_line,
             best_sv: &mut new_heap[0],
             other_heaps: old_heaps,
             k: self.k,
         };
 
-        let iter_size = size - 1;
-        let iter_formulas = old_lines[iter_size].iter();
+        let iter_formulas = unary_child_lines(size, unary_costs)
+            .into_iter()
+            .flat_map(|i| old_lines[i].iter());
 
-        let iter_pairs_size = (size + 1) / 2;
-        let iter_pairs = old_lines
-            .iter()
-            .zip(old_lines.iter().rev())
-            .take(iter_pairs_size)
-            .flat_map(|(i1, i2)| i1.iter().cartesian_product(i2));
+        let iter_pairs = binary_child_lines(size, binary_costs)
+            .into_iter()
+            .flat_map(|(i, j)| old_lines[i].iter().cartesian_product(&old_lines[j]));
         (iter_formulas, iter_pairs, new_line)
     }
 
@@ -104,12 +105,14 @@ impl EnumFormulaCache<BoolCharac> for BoolCache {
         BoolCharac: 'a,
     {
         self.lines.push(vec![]);
+        let (old_lines, new) = self.lines.split_at_mut(size);
         self.best_sv.push(BinaryHeap::new());
         let (old_heaps, new_heap) = self.best_sv.split_at_mut(size);
 
         BoolCacheLine {
             size_index: size,
-            entries: &mut self.lines[size],
+            entries: &mut new[0],
+            old_lines,
             hashes: &mut self.hash_to_line,
             best_sv: &mut new_heap[0],
             other_heaps: old_heaps,
@@ -134,6 +137,8 @@ impl IntoIterator for BoolCache {
 
 pub(crate) struct BoolCacheLine<'a> {
     entries: &'a mut Vec<BoolFormula>,
+    /// Lines of lower costs, to check equality with their formulas on hash matches.
+    old_lines: &'a [Vec<BoolFormula>],
     best_sv: &'a mut BinaryHeap<SvHash>,
     hashes: &'a mut FxHashMap<<BoolCharac as Hashed>::HashType, (usize, usize)>,
     other_heaps: &'a [BinaryHeap<SvHash>],
@@ -163,29 +168,37 @@ impl<'a> BoolCacheLine<'a> {
 }
 
 impl<'a> EnumFormulaCacheLine<BoolCharac> for BoolCacheLine<'a> {
-    fn push(&mut self, f: BoolFormula) -> bool {
-        assert_eq!(f.size, self.size_index);
+    fn push(&mut self, mut f: BoolFormula) -> bool {
+        assert_eq!(f.cost, self.size_index);
         if self.dominates(&f).is_some() {
             return false;
         }
 
+        let equal = find_slot(&mut f, CacheKind::Bool, |hash| {
+            let &(line_id, index) = self.hashes.get(hash)?;
+            let line = if line_id == self.size_index {
+                &self.entries[..]
+            } else {
+                &self.old_lines[line_id][..]
+            };
+            Some(&line[index].charac)
+        });
+        if equal.is_some() {
+            return false;
+        }
+
         let hash = f.hashed();
-        match self.hashes.entry(hash) {
-            Entry::Occupied(_) => false,
-            Entry::Vacant(e) => {
-                let index = self.entries.len();
-                e.insert((self.size_index, index));
-                self.best_sv.push(SvHash {
-                    sv: f.charac.sv,
-                    hash,
-                });
-                if self.best_sv.len() > self.k {
-                    self.best_sv.pop();
-                }
-                self.entries.push(f);
-                true
-            }
+        let index = self.entries.len();
+        self.hashes.insert(hash, (self.size_index, index));
+        self.best_sv.push(SvHash {
+            sv: f.charac.sv.clone(),
+            hash,
+        });
+        if self.best_sv.len() > self.k {
+            self.best_sv.pop();
         }
+        self.entries.push(f);
+        true
     }
 }
 
@@ -212,13 +225,13 @@ impl Ord for SvHash {
 
 impl SvHash {
     pub(crate) fn dominates(&self, f: &BoolFormula) -> bool {
-        self.sv.dominates(f.charac.sv)
+        self.sv.dominates(&f.charac.sv)
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::bool::hash::BoolHash;
+    use crate::bool::{bits::Bits, hash::BoolHash};
 
     use super::*;
 
@@ -226,21 +239,27 @@ mod test {
     fn sv_hash_ordering_in_heap() {
         let mut h = BinaryHeap::new();
         h.push(SvHash {
-            sv: SatVec { values: 42 },
+            sv: SatVec {
+                values: Bits::Inline(42),
+            },
             hash: BoolHash(0),
         });
 
         h.push(SvHash {
-            sv: SatVec { values: 1 },
+            sv: SatVec {
+                values: Bits::Inline(1),
+            },
             hash: BoolHash(0),
         });
 
         h.push(SvHash {
-            sv: SatVec { values: 7 },
+            sv: SatVec {
+                values: Bits::Inline(7),
+            },
             hash: BoolHash(0),
         });
 
         // Ensure that we get min popcount first
-        assert_eq!(h.pop().unwrap().sv.values, 1);
+        assert_eq!(h.pop().unwrap().sv.popcount(), 1);
     }
 }
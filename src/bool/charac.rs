@@ -8,7 +8,7 @@ use crate::{
         traits::{BinaryOp, UnaryOp},
         unary::LtlUnaryOp,
     },
-    traits::{EqTarget, Hashed},
+    traits::{EqTarget, Hashed, Rehash},
 };
 
 use super::{cv::CharVec, hash::BoolHash, sv::SatVec};
@@ -23,34 +23,44 @@ pub struct BoolCharac {
 }
 
 impl BoolCharac {
-    pub(crate) fn from_cv(cv: CharVec, target: CharVec) -> Self {
+    pub(crate) fn from_cv(cv: CharVec, target: &CharVec) -> Self {
         let sv = cv.satisfied(target);
         let cv_hash = cv.hashed();
         BoolCharac { cv, sv, cv_hash }
     }
 
     pub(crate) fn sat_positive_count(&self) -> usize {
-        let v = self.cv.values & self.sv.values;
-        v.count_ones() as usize
+        self.cv
+            .values
+            .count_ones_with(&self.sv.values, |cv, sv| cv & sv)
     }
 
     pub(crate) fn sat_negative_count(&self) -> usize {
-        let v = (!self.cv.values) & self.sv.values;
-        v.count_ones() as usize
+        self.cv
+            .values
+            .count_ones_with(&self.sv.values, |cv, sv| !cv & sv)
     }
 }
 
 impl UnaryOp for BoolCharac {
-    fn apply(_op: LtlUnaryOp, _f: &Self) -> Self {
-        unimplemented!("Never used, only needed for trait bounds")
+    fn apply(op: LtlUnaryOp, f: &Self) -> Self {
+        assert!(
+            op.is_boolean(),
+            "Temporal operator applied to a boolean formula"
+        );
+        let cv = !&f.cv;
+        let not_target = f.cv.xor_satvec(&f.sv);
+        let sv = cv.xor_satvec(&not_target);
+        let cv_hash = cv.hashed();
+        Self { cv, sv, cv_hash }
     }
 }
 
 impl BinaryOp for BoolCharac {
     fn apply(op: LtlBinaryOp, f1: &Self, f2: &Self) -> Self {
-        let cv = LtlBinaryOp::apply_cv(op, f1.cv, f2.cv);
-        let not_target = f1.cv.xor_satvec(f1.sv);
-        let sv = cv.xor_satvec(not_target);
+        let cv = LtlBinaryOp::apply_cv(op, &f1.cv, &f2.cv);
+        let not_target = f1.cv.xor_satvec(&f1.sv);
+        let sv = cv.xor_satvec(&not_target);
         let cv_hash = cv.hashed();
         Self { cv, sv, cv_hash }
     }
@@ -60,16 +70,69 @@ impl Hashed for BoolCharac {
     type HashType = BoolHash;
 
     // Note: it is more efficient to store the hash of the `cv` and use it for the equivalence test
-    // instead of using the `cv` directly, as the `cv` contains a [`u128`] which take more time to hash.
+    // instead of using the `cv` directly, as the `cv` contains at least a [`u128`] which takes more time to hash.
     fn hashed(&self) -> Self::HashType {
         self.cv_hash
     }
 }
 
+impl Rehash for BoolCharac {
+    fn set_hash(&mut self, hash: BoolHash) {
+        self.cv_hash = hash;
+    }
+
+    fn next_hash(hash: BoolHash) -> BoolHash {
+        BoolHash(hash.0.wrapping_add(1))
+    }
+
+    fn same_value(&self, other: &Self) -> bool {
+        self.cv == other.cv
+    }
+}
+
 impl EqTarget for BoolCharac {
     type TargetType = CharVec;
 
-    fn eq_target(&self, target: &Self::TargetType) -> bool {
-        self.cv == *target
+    fn errors(&self, target: &Self::TargetType) -> usize {
+        self.cv
+            .values
+            .count_ones_with(&target.values, |cv, target| cv ^ target)
+    }
+
+    fn eq_target(&self, target: &Self::TargetType, max_errors: usize) -> bool {
+        if max_errors == 0 {
+            self.cv == *target
+        } else {
+            self.errors(target) <= max_errors
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn not_keeps_sv_consistent() {
+        let target: CharVec = [true, false, true, true, false].into_iter().collect();
+        let cv: CharVec = [true, true, false, true, false].into_iter().collect();
+        let f = BoolCharac::from_cv(cv.clone(), &target);
+
+        let not_f = <BoolCharac as UnaryOp>::apply(LtlUnaryOp::Not, &f);
+        assert_eq!(not_f, BoolCharac::from_cv(!&cv, &target));
+        assert_eq!(not_f.sv.popcount(), 5 - f.sv.popcount());
+    }
+
+    #[test]
+    fn counts_errors() {
+        let target: CharVec = [true, false, true, true, false].into_iter().collect();
+        let cv: CharVec = [true, true, false, true, false].into_iter().collect();
+        let f = BoolCharac::from_cv(cv, &target);
+
+        assert_eq!(f.errors(&target), 2);
+        assert!(!f.eq_target(&target, 0));
+        assert!(!f.eq_target(&target, 1));
+        assert!(f.eq_target(&target, 2));
+        assert!(f.eq_target(&f.cv.clone(), 0));
     }
 }
@@ -5,49 +5,88 @@ use std::{
     ops::{BitAnd, BitOr, BitXor, Not},
 };
 
-use crate::HashType;
+use crate::{cache::collisions::CacheHasher, HashType};
 
-use super::{hash::BoolHash, sv::SatVec};
+use super::{bits::Bits, hash::BoolHash, sv::SatVec};
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Clone, PartialEq, Eq, Hash)]
 /// Represents the truth table of a formula over a set of inputs.
+///
+/// Vectors over at most 128 inputs are stored without allocation.
 pub(crate) struct CharVec {
-    pub(super) values: u128,
-    pub(super) length: u8,
+    pub(super) values: Bits,
+    pub(super) length: usize,
 }
 
-type CvHasher = fxhash::FxHasher64;
-
 impl CharVec {
     pub(crate) fn len(&self) -> usize {
-        self.length as usize
+        self.length
+    }
+
+    /// Number of inputs on which the formula is true.
+    pub(crate) fn count_ones(&self) -> usize {
+        self.values.count_ones()
     }
 
     pub fn hashed(&self) -> BoolHash {
-        let mut h = CvHasher::default();
+        let mut h = CacheHasher::default();
         self.hash(&mut h);
         BoolHash(h.finish() as HashType)
     }
 
     /// Returns the characteristic vector of satisfied inputs
-    pub(crate) fn satisfied(&self, target: CharVec) -> SatVec {
+    pub(crate) fn satisfied(&self, target: &CharVec) -> SatVec {
         assert_eq!(self.length, target.length);
-        let values = (self.values & target.values) | (self.values | target.values).not();
-
-        // Edge case: shifting 1u128 by 128 gives 1 in release mode, and panics in debug mode.
-        let values = if self.length < 128 {
-            values & ((1u128 << self.length) - 1)
-        } else {
-            values
-        };
+        let values = self
+            .values
+            .zip_with(&target.values, |x, y| (x & y) | (x | y).not())
+            .truncate(self.length);
         SatVec { values }
     }
 
     #[inline]
-    pub(crate) fn xor_satvec(&self, sv: SatVec) -> SatVec {
-        let values = self.values.bitxor(sv.values);
+    pub(crate) fn xor_satvec(&self, sv: &SatVec) -> SatVec {
+        let values = self.values.zip_with(&sv.values, u128::bitxor);
         SatVec { values }
     }
+
+    /// Boolean implication `self -> rhs`.
+    #[inline]
+    pub(crate) fn implies(&self, rhs: &CharVec) -> CharVec {
+        let values = self
+            .values
+            .zip_with(&rhs.values, |x, y| x.not() | y)
+            .truncate(self.length);
+        CharVec {
+            values,
+            length: self.length,
+        }
+    }
+
+    /// Boolean equivalence `self <-> rhs`.
+    #[inline]
+    pub(crate) fn equiv(&self, rhs: &CharVec) -> CharVec {
+        let values = self
+            .values
+            .zip_with(&rhs.values, |x, y| (x ^ y).not())
+            .truncate(self.length);
+        CharVec {
+            values,
+            length: self.length,
+        }
+    }
+}
+
+impl Not for &CharVec {
+    type Output = CharVec;
+
+    #[inline]
+    fn not(self) -> Self::Output {
+        CharVec {
+            values: self.values.not(self.length),
+            length: self.length,
+        }
+    }
 }
 
 impl Not for CharVec {
@@ -55,15 +94,19 @@ impl Not for CharVec {
 
     #[inline]
     fn not(self) -> Self::Output {
-        let CharVec { values: x, length } = self;
-        let values = x.not();
-        // Edge case: shifting 1u128 by 128 gives 1 in release mode, and panics in debug mode.
-        let values = if self.length < 128 {
-            values & ((1u128 << self.length) - 1)
-        } else {
-            values
-        };
-        CharVec { values, length }
+        (&self).not()
+    }
+}
+
+impl BitOr for &CharVec {
+    type Output = CharVec;
+
+    #[inline]
+    fn bitor(self, rhs: Self) -> Self::Output {
+        CharVec {
+            values: self.values.zip_with(&rhs.values, u128::bitor),
+            length: self.length,
+        }
     }
 }
 
@@ -72,14 +115,18 @@ impl BitOr for CharVec {
 
     #[inline]
     fn bitor(self, rhs: Self) -> Self::Output {
-        let CharVec { values: x, length } = self;
-        let CharVec {
-            values: y,
-            length: _l2,
-        } = rhs;
+        (&self).bitor(&rhs)
+    }
+}
+
+impl BitAnd for &CharVec {
+    type Output = CharVec;
+
+    #[inline]
+    fn bitand(self, rhs: Self) -> Self::Output {
         CharVec {
-            values: x.bitor(y),
-            length,
+            values: self.values.zip_with(&rhs.values, u128::bitand),
+            length: self.length,
         }
     }
 }
@@ -88,15 +135,7 @@ impl BitAnd for CharVec {
     type Output = Self;
 
     fn bitand(self, rhs: Self) -> Self::Output {
-        let CharVec { values: x, length } = self;
-        let CharVec {
-            values: y,
-            length: _l2,
-        } = rhs;
-        CharVec {
-            values: x.bitand(y),
-            length,
-        }
+        (&self).bitand(&rhs)
     }
 }
 
@@ -108,9 +147,8 @@ impl Debug for CharVec {
 
 impl Display for CharVec {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let x = self.values;
         for i in 0..self.len() {
-            write!(f, "{}", (x >> i) & 1)?;
+            write!(f, "{}", self.values.get(i) as u8)?;
         }
         Ok(())
     }
@@ -118,18 +156,8 @@ impl Display for CharVec {
 
 impl FromIterator<bool> for CharVec {
     fn from_iter<T: IntoIterator<Item = bool>>(iter: T) -> Self {
-        let mut x = 0;
-        let mut length = 0;
-        iter.into_iter().enumerate().for_each(|(i, b)| {
-            if i > 127 {
-                panic!("Iterator is too long! (max len {})", u128::BITS);
-            }
-            if b {
-                x |= 1 << i;
-            }
-            length = (i + 1) as u8;
-        });
-        CharVec { values: x, length }
+        let (values, length) = Bits::collect_with_len(iter);
+        CharVec { values, length }
     }
 }
 
@@ -139,22 +167,16 @@ mod tests {
 
     use super::*;
 
+    /// Maximum length of random vectors, chosen to span several blocks.
+    const MAX_LEN: usize = 400;
+
     fn random_vec_with_len(len: usize, rng: &mut impl Rng) -> CharVec {
-        let x: u128 = rng.gen();
-        let x = if len < 128 {
-            x & ((1u128 << len) - 1)
-        } else {
-            x
-        };
-        CharVec {
-            values: x,
-            length: len as u8,
-        }
+        (0..len).map(|_| rng.gen()).collect()
     }
 
     fn random_pair() -> (CharVec, CharVec) {
         let mut rng = thread_rng();
-        let len = rng.gen_range(0..128);
+        let len = rng.gen_range(0..MAX_LEN);
         (
             random_vec_with_len(len, &mut rng),
             random_vec_with_len(len, &mut rng),
@@ -163,7 +185,7 @@ mod tests {
 
     fn random_vec() -> CharVec {
         let mut rng = thread_rng();
-        let len = rng.gen_range(0..128);
+        let len = rng.gen_range(0..MAX_LEN);
         random_vec_with_len(len, &mut rng)
     }
 
@@ -171,7 +193,7 @@ mod tests {
     fn phi_and_not_phi_is_zero() {
         for _ in 0..100 {
             let x = random_vec();
-            assert_eq!((x & !x).values, 0);
+            assert_eq!((&x & &!&x).values.count_ones(), 0);
         }
     }
 
@@ -179,7 +201,7 @@ mod tests {
     fn not_is_involutive() {
         for _ in 0..100 {
             let x = random_vec();
-            assert_eq!(x, !!x);
+            assert_eq!(x, !!&x);
         }
     }
 
@@ -187,7 +209,7 @@ mod tests {
     fn and_is_idempotent() {
         for _ in 0..100 {
             let x = random_vec();
-            assert_eq!(x & x, x);
+            assert_eq!(&x & &x, x);
         }
     }
 
@@ -195,7 +217,7 @@ mod tests {
     fn or_is_idempotent() {
         for _ in 0..100 {
             let x = random_vec();
-            assert_eq!(x | x, x);
+            assert_eq!(&x | &x, x);
         }
     }
 
@@ -204,7 +226,35 @@ mod tests {
     fn de_morgan_or_and() {
         for _ in 0..100 {
             let (x1, x2) = random_pair();
-            assert_eq!(!(x1 | x2), !x1 & !x2);
+            assert_eq!(!(&x1 | &x2), !x1 & !x2);
+        }
+    }
+
+    #[test]
+    fn implies_as_not_or() {
+        for _ in 0..100 {
+            let (x, y) = random_pair();
+            assert_eq!(x.implies(&y), !&x | y);
+        }
+    }
+
+    #[test]
+    fn equiv_as_double_implication() {
+        for _ in 0..100 {
+            let (x, y) = random_pair();
+            assert_eq!(x.equiv(&y), x.implies(&y) & y.implies(&x));
+        }
+    }
+
+    #[test]
+    fn satisfied_matches_pointwise_equality() {
+        for _ in 0..100 {
+            let (cv, target) = random_pair();
+            let sv = cv.satisfied(&target);
+            for i in 0..cv.len() {
+                assert_eq!(sv.values.get(i), cv.values.get(i) == target.values.get(i));
+            }
+            assert!(sv.popcount() <= cv.len());
         }
     }
 }
@@ -1,4 +1,5 @@
 //! Types used for Boolean Formulas
+pub(crate) mod bits;
 pub(crate) mod cache;
 pub(crate) mod charac;
 pub(crate) mod cv;
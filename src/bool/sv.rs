@@ -4,28 +4,30 @@ use std::{
     ops::Not,
 };
 
+use super::bits::{Bits, BLOCK_BITS};
+
 /// Satisfiability vector of a boolean formula.
 ///
 /// Defined by `self.values[i] == 1` if and only if
 /// the corresponding formula satisfies the ith input.
 /// I.e. the input is positive and formula is true
 /// or the input is negative and formula is false.
-#[derive(PartialEq, Eq, Clone, Copy, Hash)]
+#[derive(PartialEq, Eq, Clone, Hash)]
 pub struct SatVec {
-    pub(super) values: u128,
+    pub(super) values: Bits,
 }
 
 impl SatVec {
-    pub(crate) fn popcount(&self) -> u32 {
+    pub(crate) fn popcount(&self) -> usize {
         self.values.count_ones()
     }
 
     /// Whether `self` dominates `other`.
-    pub(crate) fn dominates(&self, other: Self) -> bool {
+    pub(crate) fn dominates(&self, other: &Self) -> bool {
         // Other.values is a subset of self.values
         // iff the intersection of other.values
         // and the complement of self.values is empty.
-        self.values.not() & other.values == 0
+        self.values.all_zero_with(&other.values, |x, y| x.not() & y)
     }
 }
 
@@ -37,9 +39,8 @@ impl Debug for SatVec {
 
 impl Display for SatVec {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let x = self.values;
-        for i in 0..128 {
-            write!(f, "{}", (x >> i) & 1)?;
+        for i in 0..self.values.blocks().len() * BLOCK_BITS {
+            write!(f, "{}", self.values.get(i) as u8)?;
         }
         Ok(())
     }
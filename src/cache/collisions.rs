@@ -0,0 +1,159 @@
+//! Hash collisions in the formula caches.
+//!
+//! Caches identify formulas by the hash of their characteristic matrix or vector,
+//! and check the actual characteristics when hashes match.
+//! A formula whose hash is taken by a different formula is given the next free hash instead,
+//! see [`find_slot`](super::find_slot).
+//!
+//! Counting the collisions is opt-in: it is enabled by [`enable_report`],
+//! after which [`collision_counts`] returns the number of collisions met by each kind of cache.
+use std::{
+    fmt::Display,
+    hash::Hasher,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+};
+
+use fxhash::FxHasher64;
+
+use crate::HashType;
+
+/// Kinds of caches that identify formulas by hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CacheKind {
+    Ltl,
+    InitialBool,
+    Bool,
+    BeamSearch,
+    SetCover,
+}
+
+impl CacheKind {
+    const ALL: [CacheKind; 5] = [
+        CacheKind::Ltl,
+        CacheKind::InitialBool,
+        CacheKind::Bool,
+        CacheKind::BeamSearch,
+        CacheKind::SetCover,
+    ];
+}
+
+impl Display for CacheKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CacheKind::Ltl => write!(f, "LTL cache"),
+            CacheKind::InitialBool => write!(f, "initial boolean cache"),
+            CacheKind::Bool => write!(f, "boolean enumeration cache"),
+            CacheKind::BeamSearch => write!(f, "beam search cache"),
+            CacheKind::SetCover => write!(f, "set cover cache"),
+        }
+    }
+}
+
+static REPORT: AtomicBool = AtomicBool::new(false);
+static COUNTS: [AtomicUsize; 5] = [const { AtomicUsize::new(0) }; 5];
+
+/// Start counting hash collisions.
+pub fn enable_report() {
+    REPORT.store(true, Ordering::Relaxed);
+}
+
+/// Record a collision in a cache of the given kind, if counting is enabled.
+pub(crate) fn record(kind: CacheKind) {
+    if REPORT.load(Ordering::Relaxed) {
+        COUNTS[kind as usize].fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Number of collisions met by each kind of cache since counting was enabled.
+pub fn collision_counts() -> Vec<(CacheKind, usize)> {
+    CacheKind::ALL
+        .iter()
+        .map(|&kind| (kind, COUNTS[kind as usize].load(Ordering::Relaxed)))
+        .collect()
+}
+
+/// Hasher of the characteristics stored in the caches.
+///
+/// In tests, it can be made degenerate with [`with_degenerate_hash`] to force collisions.
+#[derive(Default)]
+pub(crate) struct CacheHasher(FxHasher64);
+
+impl Hasher for CacheHasher {
+    fn finish(&self) -> u64 {
+        mask(self.0.finish() as HashType) as u64
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(bytes)
+    }
+
+    fn write_u8(&mut self, i: u8) {
+        self.0.write_u8(i)
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.0.write_u32(i)
+    }
+
+    fn write_u64(&mut self, i: u64) {
+        self.0.write_u64(i)
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.0.write_usize(i)
+    }
+}
+
+#[cfg(not(test))]
+fn mask(hash: HashType) -> HashType {
+    hash
+}
+
+#[cfg(test)]
+thread_local! {
+    static HASH_MASK: std::cell::Cell<HashType> = const { std::cell::Cell::new(HashType::MAX) };
+}
+
+#[cfg(test)]
+fn mask(hash: HashType) -> HashType {
+    hash & HASH_MASK.get()
+}
+
+/// Run `f` with a degenerate [`CacheHasher`] on the current thread, keeping only the lowest `bits` bits of hashes.
+#[cfg(test)]
+pub(crate) fn with_degenerate_hash<T>(bits: u32, f: impl FnOnce() -> T) -> T {
+    let previous = HASH_MASK.replace((1 << bits) - 1);
+    let res = f();
+    HASH_MASK.set(previous);
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use std::hash::Hash;
+
+    use super::*;
+
+    fn hash(x: impl Hash) -> u64 {
+        let mut h = CacheHasher::default();
+        x.hash(&mut h);
+        h.finish()
+    }
+
+    #[test]
+    fn degenerate_hash_keeps_lowest_bits() {
+        assert!((0..100u64).map(hash).any(|h| h >= 4));
+        with_degenerate_hash(2, || assert!((0..100u64).map(hash).all(|h| h < 4)));
+        assert!((0..100u64).map(hash).any(|h| h >= 4));
+    }
+
+    #[test]
+    fn counts_collisions_when_enabled() {
+        // Other tests may record collisions concurrently, so only check that counts increase.
+        let count = |kind| collision_counts()[kind as usize].1;
+        enable_report();
+        let before = count(CacheKind::SetCover);
+        record(CacheKind::SetCover);
+        assert!(count(CacheKind::SetCover) > before);
+    }
+}
@@ -12,12 +12,17 @@ where
         Char: 'a,
         Self: 'a;
 
-    /// Creates a new chache line for formulas of size `size`,
-    /// and return an iterator over formulas of size `size-1`
-    /// and over pairs of formulas whose size sums up to `size-1`.
+    /// Creates a new chache line for formulas of cost `size`,
+    /// and return an iterator over the formulas of cost `size - c` for each `c` in `unary_costs`
+    /// and over the pairs of formulas whose costs sum up to `size - c` for each `c` in `binary_costs`.
+    ///
+    /// The operator costs are expected to be sorted and without duplicates.
+    #[allow(clippy::type_complexity)]
     fn new_line_and_iter_size<'a>(
         &'a mut self,
         size: usize,
+        unary_costs: &[usize],
+        binary_costs: &[usize],
     ) -> (
         impl Iterator<Item = &'a Formula<Char>>,
         impl Iterator<Item = (&'a Formula<Char>, &'a Formula<Char>)>,
@@ -30,10 +35,6 @@ where
     where
         Char: 'a;
 
-    fn iter_size<'a>(&'a self, size: usize) -> impl Iterator<Item = &'a Formula<Char>>
-    where
-        Char: 'a;
-
     fn nb_lines(&self) -> usize;
 }
 
@@ -43,3 +44,50 @@ where
 {
     fn push(&mut self, item: Formula<Char>) -> bool;
 }
+
+/// Indices of the lines containing the children of unary operators of costs `op_costs`,
+/// for a formula of cost `size`.
+pub(crate) fn unary_child_lines(size: usize, op_costs: &[usize]) -> Vec<usize> {
+    op_costs
+        .iter()
+        .filter(|&&c| c < size)
+        .map(|&c| size - c)
+        .collect()
+}
+
+/// Pairs of indices of the lines containing the children of binary operators of costs `op_costs`,
+/// for a formula of cost `size`.
+///
+/// Only pairs `(i, j)` with `i <= j` are returned, the other order is left to the caller.
+pub(crate) fn binary_child_lines(size: usize, op_costs: &[usize]) -> Vec<(usize, usize)> {
+    op_costs
+        .iter()
+        .filter(|&&c| c < size)
+        .flat_map(|&c| {
+            let children = size - c;
+            (1..=children / 2).map(move |i| (i, children - i))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn child_lines_with_unit_costs() {
+        assert_eq!(unary_child_lines(4, &[1]), vec![3]);
+        assert_eq!(binary_child_lines(4, &[1]), vec![(1, 2)]);
+        assert_eq!(binary_child_lines(5, &[1]), vec![(1, 3), (2, 2)]);
+        assert_eq!(binary_child_lines(2, &[1]), vec![]);
+    }
+
+    #[test]
+    fn child_lines_with_weights() {
+        assert_eq!(unary_child_lines(4, &[1, 3, 4]), vec![3, 1]);
+        assert_eq!(
+            binary_child_lines(6, &[1, 2, 5]),
+            vec![(1, 4), (2, 3), (1, 3), (2, 2)]
+        );
+    }
+}
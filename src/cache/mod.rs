@@ -1,9 +1,14 @@
 //! Cache for use with the [Semantic Enumeration algorithm](crate::algos::enumeration)
+pub mod collisions;
 pub(crate) mod enum_cache;
 
+use collisions::CacheKind;
 pub(crate) use enum_cache::{EnumFormulaCache, EnumFormulaCacheLine};
 
-use super::{formula::Formula, traits::Hashed};
+use super::{
+    formula::Formula,
+    traits::{Hashed, Rehash},
+};
 
 /// Trait for structs that can store a set of formulas,
 /// and retrieve them by hash.
@@ -14,3 +19,29 @@ where
     fn len(&self) -> usize;
     fn get(&self, hash: &Char::HashType) -> Option<&Formula<Char>>;
 }
+
+/// Find the hash under which `f` should be stored in a cache of the given kind,
+/// where `stored(h)` is the characteristic stored under the hash `h`, if any.
+///
+/// Hashes taken by different characteristics are skipped, each counting as a collision.
+/// Returns the hash of the stored formula equal to `f` if there is one.
+/// Otherwise, `f` is given the first free hash and `None` is returned.
+pub(crate) fn find_slot<'c, Char>(
+    f: &mut Formula<Char>,
+    kind: CacheKind,
+    stored: impl Fn(&Char::HashType) -> Option<&'c Char>,
+) -> Option<Char::HashType>
+where
+    Char: Rehash + 'c,
+{
+    let mut hash = f.hashed();
+    while let Some(charac) = stored(&hash) {
+        if charac.same_value(&f.charac) {
+            return Some(hash);
+        }
+        collisions::record(kind);
+        hash = Char::next_hash(hash);
+    }
+    f.charac.set_hash(hash);
+    None
+}
@@ -0,0 +1,118 @@
+//! Cost model of formulas.
+//!
+//! The cost of a formula is the sum of the weights of its nodes: each operator and each
+//! predicate of the alphabet has a weight, which is 1 unless specified otherwise,
+//! so that the default cost of a formula is its size.
+//! A negated predicate `!p` has the weight of `p`.
+//!
+//! Weights are written `name=weight`, e.g. `U=3` or `p=2`, and must be positive.
+use std::str::FromStr;
+
+use fxhash::FxHashMap;
+use thiserror::Error;
+
+use crate::ops::{binary::LtlBinaryOp, unary::LtlUnaryOp};
+
+/// Weights of the operators and predicates.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CostModel {
+    unary: FxHashMap<LtlUnaryOp, usize>,
+    binary: FxHashMap<LtlBinaryOp, usize>,
+    atoms: FxHashMap<String, usize>,
+}
+
+impl CostModel {
+    pub(crate) fn unary(&self, op: LtlUnaryOp) -> usize {
+        self.unary.get(&op).copied().unwrap_or(1)
+    }
+
+    pub(crate) fn binary(&self, op: LtlBinaryOp) -> usize {
+        self.binary.get(&op).copied().unwrap_or(1)
+    }
+
+    /// Weight of the predicate `name`, given without negation.
+    pub(crate) fn atom(&self, name: &str) -> usize {
+        self.atoms.get(name).copied().unwrap_or(1)
+    }
+
+    /// Set the weight of an operator or predicate, replacing any previous one.
+    pub fn set(&mut self, weight: Weight) {
+        match weight {
+            Weight::Unary(op, w) => self.unary.insert(op, w),
+            Weight::Binary(op, w) => self.binary.insert(op, w),
+            Weight::Atom(name, w) => self.atoms.insert(name, w),
+        };
+    }
+}
+
+/// Weight of an operator or of a predicate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Weight {
+    Unary(LtlUnaryOp, usize),
+    Binary(LtlBinaryOp, usize),
+    Atom(String, usize),
+}
+
+#[derive(Debug, Error, PartialEq)]
+#[error("Invalid weight '{}', expected 'name=weight' with a positive integer weight.", .0)]
+pub struct InvalidWeight(String);
+
+/// Split a token into its name and its weight, if it is written `name=weight`.
+pub(crate) fn split_weight(token: &str) -> Result<(&str, Option<usize>), InvalidWeight> {
+    let Some((name, weight)) = token.rsplit_once('=') else {
+        return Ok((token, None));
+    };
+    match weight.trim().parse() {
+        Ok(w) if w > 0 => Ok((name.trim(), Some(w))),
+        _ => Err(InvalidWeight(token.to_owned())),
+    }
+}
+
+impl FromStr for Weight {
+    type Err = InvalidWeight;
+
+    /// Parse a weight written `name=weight`.
+    ///
+    /// The name is read as an operator if possible, and as a predicate otherwise.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, Some(w)) = split_weight(s)? else {
+            return Err(InvalidWeight(s.to_owned()));
+        };
+        if name.is_empty() {
+            Err(InvalidWeight(s.to_owned()))
+        } else if let Ok(op) = LtlUnaryOp::try_from(name) {
+            Ok(Weight::Unary(op, w))
+        } else if let Ok(op) = LtlBinaryOp::try_from(name) {
+            Ok(Weight::Binary(op, w))
+        } else {
+            Ok(Weight::Atom(name.to_owned(), w))
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_weights() {
+        assert_eq!("U=3".parse(), Ok(Weight::Binary(LtlBinaryOp::Until, 3)));
+        assert_eq!("<-> = 2".parse(), Ok(Weight::Binary(LtlBinaryOp::Equiv, 2)));
+        assert_eq!("X=1".parse(), Ok(Weight::Unary(LtlUnaryOp::Next, 1)));
+        assert_eq!("p=4".parse(), Ok(Weight::Atom("p".to_owned(), 4)));
+
+        for s in ["U", "U=0", "U=-1", "=2", "p=q"] {
+            assert!(s.parse::<Weight>().is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn default_weight_is_one() {
+        let mut costs = CostModel::default();
+        costs.set(Weight::Binary(LtlBinaryOp::Until, 3));
+        assert_eq!(costs.binary(LtlBinaryOp::Until), 3);
+        assert_eq!(costs.binary(LtlBinaryOp::And), 1);
+        assert_eq!(costs.unary(LtlUnaryOp::Finally), 1);
+        assert_eq!(costs.atom("p"), 1);
+    }
+}
@@ -1,7 +1,9 @@
 //! Generic formulas types.
+pub mod cost;
+pub mod parser;
 pub mod tree;
 
-use std::{fmt::Debug, rc::Rc};
+use std::{fmt::Debug, sync::Arc};
 
 use tree::FormulaTree;
 
@@ -22,13 +24,15 @@ use super::{
 ///
 /// `Char` is required to implement the [`Hashed`] trait, i.e. it can be converted to a hash.
 /// The `node` attribute uses this hash to implicitly store the tree structure of the formula.
+///
+/// The `cost` attribute is the cost of the formula in the [cost model](cost), i.e. its size by default.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Formula<Char>
 where
     Char: Hashed,
 {
     pub(crate) charac: Char,
-    pub(crate) size: usize,
+    pub(crate) cost: usize,
     pub(crate) node: FormulaNode<Char>,
 }
 
@@ -36,10 +40,10 @@ impl<Char> Formula<Char>
 where
     Char: Hashed,
 {
-    pub(crate) fn new_base(char: Char, size: usize, base: Rc<FormulaTree>) -> Self {
+    pub(crate) fn new_base(char: Char, cost: usize, base: Arc<FormulaTree>) -> Self {
         Self {
             charac: char,
-            size,
+            cost,
             node: FormulaNode::Base(base),
         }
     }
@@ -62,12 +66,17 @@ where
 {
     type TargetType = Char::TargetType;
 
-    fn eq_target(&self, target: &Self::TargetType) -> bool {
-        self.charac.eq_target(target)
+    fn errors(&self, target: &Self::TargetType) -> usize {
+        self.charac.errors(target)
+    }
+
+    fn eq_target(&self, target: &Self::TargetType, max_errors: usize) -> bool {
+        self.charac.eq_target(target, max_errors)
     }
 }
 
-pub(crate) fn apply_unary<Char>(op: LtlUnaryOp, f: &Formula<Char>) -> Formula<Char>
+/// Apply `op`, whose weight is `op_cost`, to `f`.
+pub(crate) fn apply_unary<Char>(op: LtlUnaryOp, op_cost: usize, f: &Formula<Char>) -> Formula<Char>
 where
     Char: UnaryOp + Hashed,
 {
@@ -78,13 +87,15 @@ where
     };
     Formula {
         charac,
-        size: f.size + 1,
+        cost: f.cost + op_cost,
         node,
     }
 }
 
+/// Apply `op`, whose weight is `op_cost`, to `f1` and `f2`.
 pub(crate) fn apply_binary<Char>(
     op: LtlBinaryOp,
+    op_cost: usize,
     f1: &Formula<Char>,
     f2: &Formula<Char>,
 ) -> Formula<Char>
@@ -99,7 +110,7 @@ where
     };
     Formula {
         charac,
-        size: f1.size + 1 + f2.size,
+        cost: f1.cost + op_cost + f2.cost,
         node,
     }
 }
@@ -109,7 +120,7 @@ pub(crate) enum FormulaNode<Char>
 where
     Char: Hashed,
 {
-    Base(Rc<FormulaTree>),
+    Base(Arc<FormulaTree>),
     Unary {
         op: LtlUnaryOp,
         child: Char::HashType,
@@ -128,23 +139,23 @@ pub(crate) fn rebuild_formula<Char>(
 where
     Char: Hashed,
 {
-    Rc::<FormulaTree>::unwrap_or_clone(rebuild_formula_aux(f, cache))
+    Arc::<FormulaTree>::unwrap_or_clone(rebuild_formula_aux(f, cache))
 }
 
 pub(crate) fn rebuild_formula_aux<Char>(
     f: &Formula<Char>,
     cache: &impl FormulaCache<Char>,
-) -> Rc<FormulaTree>
+) -> Arc<FormulaTree>
 where
     Char: Hashed,
 {
     match &f.node {
         FormulaNode::Base(b) => b.clone(),
-        FormulaNode::Unary { op, child } => Rc::from(FormulaTree::UnaryNode {
+        FormulaNode::Unary { op, child } => Arc::from(FormulaTree::UnaryNode {
             op: *op,
             child: rebuild_formula_aux(cache.get(child).unwrap(), cache),
         }),
-        FormulaNode::Binary { op, left, right } => Rc::from(FormulaTree::BinaryNode {
+        FormulaNode::Binary { op, left, right } => Arc::from(FormulaTree::BinaryNode {
             op: *op,
             left: rebuild_formula_aux(cache.get(left).unwrap(), cache),
             right: rebuild_formula_aux(cache.get(right).unwrap(), cache),
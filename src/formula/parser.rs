@@ -0,0 +1,526 @@
+//! Parser for LTL formulas written as strings.
+//!
+//! The parser accepts the output of the [`Display`](std::fmt::Display) implementation
+//! of [`FormulaTree`], as well as the usual infix and prefix notations:
+//! `F (p & X q)`, `G(p) | q U r` and `&(F(p), X(q))` are all valid formulas.
+//!
+//! Operators are the tokens accepted by the [`TryFrom<&str>`] implementations of
+//! [`LtlUnaryOp`] and [`LtlBinaryOp`].
+//! Unary operators bind tighter than binary ones, and binary operators
+//! have the following precedence, from loosest to tightest: `<->`, `->`, `|`, `&`,
+//! and finally `U`, `W`, `R` and `S`.
+//! `<->`, `|` and `&` associate to the left, the others to the right.
+//!
+//! Bounded operators are written with their interval, e.g. `F[1,3] p` or `p U[0,2] q`.
+//!
+//! Predicates are resolved against an alphabet. `!` applied to a predicate yields
+//! the negated atom, and the [`Not`](LtlUnaryOp::Not) operator on any other formula.
+//! A predicate named like an operator, such as `O` or `S`, is read as the operator only where
+//! the predicate cannot appear: before an operand for a unary operator, after an operand
+//! for a binary operator, or before the parenthesis of its prefix notation.
+//! A word that is neither an operator nor a predicate is read as a sequence of unary operators
+//! followed by a predicate, e.g. `GFp` is read as `G (F (p))`.
+use std::sync::Arc;
+
+use thiserror::Error;
+
+use crate::{
+    ltl::{Predicate, PredicateForm},
+    ops::{binary::LtlBinaryOp, unary::LtlUnaryOp},
+};
+
+use super::tree::FormulaTree;
+
+/// Kind of error encountered while parsing a formula.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum FormulaParseErrorKind {
+    #[error("unexpected character")]
+    UnexpectedChar,
+    #[error("unexpected token")]
+    UnexpectedToken,
+    #[error("unexpected end of formula")]
+    UnexpectedEnd,
+    #[error("unknown predicate")]
+    UnknownPredicate,
+    #[error("invalid interval, expected '[a,b]' with a <= b")]
+    InvalidInterval,
+}
+
+/// Error encountered while parsing a formula, with its position.
+///
+/// Columns start at 1.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("column {column}: {kind} (found '{text}')")]
+pub struct FormulaParseError {
+    pub column: usize,
+    /// Offending text.
+    pub text: String,
+    pub kind: FormulaParseErrorKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Token<'a> {
+    LParen,
+    RParen,
+    Comma,
+    Bang,
+    Unary(LtlUnaryOp),
+    Binary(LtlBinaryOp),
+    Ident(&'a str),
+}
+
+/// Maximum length of a symbolic (non alphanumeric) operator token.
+const MAX_SYMBOL_LEN: usize = 3;
+
+fn is_ident_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Read an operator from its string representation.
+fn operator(s: &str) -> Option<Token<'_>> {
+    if let Ok(op) = LtlUnaryOp::try_from(s) {
+        Some(Token::Unary(op))
+    } else if let Ok(op) = LtlBinaryOp::try_from(s) {
+        Some(Token::Binary(op))
+    } else {
+        None
+    }
+}
+
+/// Read the bounded operator starting at byte offset `offset` of `input`
+/// if the operator name, of length `len`, is followed by an interval such as `[1,3]`.
+fn bounded_operator(
+    input: &str,
+    offset: usize,
+    len: usize,
+) -> Result<Option<(Token<'_>, usize)>, FormulaParseError> {
+    let rest = &input[offset..];
+    if !rest[len..].starts_with('[') {
+        return Ok(None);
+    }
+    let end = rest.find(']').map_or(rest.len(), |i| i + 1);
+    match operator(&rest[..end]) {
+        Some(op) => Ok(Some((op, end))),
+        None => Err(FormulaParseError {
+            column: column(input, offset + len),
+            text: rest[len..end].to_owned(),
+            kind: FormulaParseErrorKind::InvalidInterval,
+        }),
+    }
+}
+
+/// Split `input` into tokens, together with their byte offset.
+fn tokenize<'a>(
+    input: &'a str,
+    alphabet: &[String],
+) -> Result<Vec<(usize, Token<'a>)>, FormulaParseError> {
+    let mut tokens = vec![];
+    let mut offset = 0;
+    while let Some(c) = input[offset..].chars().next() {
+        let rest = &input[offset..];
+        if c.is_whitespace() {
+            offset += c.len_utf8();
+            continue;
+        }
+
+        let (token, len) = match c {
+            '(' => (Token::LParen, 1),
+            ')' => (Token::RParen, 1),
+            ',' => (Token::Comma, 1),
+            '!' => (Token::Bang, 1),
+            c if is_ident_char(c) => {
+                let len = rest.find(|c| !is_ident_char(c)).unwrap_or(rest.len());
+                let word = &rest[..len];
+                let is_predicate = alphabet.iter().any(|p| p == word);
+                if let Some(op) = operator(word) {
+                    match bounded_operator(input, offset, len)? {
+                        Some(bounded) => bounded,
+                        // Resolved by the parser from the surrounding tokens.
+                        None if is_predicate => (Token::Ident(word), len),
+                        None => (op, len),
+                    }
+                } else if is_predicate {
+                    (Token::Ident(word), len)
+                } else {
+                    // Read a leading unary operator glued to the rest of the word.
+                    let first = &word[..c.len_utf8()];
+                    match operator(first) {
+                        Some(op @ Token::Unary(_)) => (op, first.len()),
+                        _ => (Token::Ident(word), len),
+                    }
+                }
+            }
+            _ => (1..=MAX_SYMBOL_LEN.min(rest.len()))
+                .rev()
+                .filter(|&len| rest.is_char_boundary(len))
+                .find_map(|len| operator(&rest[..len]).map(|op| (op, len)))
+                .ok_or_else(|| FormulaParseError {
+                    column: column(input, offset),
+                    text: c.to_string(),
+                    kind: FormulaParseErrorKind::UnexpectedChar,
+                })?,
+        };
+        tokens.push((offset, token));
+        offset += len;
+    }
+    Ok(tokens)
+}
+
+/// Column (starting at 1) of the byte offset `offset` in `input`.
+fn column(input: &str, offset: usize) -> usize {
+    input[..offset].chars().count() + 1
+}
+
+/// Binding power of binary operators, and whether they associate to the right.
+fn precedence(op: LtlBinaryOp) -> (u8, bool) {
+    match op {
+        LtlBinaryOp::Equiv => (1, false),
+        LtlBinaryOp::Implies => (2, true),
+        LtlBinaryOp::Or => (3, false),
+        LtlBinaryOp::And => (4, false),
+        LtlBinaryOp::Until
+        | LtlBinaryOp::WeakUntil
+        | LtlBinaryOp::Release
+        | LtlBinaryOp::Since
+        | LtlBinaryOp::BoundedUntil(_) => (5, true),
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    alphabet: &'a [String],
+    tokens: Vec<(usize, Token<'a>)>,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<Token<'a>> {
+        self.tokens.get(self.pos).map(|&(_, t)| t)
+    }
+
+    fn next(&mut self) -> Result<Token<'a>, FormulaParseError> {
+        let token = self.peek().ok_or_else(|| self.error_at_end())?;
+        self.pos += 1;
+        Ok(token)
+    }
+
+    fn error_at_end(&self) -> FormulaParseError {
+        FormulaParseError {
+            column: column(self.input, self.input.len()),
+            text: String::new(),
+            kind: FormulaParseErrorKind::UnexpectedEnd,
+        }
+    }
+
+    /// Error on the token at position `pos`.
+    fn error_at(&self, pos: usize, kind: FormulaParseErrorKind) -> FormulaParseError {
+        let Some(&(offset, _)) = self.tokens.get(pos) else {
+            return self.error_at_end();
+        };
+        let end = self
+            .tokens
+            .get(pos + 1)
+            .map_or(self.input.len(), |&(o, _)| o);
+        FormulaParseError {
+            column: column(self.input, offset),
+            text: self.input[offset..end].trim_end().to_owned(),
+            kind,
+        }
+    }
+
+    fn expect(&mut self, expected: Token<'a>) -> Result<(), FormulaParseError> {
+        if self.next()? == expected {
+            Ok(())
+        } else {
+            Err(self.error_at(self.pos - 1, FormulaParseErrorKind::UnexpectedToken))
+        }
+    }
+
+    /// Parse binary operators binding at least as tight as `min_prec`.
+    fn parse_binary(&mut self, min_prec: u8) -> Result<Arc<FormulaTree>, FormulaParseError> {
+        let mut left = self.parse_unary()?;
+        loop {
+            let op = match self.peek() {
+                Some(Token::Binary(op)) => op,
+                // A predicate cannot follow an operand.
+                Some(Token::Ident(name)) => match operator(name) {
+                    Some(Token::Binary(op)) => op,
+                    _ => break,
+                },
+                _ => break,
+            };
+            let (prec, right_assoc) = precedence(op);
+            if prec < min_prec {
+                break;
+            }
+            self.pos += 1;
+            let right = self.parse_binary(if right_assoc { prec } else { prec + 1 })?;
+            left = Arc::new(FormulaTree::BinaryNode { op, left, right });
+        }
+        Ok(left)
+    }
+
+    fn parse_unary(&mut self) -> Result<Arc<FormulaTree>, FormulaParseError> {
+        let pos = self.pos;
+        match self.next()? {
+            Token::Unary(op) => self.parse_unary_node(op),
+            Token::Bang => {
+                let child = self.parse_unary()?;
+                match &*child {
+                    FormulaTree::Atom(Predicate(_, form)) => Ok(Arc::new(self.negate(form))),
+                    _ => Ok(Arc::new(FormulaTree::UnaryNode {
+                        op: LtlUnaryOp::Not,
+                        child,
+                    })),
+                }
+            }
+            Token::LParen => {
+                let f = self.parse_binary(0)?;
+                self.expect(Token::RParen)?;
+                Ok(f)
+            }
+            Token::Binary(op) => self.parse_prefix(op),
+            // A predicate named like an operator, followed by what only the operator can be.
+            Token::Ident(name) => match (operator(name), self.peek()) {
+                (
+                    Some(Token::Unary(op)),
+                    Some(Token::LParen | Token::Bang | Token::Unary(_) | Token::Ident(_)),
+                ) => self.parse_unary_node(op),
+                (Some(Token::Binary(op)), Some(Token::LParen)) => self.parse_prefix(op),
+                _ => self.parse_predicate(pos, name),
+            },
+            Token::RParen | Token::Comma => {
+                Err(self.error_at(pos, FormulaParseErrorKind::UnexpectedToken))
+            }
+        }
+    }
+
+    /// Parse the operand of the unary operator `op`.
+    fn parse_unary_node(&mut self, op: LtlUnaryOp) -> Result<Arc<FormulaTree>, FormulaParseError> {
+        let child = self.parse_unary()?;
+        Ok(Arc::new(FormulaTree::UnaryNode { op, child }))
+    }
+
+    /// Parse the operands of the binary operator `op` in prefix notation: `op(left, right)`.
+    fn parse_prefix(&mut self, op: LtlBinaryOp) -> Result<Arc<FormulaTree>, FormulaParseError> {
+        self.expect(Token::LParen)?;
+        let left = self.parse_binary(0)?;
+        self.expect(Token::Comma)?;
+        let right = self.parse_binary(0)?;
+        self.expect(Token::RParen)?;
+        Ok(Arc::new(FormulaTree::BinaryNode { op, left, right }))
+    }
+
+    /// Predicate `name`, whose token is at position `pos`.
+    fn parse_predicate(
+        &self,
+        pos: usize,
+        name: &str,
+    ) -> Result<Arc<FormulaTree>, FormulaParseError> {
+        match self.alphabet.iter().position(|p| p == name) {
+            Some(i) => Ok(Arc::new(FormulaTree::Atom(Predicate(
+                name.to_owned(),
+                PredicateForm::Positive(i),
+            )))),
+            None => Err(self.error_at(pos, FormulaParseErrorKind::UnknownPredicate)),
+        }
+    }
+
+    /// Negation of an atom, named as in [`atoms`](crate::algos).
+    fn negate(&self, form: &PredicateForm) -> FormulaTree {
+        let predicate = match *form {
+            PredicateForm::Positive(i) => {
+                Predicate(format!("!{}", self.alphabet[i]), PredicateForm::Negative(i))
+            }
+            PredicateForm::Negative(i) => {
+                Predicate(self.alphabet[i].clone(), PredicateForm::Positive(i))
+            }
+        };
+        FormulaTree::Atom(predicate)
+    }
+}
+
+/// Parse an LTL formula over the predicates in `alphabet`.
+///
+/// See the [module documentation](self) for the accepted syntax.
+pub fn parse_formula(input: &str, alphabet: &[String]) -> Result<FormulaTree, FormulaParseError> {
+    let tokens = tokenize(input, alphabet)?;
+    let mut parser = Parser {
+        input,
+        alphabet,
+        tokens,
+        pos: 0,
+    };
+    let f = parser.parse_binary(0)?;
+    if parser.peek().is_some() {
+        return Err(parser.error_at(parser.pos, FormulaParseErrorKind::UnexpectedToken));
+    }
+    Ok(Arc::unwrap_or_clone(f))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ops::interval::Interval;
+
+    fn alphabet() -> Vec<String> {
+        vec!["p".to_owned(), "q".to_owned(), "r_1".to_owned()]
+    }
+
+    fn parse(s: &str) -> FormulaTree {
+        parse_formula(s, &alphabet()).unwrap()
+    }
+
+    fn parse_err(s: &str) -> FormulaParseError {
+        parse_formula(s, &alphabet()).unwrap_err()
+    }
+
+    #[test]
+    fn precedence_and_associativity() {
+        assert_eq!(parse("p | q & r_1"), parse("p | (q & r_1)"));
+        assert_eq!(parse("p & q U r_1"), parse("p & (q U r_1)"));
+        assert_eq!(parse("p U q U r_1"), parse("p U (q U r_1)"));
+        assert_eq!(parse("p & q & r_1"), parse("(p & q) & r_1"));
+        assert_eq!(parse("p -> q -> r_1"), parse("p -> (q -> r_1)"));
+        assert_eq!(parse("p <-> q | r_1 -> p"), parse("p <-> ((q | r_1) -> p)"));
+        assert_eq!(parse("p R q W r_1"), parse("p R (q W r_1)"));
+        assert_eq!(parse("F p & X q"), parse("(F (p)) & (X (q))"));
+    }
+
+    #[test]
+    fn bounded_operators() {
+        let interval = Interval::new(1, 3).unwrap();
+        assert_eq!(
+            parse("F[1,3] p"),
+            FormulaTree::UnaryNode {
+                op: LtlUnaryOp::BoundedFinally(interval),
+                child: Arc::new(parse("p")),
+            }
+        );
+        assert_eq!(parse("GF[1,3]p"), parse("G (F[1,3] (p))"));
+        assert_eq!(parse("p U[1,3] q & r_1"), parse("(p U[1,3] q) & r_1"));
+
+        let err = parse_err("F[3,1] p");
+        assert_eq!(err.kind, FormulaParseErrorKind::InvalidInterval);
+        assert_eq!((err.column, err.text.as_str()), (2, "[3,1]"));
+    }
+
+    #[test]
+    fn prefix_notation() {
+        assert_eq!(parse("&(F(p), X(q))"), parse("F p & X q"));
+        assert_eq!(parse("U(p, |(q, r_1))"), parse("p U (q | r_1)"));
+    }
+
+    #[test]
+    fn glued_unary_operators() {
+        assert_eq!(parse("GFp"), parse("G (F (p))"));
+        assert_eq!(parse("X!q"), parse("X (!q)"));
+        assert_eq!(parse("NXp"), parse("N (X (p))"));
+    }
+
+    #[test]
+    fn negated_atoms() {
+        let f = parse("!q");
+        assert_eq!(
+            f,
+            FormulaTree::Atom(Predicate("!q".to_owned(), PredicateForm::Negative(1)))
+        );
+        assert_eq!(parse("!!q"), parse("q"));
+    }
+
+    #[test]
+    fn negated_formulas() {
+        let f = parse("!F p");
+        assert_eq!(
+            f,
+            FormulaTree::UnaryNode {
+                op: LtlUnaryOp::Not,
+                child: Arc::new(parse("F p")),
+            }
+        );
+        assert_eq!(parse("!(p U q) & r_1"), parse("(! (p U q)) & r_1"));
+    }
+
+    #[test]
+    fn display_then_parse_is_ident() {
+        for s in [
+            "p",
+            "!r_1",
+            "F (p & X q)",
+            "(p | !q) U G r_1",
+            "X X (p U (q & F !p))",
+            "G (p | q) & (F r_1 | X q)",
+            "!(p U q) | N !F p",
+            "G[0,2] (p U[1,1] q)",
+        ] {
+            let f = parse(s);
+            assert_eq!(parse(&format!("{f}")), f, "{f}");
+        }
+    }
+
+    #[test]
+    fn predicates_named_like_operators() {
+        let alphabet = [
+            "O", "H", "S", "R", "W", "N", "Y", "Z", "X", "F", "G", "U", "p",
+        ]
+        .map(str::to_owned);
+        let parse = |s: &str| parse_formula(s, &alphabet).unwrap();
+        let atom = |i: usize| {
+            Arc::new(FormulaTree::Atom(Predicate(
+                alphabet[i].clone(),
+                PredicateForm::Positive(i),
+            )))
+        };
+        assert_eq!(parse("O"), *atom(0));
+        assert_eq!(
+            parse("O O"),
+            FormulaTree::UnaryNode {
+                op: LtlUnaryOp::Once,
+                child: atom(0),
+            }
+        );
+        assert_eq!(
+            parse("S S S"),
+            FormulaTree::BinaryNode {
+                op: LtlBinaryOp::Since,
+                left: atom(2),
+                right: atom(2),
+            }
+        );
+        assert_eq!(parse("S(p, H)"), parse("p S H"));
+        assert_eq!(parse("!O & F[1,2] F"), parse("(!O) & (F[1,2] (F))"));
+
+        for s in [
+            "O",
+            "O (H)",
+            "(S) S (R)",
+            "(W) W (N & X)",
+            "Y (Z) U !G",
+            "G (F | U) R X (!O)",
+            "H (p S N) & !((Y) S Z)",
+            "F[0,2] F",
+        ] {
+            let f = parse(s);
+            assert_eq!(parse(&format!("{f}")), f, "{f}");
+        }
+    }
+
+    #[test]
+    fn errors() {
+        let err = parse_err("F (p & s)");
+        assert_eq!(err.kind, FormulaParseErrorKind::UnknownPredicate);
+        assert_eq!((err.column, err.text.as_str()), (8, "s"));
+
+        let err = parse_err("p & (q");
+        assert_eq!(err.kind, FormulaParseErrorKind::UnexpectedEnd);
+        assert_eq!(err.column, 7);
+
+        let err = parse_err("p q");
+        assert_eq!(err.kind, FormulaParseErrorKind::UnexpectedToken);
+        assert_eq!((err.column, err.text.as_str()), (3, "q"));
+
+        let err = parse_err("p $ q");
+        assert_eq!(err.kind, FormulaParseErrorKind::UnexpectedChar);
+        assert_eq!((err.column, err.text.as_str()), (3, "$"));
+    }
+}
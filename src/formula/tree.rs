@@ -1,7 +1,8 @@
 //! Explicit formula tree representation.
-use std::{fmt::Display, ops::Not, rc::Rc};
+use std::{fmt::Display, ops::Not, sync::Arc};
 
 use crate::{
+    formula::cost::CostModel,
     ltl::{cm::CharMatrix, trace::Trace, Predicate, PredicateForm},
     ops::{binary::LtlBinaryOp, unary::LtlUnaryOp},
 };
@@ -12,17 +13,19 @@ pub enum FormulaTree {
     Atom(Predicate),
     UnaryNode {
         op: LtlUnaryOp,
-        child: Rc<FormulaTree>,
+        child: Arc<FormulaTree>,
     },
     BinaryNode {
         op: LtlBinaryOp,
-        left: Rc<FormulaTree>,
-        right: Rc<FormulaTree>,
+        left: Arc<FormulaTree>,
+        right: Arc<FormulaTree>,
     },
 }
 
 impl FormulaTree {
     /// Compute the size of the formula.
+    ///
+    /// Bounded operators such as `F[1,3]` count as a single node, like their unbounded version.
     pub fn size(&self) -> usize {
         match self {
             FormulaTree::Atom(_) => 1,
@@ -31,12 +34,42 @@ impl FormulaTree {
         }
     }
 
+    /// Compute the cost of the formula in the given cost model.
+    pub fn cost(&self, costs: &CostModel) -> usize {
+        match self {
+            FormulaTree::Atom(p) => costs.atom(p.name()),
+            FormulaTree::UnaryNode { op, child } => costs.unary(*op) + child.cost(costs),
+            FormulaTree::BinaryNode { op, left, right } => {
+                costs.binary(*op) + left.cost(costs) + right.cost(costs)
+            }
+        }
+    }
+
+    /// Whether the formula contains a past-time operator.
+    pub fn has_past(&self) -> bool {
+        match self {
+            FormulaTree::Atom(_) => false,
+            FormulaTree::UnaryNode { op, child } => op.is_past() || child.has_past(),
+            FormulaTree::BinaryNode { op, left, right } => {
+                op.is_past() || left.has_past() || right.has_past()
+            }
+        }
+    }
+
     /// Evaluate the formula on a set of input traces.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the formula [has past-time operators](Self::has_past) and some trace is a lasso.
     pub fn eval(&self, traces: &[Trace]) -> CharMatrix {
         match self {
             FormulaTree::Atom(Predicate(_, pf)) => match *pf {
-                PredicateForm::Positive(i) => traces.iter().map(|t| t.alphabet[i]).collect(),
-                PredicateForm::Negative(i) => traces.iter().map(|t| t.alphabet[i].not()).collect(),
+                PredicateForm::Positive(i) => {
+                    traces.iter().map(|t| t.alphabet[i].clone()).collect()
+                }
+                PredicateForm::Negative(i) => {
+                    traces.iter().map(|t| (&t.alphabet[i]).not()).collect()
+                }
             },
             FormulaTree::UnaryNode { op, child } => {
                 let cm = child.eval(traces);
@@ -49,6 +82,73 @@ impl FormulaTree {
             }
         }
     }
+
+    /// Evaluate the formula on labelled traces and compare the result with the labels.
+    pub fn classify(&self, traces: &[Trace], target: &[bool]) -> Classification {
+        Classification::new(&self.eval(traces).accepted_vec(), target)
+    }
+}
+
+/// Comparison of the traces accepted by a formula with the expected labels.
+///
+/// Misclassified traces are identified by their index among
+/// the positive traces, or among the negative traces.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Classification {
+    pub true_positives: usize,
+    pub false_negatives: usize,
+    pub true_negatives: usize,
+    pub false_positives: usize,
+    /// Indices of the rejected positive traces.
+    pub misclassified_positives: Vec<usize>,
+    /// Indices of the accepted negative traces.
+    pub misclassified_negatives: Vec<usize>,
+}
+
+impl Classification {
+    pub fn new(accepted: &[bool], target: &[bool]) -> Self {
+        assert_eq!(accepted.len(), target.len());
+        let mut misclassified_positives = vec![];
+        let mut misclassified_negatives = vec![];
+        let (mut nb_pos, mut nb_neg) = (0, 0);
+        for (&a, &t) in accepted.iter().zip(target) {
+            if t {
+                if !a {
+                    misclassified_positives.push(nb_pos);
+                }
+                nb_pos += 1;
+            } else {
+                if a {
+                    misclassified_negatives.push(nb_neg);
+                }
+                nb_neg += 1;
+            }
+        }
+
+        Self {
+            true_positives: nb_pos - misclassified_positives.len(),
+            false_negatives: misclassified_positives.len(),
+            true_negatives: nb_neg - misclassified_negatives.len(),
+            false_positives: misclassified_negatives.len(),
+            misclassified_positives,
+            misclassified_negatives,
+        }
+    }
+
+    /// Number of misclassified traces.
+    pub fn errors(&self) -> usize {
+        self.false_negatives + self.false_positives
+    }
+
+    /// Proportion of correctly classified traces, 1 if there are no traces.
+    pub fn accuracy(&self) -> f64 {
+        let total = self.errors() + self.true_positives + self.true_negatives;
+        if total == 0 {
+            1.
+        } else {
+            (total - self.errors()) as f64 / total as f64
+        }
+    }
 }
 
 impl Display for FormulaTree {
@@ -60,3 +160,43 @@ impl Display for FormulaTree {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::formula::parser::parse_formula;
+
+    #[test]
+    fn classification_counts_and_indices() {
+        let target = [true, true, true, false, false];
+        let accepted = [true, false, true, true, false];
+        let res = Classification::new(&accepted, &target);
+        assert_eq!(
+            (res.true_positives, res.false_negatives),
+            (2, 1),
+            "positive counts"
+        );
+        assert_eq!(
+            (res.true_negatives, res.false_positives),
+            (1, 1),
+            "negative counts"
+        );
+        assert_eq!(res.misclassified_positives, vec![1]);
+        assert_eq!(res.misclassified_negatives, vec![0]);
+        assert_eq!(res.errors(), 2);
+        assert!((res.accuracy() - 0.6).abs() < 1e-9);
+    }
+
+    #[test]
+    fn cost_sums_weights() {
+        let alphabet = ["p".to_owned(), "q".to_owned()];
+        let f = parse_formula("X (!p U q) & F p", &alphabet).unwrap();
+        assert_eq!(f.cost(&CostModel::default()), f.size());
+
+        let mut costs = CostModel::default();
+        for w in ["U=3", "p=2"] {
+            costs.set(w.parse().unwrap());
+        }
+        assert_eq!(f.cost(&costs), f.size() + 2 + 2);
+    }
+}
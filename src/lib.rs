@@ -1,6 +1,6 @@
 pub mod algos;
 pub(crate) mod bool;
-pub(crate) mod cache;
+pub mod cache;
 pub mod formula;
 pub mod ltl;
 pub(crate) mod ops;
@@ -1,27 +1,205 @@
-use std::{collections::hash_map::Entry, vec};
+use std::{mem, ops::Not, vec};
 
 use fxhash::FxHashMap;
 use itertools::Itertools;
 
 use crate::{
-    cache::{EnumFormulaCache, EnumFormulaCacheLine, FormulaCache},
+    cache::{
+        collisions::CacheKind,
+        enum_cache::{binary_child_lines, unary_child_lines},
+        find_slot, EnumFormulaCache, EnumFormulaCacheLine, FormulaCache,
+    },
+    formula::{apply_binary, apply_unary, tree::FormulaTree, FormulaNode},
+    ops::{binary::LtlBinaryOp, traits::Commutativity, unary::LtlUnaryOp},
     traits::Hashed,
 };
 
-use super::{charac::LtlCharac, hash::LtlHash, LtlFormula};
+use super::{
+    charac::LtlCharac,
+    cm::CharMatrix,
+    hash::LtlHash,
+    trace::{Operators, Trace},
+    LtlFormula, Predicate, PredicateForm,
+};
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub(crate) struct LtlCache {
     hash_to_line: FxHashMap<LtlHash, (usize, usize)>,
     pub(crate) lines: Vec<Vec<LtlFormula>>,
+    /// Atoms not yet in the cache, which are added when the line of their cost is created.
+    atoms: Vec<LtlFormula>,
+    /// Formulas dropped by the enumeration, by line, if they are kept to [extend](LtlCache::extended)
+    /// the cache.
+    dropped: Option<Vec<Vec<Dropped>>>,
 }
 
+/// Node of a formula dropped by the enumeration, with the hash of the equal formula of the cache.
+type Dropped = (FormulaNode<LtlCharac>, LtlHash);
+
 impl LtlCache {
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(atoms: Vec<LtlFormula>) -> Self {
         Self {
             hash_to_line: Default::default(),
             lines: vec![],
+            atoms,
+            dropped: None,
+        }
+    }
+
+    /// Like [`LtlCache::new`], but the formulas dropped by the enumeration as equal to
+    /// a formula of the cache are kept, so that they can be recovered when the cache is
+    /// [extended](LtlCache::extended) with traces that distinguish them.
+    pub(crate) fn keeping_dropped(atoms: Vec<LtlFormula>) -> Self {
+        Self {
+            dropped: Some(vec![]),
+            ..Self::new(atoms)
+        }
+    }
+
+    /// Cache made of the given `lines`, whose formulas must have distinct hashes.
+    ///
+    /// The `atoms` more expensive than the formulas of `lines` are added
+    /// when enumeration reaches their cost.
+    pub(crate) fn from_lines(lines: Vec<Vec<LtlFormula>>, atoms: Vec<LtlFormula>) -> Self {
+        let hash_to_line = (lines.iter().enumerate())
+            .flat_map(|(i, l)| l.iter().enumerate().map(move |(j, f)| (f.hashed(), (i, j))))
+            .collect();
+        let atoms = atoms
+            .into_iter()
+            .filter(|f| f.cost >= lines.len())
+            .collect();
+        Self {
+            hash_to_line,
+            lines,
+            atoms,
+            dropped: None,
+        }
+    }
+
+    /// Remove the lines of cost at least `nb_lines`.
+    ///
+    /// Their atoms are added again when enumeration reaches their cost.
+    pub(crate) fn truncate(&mut self, nb_lines: usize) {
+        for line in self.lines.drain(nb_lines.min(self.lines.len())..) {
+            for f in line {
+                self.hash_to_line.remove(&f.hashed());
+                if let FormulaNode::Base(_) = f.node {
+                    self.atoms.push(f);
+                }
+            }
+        }
+        if let Some(dropped) = &mut self.dropped {
+            dropped.truncate(nb_lines);
+        }
+    }
+
+    /// Same cache on the traces followed by `new_traces`, on which its formulas are evaluated.
+    ///
+    /// `atoms` are the atoms on all the traces, those more expensive than the formulas of
+    /// the cache being added when enumeration reaches their cost.
+    /// The hashes of the formulas are recomputed, and they stay pairwise distinct.
+    ///
+    /// If the cache [keeps its dropped formulas](LtlCache::keeping_dropped), those distinguished
+    /// by `new_traces` from the formula they were equal to are added back, along with the
+    /// formulas built from them with `operators`, so that the cache has the same formulas
+    /// (up to equivalence) as if it had been enumerated on all the traces.
+    /// Otherwise, dropped formulas are not recovered, even if they are now distinct.
+    pub(crate) fn extended(
+        &self,
+        new_traces: &[Trace],
+        atoms: Vec<LtlFormula>,
+        operators: &Operators,
+    ) -> Self {
+        let pending = (atoms.into_iter())
+            .filter(|f| f.cost >= self.lines.len())
+            .collect();
+        let mut res = Self {
+            dropped: self.dropped.as_ref().map(|_| vec![]),
+            ..Self::new(pending)
+        };
+        // Values on the new traces and new hash of the formulas, by previous hash
+        let mut extended: FxHashMap<LtlHash, (CharMatrix, LtlHash)> = FxHashMap::default();
+        for (size, line) in self.lines.iter().enumerate() {
+            let mut new_line = res.new_line(size);
+            for f in line {
+                let (rows, node) = extend_node(&f.node, new_traces, &extended);
+                let charac = (f.charac.cm.seqs.iter())
+                    .chain(rows.seqs.iter())
+                    .cloned()
+                    .collect();
+                let pushed = new_line.push(LtlFormula {
+                    charac,
+                    cost: f.cost,
+                    node,
+                });
+                debug_assert!(pushed, "Distinct formulas stay distinct on more traces");
+                let hash = new_line.entries.last().unwrap().hashed();
+                extended.insert(f.hashed(), (rows, hash));
+            }
+
+            let Some(dropped) = &self.dropped else {
+                continue;
+            };
+            // Equal to a formula of the cache on the previous traces, so they are added
+            // unless they are still equal to it on the new traces
+            for (node, equal) in &dropped[size] {
+                let (rows, node) = extend_node(node, new_traces, &extended);
+                let previous = &self.get(equal).unwrap().charac.cm;
+                let charac = (previous.seqs.iter())
+                    .chain(rows.seqs.iter())
+                    .cloned()
+                    .collect();
+                new_line.push(LtlFormula {
+                    charac,
+                    cost: size,
+                    node,
+                });
+            }
+
+            // The formulas with a child that was not in the cache were never enumerated
+            let old_lines = new_line.old_lines;
+            let split = |i: usize| old_lines[i].split_at(self.lines[i].len());
+            for &op in &operators.unary {
+                let op_cost = operators.costs.unary(op);
+                if op_cost >= size {
+                    continue;
+                }
+                for f in split(size - op_cost).1 {
+                    new_line.push(apply_unary(op, op_cost, f));
+                }
+            }
+            for &op in &operators.binary {
+                let op_cost = operators.costs.binary(op);
+                if op_cost >= size {
+                    continue;
+                }
+                let children = size - op_cost;
+                for i in 1..=children / 2 {
+                    let (old_left, new_left) = split(i);
+                    let (old_right, new_right) = split(children - i);
+                    let pairs = (new_left.iter())
+                        .cartesian_product(old_right.iter().chain(new_right))
+                        .chain(old_left.iter().cartesian_product(new_right));
+                    for (f_l, f_r) in pairs {
+                        new_line.push(apply_binary(op, op_cost, f_l, f_r));
+                        // For non-commutative operations
+                        if !op.commutes() {
+                            new_line.push(apply_binary(op, op_cost, f_r, f_l));
+                        }
+                    }
+                }
+            }
         }
+        res
+    }
+
+    /// Remove the atoms of cost `size` from the pending atoms.
+    fn take_atoms(&mut self, size: usize) -> Vec<LtlFormula> {
+        let (now, later) = mem::take(&mut self.atoms)
+            .into_iter()
+            .partition(|f| f.cost == size);
+        self.atoms = later;
+        now
     }
 }
 
@@ -42,6 +220,8 @@ impl EnumFormulaCache<LtlCharac> for LtlCache {
     fn new_line_and_iter_size<'a>(
         &'a mut self,
         new_size: usize,
+        unary_costs: &[usize],
+        binary_costs: &[usize],
     ) -> (
         impl Iterator<Item = &'a LtlFormula>,
         impl Iterator<Item = (&'a LtlFormula, &'a LtlFormula)>,
@@ -50,46 +230,51 @@ impl EnumFormulaCache<LtlCharac> for LtlCache {
     where
         LtlCharac: 'a,
     {
+        let atoms = self.take_atoms(new_size);
         self.lines.push(vec![]);
         let (old_lines, new) = self.lines.split_at_mut(new_size);
+        let old_lines: &'a [_] = old_lines;
 
-        let new_line = LtlCacheLine {
+        let mut new_line = LtlCacheLine {
             size_index: new_size,
             entries: &mut new[0],
+            old_lines,
             hashes: &mut self.hash_to_line,
+            dropped: self.dropped.as_mut().map(new_dropped_line),
         };
+        for f in atoms {
+            new_line.push(f);
+        }
 
-        let iter_size = new_size - 1;
-        let iter_formulas = old_lines[iter_size].iter();
+        let iter_formulas = unary_child_lines(new_size, unary_costs)
+            .into_iter()
+            .flat_map(|i| old_lines[i].iter());
 
-        let iter_pairs_size = (new_size + 1) / 2;
-        let iter_pairs = old_lines
-            .iter()
-            .zip(old_lines.iter().rev())
-            .take(iter_pairs_size)
-            .flat_map(|(i1, i2)| i1.iter().cartesian_product(i2));
+        let iter_pairs = binary_child_lines(new_size, binary_costs)
+            .into_iter()
+            .flat_map(|(i, j)| old_lines[i].iter().cartesian_product(&old_lines[j]));
         (iter_formulas, iter_pairs, new_line)
     }
 
-    fn iter_size<'a>(&'a self, size: usize) -> impl Iterator<Item = &'a LtlFormula>
-    where
-        LtlCharac: 'a,
-    {
-        self.lines[size].iter()
-    }
-
     fn new_line<'a>(&'a mut self, size: usize) -> Self::CacheLine<'a>
     where
         LtlCharac: 'a,
     {
+        let atoms = self.take_atoms(size);
         self.lines.push(vec![]);
-        let entries = &mut self.lines[size];
+        let (old_lines, new) = self.lines.split_at_mut(size);
 
-        LtlCacheLine {
+        let mut line = LtlCacheLine {
             size_index: size,
-            entries,
+            entries: &mut new[0],
+            old_lines,
             hashes: &mut self.hash_to_line,
+            dropped: self.dropped.as_mut().map(new_dropped_line),
+        };
+        for f in atoms {
+            line.push(f);
         }
+        line
     }
 
     fn nb_lines(&self) -> usize {
@@ -97,6 +282,53 @@ impl EnumFormulaCache<LtlCharac> for LtlCache {
     }
 }
 
+/// Add a line to the dropped formulas of the lines of a cache, and return it.
+fn new_dropped_line(dropped: &mut Vec<Vec<Dropped>>) -> &mut Vec<Dropped> {
+    dropped.push(vec![]);
+    dropped.last_mut().unwrap()
+}
+
+/// Values on `new_traces` of the formula of the cache whose node is `node`, and its node with the
+/// new hashes of its children, given the values and new hashes of the formulas by previous hash.
+fn extend_node(
+    node: &FormulaNode<LtlCharac>,
+    new_traces: &[Trace],
+    extended: &FxHashMap<LtlHash, (CharMatrix, LtlHash)>,
+) -> (CharMatrix, FormulaNode<LtlCharac>) {
+    match node {
+        FormulaNode::Base(base) => {
+            let rows = match base.as_ref() {
+                FormulaTree::Atom(Predicate(_, PredicateForm::Positive(i))) => {
+                    new_traces.iter().map(|t| t.alphabet[*i].clone()).collect()
+                }
+                FormulaTree::Atom(Predicate(_, PredicateForm::Negative(i))) => {
+                    new_traces.iter().map(|t| (&t.alphabet[*i]).not()).collect()
+                }
+                _ => unreachable!("Base formulas of the LTL cache are atoms"),
+            };
+            (rows, node.clone())
+        }
+        FormulaNode::Unary { op, child } => {
+            let (rows, child) = &extended[child];
+            let node = FormulaNode::Unary {
+                op: *op,
+                child: *child,
+            };
+            (LtlUnaryOp::apply_cm(*op, rows), node)
+        }
+        FormulaNode::Binary { op, left, right } => {
+            let (left_rows, left) = &extended[left];
+            let (right_rows, right) = &extended[right];
+            let node = FormulaNode::Binary {
+                op: *op,
+                left: *left,
+                right: *right,
+            };
+            (LtlBinaryOp::apply_cm(*op, left_rows, right_rows), node)
+        }
+    }
+}
+
 impl IntoIterator for LtlCache {
     type Item = LtlFormula;
 
@@ -110,20 +342,35 @@ impl IntoIterator for LtlCache {
 pub(crate) struct LtlCacheLine<'a> {
     size_index: usize,
     entries: &'a mut Vec<LtlFormula>,
+    /// Lines of lower costs, to check equality with their formulas on hash matches.
+    old_lines: &'a [Vec<LtlFormula>],
     hashes: &'a mut FxHashMap<LtlHash, (usize, usize)>,
+    /// Where the dropped formulas of the line are kept, if they are.
+    dropped: Option<&'a mut Vec<Dropped>>,
 }
 
 impl<'a> EnumFormulaCacheLine<LtlCharac> for LtlCacheLine<'a> {
-    fn push(&mut self, f: LtlFormula) -> bool {
-        assert_eq!(f.size, self.size_index);
-        match self.hashes.entry(f.hashed()) {
-            Entry::Occupied(_) => false,
-            Entry::Vacant(e) => {
-                let index = self.entries.len();
-                e.insert((self.size_index, index));
-                self.entries.push(f);
-                true
+    fn push(&mut self, mut f: LtlFormula) -> bool {
+        assert_eq!(f.cost, self.size_index);
+        let equal = find_slot(&mut f, CacheKind::Ltl, |hash| {
+            let &(line_id, index) = self.hashes.get(hash)?;
+            let line = if line_id == self.size_index {
+                &self.entries[..]
+            } else {
+                &self.old_lines[line_id][..]
+            };
+            Some(&line[index].charac)
+        });
+        if let Some(equal) = equal {
+            if let Some(dropped) = &mut self.dropped {
+                dropped.push((f.node, equal));
             }
+            return false;
         }
+
+        let index = self.entries.len();
+        self.hashes.insert(f.hashed(), (self.size_index, index));
+        self.entries.push(f);
+        true
     }
 }
@@ -5,7 +5,7 @@ use crate::{
         traits::{BinaryOp, UnaryOp},
         unary::LtlUnaryOp,
     },
-    traits::{EqTarget, Hashed},
+    traits::{EqTarget, Hashed, Rehash},
 };
 
 use super::{cm::CharMatrix, cs::CharSeq, hash::LtlHash};
@@ -50,10 +50,32 @@ impl Hashed for LtlCharac {
     }
 }
 
+impl Rehash for LtlCharac {
+    fn set_hash(&mut self, hash: LtlHash) {
+        self.cm_hash = hash;
+    }
+
+    fn next_hash(hash: LtlHash) -> LtlHash {
+        LtlHash(hash.0.wrapping_add(1))
+    }
+
+    fn same_value(&self, other: &Self) -> bool {
+        self.cm == other.cm
+    }
+}
+
 impl EqTarget for LtlCharac {
     type TargetType = [bool];
 
-    fn eq_target(&self, target: &Self::TargetType) -> bool {
-        self.cm.is_equivalent(target)
+    fn errors(&self, target: &Self::TargetType) -> usize {
+        self.cm.errors(target)
+    }
+
+    fn eq_target(&self, target: &Self::TargetType, max_errors: usize) -> bool {
+        if max_errors == 0 {
+            self.cm.is_equivalent(target)
+        } else {
+            self.errors(target) <= max_errors
+        }
     }
 }
@@ -1,10 +1,10 @@
 //! Characteristic Matrix of LTL formulas.
 use std::{
     hash::{Hash, Hasher},
-    ops::{BitAnd, BitOr},
+    ops::{BitAnd, BitOr, Not},
 };
 
-use crate::HashType;
+use crate::{cache::collisions::CacheHasher, ops::interval::Interval, HashType};
 
 use super::{cs::CharSeq, hash::LtlHash};
 
@@ -13,11 +13,9 @@ pub struct CharMatrix {
     pub(crate) seqs: Box<[CharSeq]>,
 }
 
-type CmHasher = fxhash::FxHasher64;
-
 impl CharMatrix {
     pub(crate) fn hashed(&self) -> LtlHash {
-        let mut h = CmHasher::default();
+        let mut h = CacheHasher::default();
         self.hash(&mut h);
         LtlHash(h.finish() as HashType)
     }
@@ -28,6 +26,15 @@ impl CharMatrix {
             .zip(target)
             .all(|(cm, b)| cm.accepts() == *b)
     }
+
+    /// Number of traces whose acceptance differs from `target`.
+    pub(crate) fn errors(&self, target: &[bool]) -> usize {
+        self.seqs
+            .iter()
+            .zip(target)
+            .filter(|(cm, b)| cm.accepts() != **b)
+            .count()
+    }
 }
 
 impl FromIterator<CharSeq> for CharMatrix {
@@ -61,7 +68,7 @@ macro_rules! binop_for_cm {
                 .seqs
                 .iter()
                 .zip(rhs.seqs.iter())
-                .map(|(c1, c2)| c1.$f(*c2))
+                .map(|(c1, c2)| c1.$f(c2))
                 .collect();
             CharMatrix { seqs }
         }
@@ -74,8 +81,42 @@ impl CharMatrix {
         self.seqs.iter().map(|x| x.accepts()).collect()
     }
 
-    op_for_cm!(next, globally, finally);
+    op_for_cm!(not, next, weak_next, globally, finally);
+    op_for_cm!(yesterday, weak_yesterday, once, historically);
     binop_for_cm!(bitor as or);
     binop_for_cm!(bitand as and);
     binop_for_cm!(until);
+    binop_for_cm!(weak_until);
+    binop_for_cm!(release);
+    binop_for_cm!(implies);
+    binop_for_cm!(equiv);
+    binop_for_cm!(since);
+
+    pub(crate) fn bounded_finally(&self, interval: Interval) -> Self {
+        let seqs = self
+            .seqs
+            .iter()
+            .map(|c| c.bounded_finally(interval.lo, interval.hi))
+            .collect();
+        CharMatrix { seqs }
+    }
+
+    pub(crate) fn bounded_globally(&self, interval: Interval) -> Self {
+        let seqs = self
+            .seqs
+            .iter()
+            .map(|c| c.bounded_globally(interval.lo, interval.hi))
+            .collect();
+        CharMatrix { seqs }
+    }
+
+    pub(crate) fn bounded_until(&self, rhs: &Self, interval: Interval) -> Self {
+        let seqs = self
+            .seqs
+            .iter()
+            .zip(rhs.seqs.iter())
+            .map(|(c1, c2)| c1.bounded_until(c2, interval.lo, interval.hi))
+            .collect();
+        CharMatrix { seqs }
+    }
 }
@@ -2,29 +2,304 @@
 //! and related operators.
 use std::{
     fmt::{Debug, Display},
+    io::{self, Read, Write},
     ops::{BitAnd, BitOr, Not},
 };
 
+use clap::ValueEnum;
+
+use super::persist::{invalid_data, read_u64, read_u8, write_u64, write_u8};
+
+/// Number of positions stored in a single word of a [`CharSeq`].
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// Interpretation of temporal operators at the end of a finite trace.
+///
+/// Each semantics fixes whether a formula is considered to hold "after" the last position.
+/// `X`, `F` and `U` use this value when unfolded past the end of the trace,
+/// while `G`, being the dual of `F`, is only affected under the strong view.
+/// The weak next operator `N` always holds at the last position.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, ValueEnum)]
+pub enum Semantics {
+    /// Standard LTLf semantics: `X` is a strong next,
+    /// `F p` and `p U q` require a witness inside the trace and `G p` requires `p` until the end.
+    #[default]
+    Ltlf,
+    /// Strong view: nothing holds after the end of the trace,
+    /// so that `X` is a strong next and `G` never holds.
+    Strong,
+    /// Weak view: everything holds after the end of the trace,
+    /// so that `X` is a weak next, `F` always holds and `U` is a weak until.
+    Weak,
+}
+
+impl Semantics {
+    /// Value after the end of the trace of formulas unfolded by `X`, `F` and `U`.
+    #[inline]
+    pub(crate) fn existential_end(self) -> bool {
+        self == Semantics::Weak
+    }
+
+    /// Value after the end of the trace of formulas unfolded by `G`.
+    #[inline]
+    pub(crate) fn universal_end(self) -> bool {
+        self != Semantics::Strong
+    }
+}
+
 /// Characteristic sequence of an LTL formula on a trace.
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+///
+/// Bit `i` is set if and only if the formula holds at position `i` of the trace.
+/// Traces of at most 64 steps are packed in a single word,
+/// longer traces are split over several words, earliest positions first.
+/// The [`End`] of the sequence determines how temporal operators behave at the end of the trace.
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct CharSeq {
-    values: u64,
+    values: Values,
     length: usize,
+    end: End,
+}
+
+/// What follows the last position of a trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum End {
+    /// The trace is finite, and interpreted with the given semantics.
+    Finite(Semantics),
+    /// The trace is a lasso `u.v^w`: the last position is followed by the given loop start,
+    /// which is the first position of `v`.
+    Loop(usize),
+}
+
+/// Storage of the bits of a [`CharSeq`].
+///
+/// Invariant: [`Values::Word`] is used if and only if the length is at most 64,
+/// so that the derived equality and hash only depend on the bits.
+#[derive(Clone, PartialEq, Eq, Hash)]
+enum Values {
+    Word(u64),
+    Words(Box<[u64]>),
+}
+
+/// Mask of the bits of the last word of a sequence of length `length` that are inside the trace.
+#[inline]
+fn last_word_mask(length: usize) -> u64 {
+    match length % WORD_BITS {
+        0 if length > 0 => u64::MAX,
+        r => (1u64 << r) - 1,
+    }
+}
+
+/// Single word LTL Finally operator: bit `i` is set if any bit `j >= i` is set.
+#[inline]
+fn finally_word(mut x: u64) -> u64 {
+    x |= x >> 1;
+    x |= x >> 2;
+    x |= x >> 4;
+    x |= x >> 8;
+    x |= x >> 16;
+    x |= x >> 32;
+    x
+}
+
+/// Single word LTL Until operator, ignoring what happens after the end of the word.
+#[inline]
+fn until_word(mut x: u64, mut y: u64) -> u64 {
+    y |= x & (y >> 1);
+    x &= x >> 1;
+    y |= x & (y >> 2);
+    x &= x >> 2;
+    y |= x & (y >> 4);
+    x &= x >> 4;
+    y |= x & (y >> 8);
+    x &= x >> 8;
+    y |= x & (y >> 16);
+    x &= x >> 16;
+    y |= x & (y >> 32);
+    y
+}
+
+/// Single word past-time Once operator: bit `i` is set if any bit `j <= i` is set.
+#[inline]
+fn once_word(mut x: u64) -> u64 {
+    x |= x << 1;
+    x |= x << 2;
+    x |= x << 4;
+    x |= x << 8;
+    x |= x << 16;
+    x |= x << 32;
+    x
+}
+
+/// Single word past-time Since operator, ignoring what happens before the start of the word.
+#[inline]
+fn since_word(mut x: u64, mut y: u64) -> u64 {
+    y |= x & (y << 1);
+    x &= x << 1;
+    y |= x & (y << 2);
+    x &= x << 2;
+    y |= x & (y << 4);
+    x &= x << 4;
+    y |= x & (y << 8);
+    x &= x << 8;
+    y |= x & (y << 16);
+    x &= x << 16;
+    y |= x & (y << 32);
+    y
+}
+
+impl CharSeq {
+    fn words(&self) -> &[u64] {
+        match &self.values {
+            Values::Word(x) => std::slice::from_ref(x),
+            Values::Words(xs) => xs,
+        }
+    }
+
+    fn words_mut(&mut self) -> &mut [u64] {
+        match &mut self.values {
+            Values::Word(x) => std::slice::from_mut(x),
+            Values::Words(xs) => xs,
+        }
+    }
+
+    /// Apply `f` word by word to `self` and `rhs`, storing the result in `self`.
+    #[inline]
+    fn zip_with(mut self, rhs: &Self, f: impl Fn(u64, u64) -> u64) -> Self {
+        assert_eq!(self.length, rhs.length);
+        debug_assert_eq!(self.end, rhs.end);
+        match (&mut self.values, &rhs.values) {
+            (Values::Word(x), Values::Word(y)) => *x = f(*x, *y),
+            _ => self
+                .words_mut()
+                .iter_mut()
+                .zip(rhs.words())
+                .for_each(|(x, &y)| *x = f(*x, y)),
+        }
+        self
+    }
+
+    /// Sequence of the same length and semantics as `self`, holding everywhere if `b` is set.
+    fn constant(&self, b: bool) -> Self {
+        let mut res = self.clone();
+        res.words_mut().iter_mut().for_each(|x| *x = 0);
+        if b {
+            res.not()
+        } else {
+            res
+        }
+    }
+
+    /// Unset the bits after the end of the trace.
+    fn truncate(mut self) -> Self {
+        let mask = last_word_mask(self.length);
+        if let Some(last) = self.words_mut().last_mut() {
+            *last &= mask;
+        }
+        self
+    }
+
+    /// Set the bit of the last position of the trace.
+    fn set_last(&mut self) {
+        if let Some(i) = self.length.checked_sub(1) {
+            self.words_mut()[i / WORD_BITS] |= 1 << (i % WORD_BITS);
+        }
+    }
+
+    /// Globally operator, assuming everything holds after the end of the trace.
+    fn weak_globally(&self) -> Self {
+        self.not().strong_finally().not()
+    }
+
+    /// Shift every position one step later, leaving the first one unset.
+    fn shift_later(&self) -> Self {
+        let mut res = self.clone();
+        match &mut res.values {
+            Values::Word(x) => *x <<= 1,
+            Values::Words(xs) => {
+                for i in (0..xs.len()).rev() {
+                    let carry = i.checked_sub(1).map_or(0, |j| xs[j] >> (WORD_BITS - 1));
+                    xs[i] = (xs[i] << 1) | carry;
+                }
+            }
+        }
+        res.truncate()
+    }
+
+    /// Shift every position one step earlier, leaving the last one unset.
+    fn shift_earlier(&self) -> Self {
+        let mut res = self.clone();
+        match &mut res.values {
+            Values::Word(x) => *x >>= 1,
+            Values::Words(xs) => {
+                for i in 0..xs.len() {
+                    let carry = xs.get(i + 1).map_or(0, |y| y << (WORD_BITS - 1));
+                    xs[i] = (xs[i] >> 1) | carry;
+                }
+            }
+        }
+        res
+    }
+
+    /// Finally operator, assuming nothing holds after the end of the trace.
+    fn strong_finally(&self) -> Self {
+        let mut res = self.clone();
+        match &mut res.values {
+            Values::Word(x) => *x = finally_word(*x),
+            Values::Words(xs) => {
+                // Whether the operand holds at some position of a later word.
+                let mut carry = false;
+                for x in xs.iter_mut().rev() {
+                    let seen = *x != 0;
+                    *x = if carry { u64::MAX } else { finally_word(*x) };
+                    carry |= seen;
+                }
+            }
+        }
+        res
+    }
+
+    /// Until operator, assuming nothing holds after the end of the trace.
+    fn strong_until(&self, rhs: &Self) -> Self {
+        assert_eq!(self.length, rhs.length);
+        let mut res = rhs.clone();
+        match (&self.values, &mut res.values) {
+            (&Values::Word(x), Values::Word(y)) => *y = until_word(x, *y),
+            _ => {
+                // Whether the until holds at the first position of the next word.
+                let mut carry = false;
+                for (&x, y) in self.words().iter().zip(res.words_mut()).rev() {
+                    *y = until_word(x, *y);
+                    if carry {
+                        // `x` holds from position `i` to the end of the word.
+                        *y |= !finally_word(!x);
+                    }
+                    carry = *y & 1 == 1;
+                }
+            }
+        }
+        res
+    }
 }
 
 impl Not for CharSeq {
     type Output = Self;
 
+    fn not(mut self) -> Self::Output {
+        let mask = last_word_mask(self.length);
+        let words = self.words_mut();
+        words.iter_mut().for_each(|x| *x = x.not());
+        if let Some(last) = words.last_mut() {
+            *last &= mask;
+        }
+        self
+    }
+}
+
+impl Not for &CharSeq {
+    type Output = CharSeq;
+
     fn not(self) -> Self::Output {
-        let CharSeq { values: x, length } = self;
-        let values = x.not();
-        // Edge case: shifting 1u64 by 64 gives 1 in release mode, and panics in debug mode.
-        let values = if self.length < 64 {
-            values & ((1u64 << self.length) - 1)
-        } else {
-            values
-        };
-        CharSeq { values, length }
+        self.clone().not()
     }
 }
 
@@ -32,16 +307,15 @@ impl BitOr for CharSeq {
     type Output = Self;
 
     fn bitor(self, rhs: Self) -> Self::Output {
-        let CharSeq { values: x, length } = self;
-        let CharSeq {
-            values: y,
-            length: l2,
-        } = rhs;
-        assert_eq!(length, l2);
-        CharSeq {
-            values: x.bitor(y),
-            length,
-        }
+        self.zip_with(&rhs, u64::bitor)
+    }
+}
+
+impl BitOr for &CharSeq {
+    type Output = CharSeq;
+
+    fn bitor(self, rhs: Self) -> Self::Output {
+        self.clone().zip_with(rhs, u64::bitor)
     }
 }
 
@@ -49,16 +323,15 @@ impl BitAnd for CharSeq {
     type Output = Self;
 
     fn bitand(self, rhs: Self) -> Self::Output {
-        let CharSeq { values: x, length } = self;
-        let CharSeq {
-            values: y,
-            length: l2,
-        } = rhs;
-        assert_eq!(length, l2);
-        CharSeq {
-            values: x.bitand(y),
-            length,
-        }
+        self.zip_with(&rhs, u64::bitand)
+    }
+}
+
+impl BitAnd for &CharSeq {
+    type Output = CharSeq;
+
+    fn bitand(self, rhs: Self) -> Self::Output {
+        self.clone().zip_with(rhs, u64::bitand)
     }
 }
 
@@ -67,65 +340,418 @@ impl CharSeq {
         self.length
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
     /// Whether the formula accepts the trace,
     /// i.e. it is true starting from the first position.
     #[inline]
     pub(crate) fn accepts(&self) -> bool {
-        (self.values & 1) == 1
+        (self.words()[0] & 1) == 1
     }
 
-    /// LTL Next operator (X)
+    /// Whether the formula holds at position `i` of the trace.
     #[inline]
-    pub(crate) fn next(mut self) -> Self {
-        self.values >>= 1;
+    pub(crate) fn get(&self, i: usize) -> bool {
+        (self.words()[i / WORD_BITS] >> (i % WORD_BITS)) & 1 == 1
+    }
+
+    /// Position following the last one if the trace is a lasso.
+    pub fn loop_start(&self) -> Option<usize> {
+        match self.end {
+            End::Finite(_) => None,
+            End::Loop(start) => Some(start),
+        }
+    }
+
+    /// Semantics of the trace if it is finite, `None` if it is a lasso.
+    pub fn semantics(&self) -> Option<Semantics> {
+        match self.end {
+            End::Finite(semantics) => Some(semantics),
+            End::Loop(_) => None,
+        }
+    }
+
+    /// Same sequence, interpreted with the given semantics if the trace is finite.
+    ///
+    /// Lassos are left untouched, since they have no end.
+    pub fn with_semantics(mut self, semantics: Semantics) -> Self {
+        if let End::Finite(_) = self.end {
+            self.end = End::Finite(semantics);
+        }
         self
     }
 
+    /// Same sequence, on the lasso looping back to position `start` after the last position.
+    pub fn with_loop(mut self, start: usize) -> Self {
+        assert!(start < self.length, "loop start outside of the trace");
+        self.end = End::Loop(start);
+        self
+    }
+
+    /// Write the sequence in the format of the [cache files](super::persist).
+    pub(crate) fn write_to(&self, w: &mut impl Write) -> io::Result<()> {
+        write_u64(w, self.length as u64)?;
+        match self.end {
+            End::Finite(semantics) => {
+                write_u8(w, 0)?;
+                write_u8(
+                    w,
+                    match semantics {
+                        Semantics::Ltlf => 0,
+                        Semantics::Strong => 1,
+                        Semantics::Weak => 2,
+                    },
+                )?;
+            }
+            End::Loop(start) => {
+                write_u8(w, 1)?;
+                write_u64(w, start as u64)?;
+            }
+        }
+        self.words().iter().try_for_each(|&x| write_u64(w, x))
+    }
+
+    /// Read a sequence written by [`CharSeq::write_to`].
+    pub(crate) fn read_from(r: &mut impl Read) -> io::Result<Self> {
+        let length = read_u64(r)? as usize;
+        let end = match read_u8(r)? {
+            0 => End::Finite(match read_u8(r)? {
+                0 => Semantics::Ltlf,
+                1 => Semantics::Strong,
+                2 => Semantics::Weak,
+                _ => return Err(invalid_data("unknown semantics")),
+            }),
+            1 => match read_u64(r)? as usize {
+                start if start < length => End::Loop(start),
+                _ => return Err(invalid_data("loop start outside of the trace")),
+            },
+            _ => return Err(invalid_data("unknown end of trace")),
+        };
+        let values = if length <= WORD_BITS {
+            Values::Word(read_u64(r)?)
+        } else {
+            let words = (0..length.div_ceil(WORD_BITS))
+                .map(|_| read_u64(r))
+                .collect::<io::Result<_>>()?;
+            Values::Words(words)
+        };
+        Ok(Self {
+            values,
+            length,
+            end,
+        })
+    }
+
+    /// LTL Next operator (X)
+    #[inline]
+    pub(crate) fn next(&self) -> Self {
+        let mut res = self.shift_earlier();
+        let end = match self.end {
+            End::Finite(semantics) => semantics.existential_end(),
+            End::Loop(start) => self.get(start),
+        };
+        if end {
+            res.set_last();
+        }
+        res
+    }
+
+    /// LTL Weak Next operator (N), which holds at the last position of finite traces.
+    ///
+    /// It coincides with [`CharSeq::next`] on lassos.
+    #[inline]
+    pub(crate) fn weak_next(&self) -> Self {
+        let mut res = self.shift_earlier();
+        let end = match self.end {
+            End::Finite(_) => true,
+            End::Loop(start) => self.get(start),
+        };
+        if end {
+            res.set_last();
+        }
+        res
+    }
+
     /// LTL Globally operator (G)
     #[inline]
-    pub(crate) fn globally(self) -> Self {
-        self.not().finally().not()
+    pub(crate) fn globally(&self) -> Self {
+        let res = self.weak_globally();
+        let end = match self.end {
+            End::Finite(semantics) => semantics.universal_end(),
+            // The operand must hold on the whole loop.
+            End::Loop(start) => res.get(start),
+        };
+        if end {
+            res
+        } else {
+            self.constant(false)
+        }
     }
 
     /// LTL Finally operator (F)
     #[inline]
-    pub(crate) fn finally(self) -> Self {
-        let CharSeq {
-            values: mut x,
-            length,
-        } = self;
-        x |= x >> 1;
-        x |= x >> 2;
-        x |= x >> 4;
-        x |= x >> 8;
-        x |= x >> 16;
-        x |= x >> 32;
-        CharSeq { values: x, length }
+    pub(crate) fn finally(&self) -> Self {
+        let res = self.strong_finally();
+        let end = match self.end {
+            End::Finite(semantics) => semantics.existential_end(),
+            // Every position eventually reaches the loop.
+            End::Loop(start) => res.get(start),
+        };
+        if end {
+            self.constant(true)
+        } else {
+            res
+        }
+    }
+
+    /// Until operator, which is a weak until if `weak` is set
+    /// and a strong until otherwise, before applying the semantics of the trace.
+    ///
+    /// Under the strong (resp. weak) view, both are strong (resp. weak).
+    fn until_with(&self, rhs: &Self, weak: bool) -> Self {
+        let res = self.strong_until(rhs);
+        let end = match self.end {
+            End::Finite(semantics) if weak => semantics.universal_end(),
+            End::Finite(semantics) => semantics.existential_end(),
+            // `self` may also hold on the whole loop for weak untils.
+            End::Loop(start) => res.get(start) || (weak && self.weak_globally().get(start)),
+        };
+        if end {
+            // `self` may also hold until the end of the trace.
+            res | self.weak_globally()
+        } else {
+            res
+        }
     }
 
     /// LTL Until operator (U)
     #[inline]
-    pub(crate) fn until(self, rhs: Self) -> Self {
-        let CharSeq {
-            values: mut x,
-            length,
-        } = self;
-        let CharSeq {
-            values: mut y,
-            length: _l2,
-        } = rhs;
-        y |= x & (y >> 1);
-        x &= x >> 1;
-        y |= x & (y >> 2);
-        x &= x >> 2;
-        y |= x & (y >> 4);
-        x &= x >> 4;
-        y |= x & (y >> 8);
-        x &= x >> 8;
-        y |= x & (y >> 16);
-        x &= x >> 16;
-        y |= x & (y >> 32);
-        CharSeq { values: y, length }
+    pub(crate) fn until(&self, rhs: &Self) -> Self {
+        self.until_with(rhs, false)
+    }
+
+    /// LTL Weak Until operator (W)
+    #[inline]
+    pub(crate) fn weak_until(&self, rhs: &Self) -> Self {
+        self.until_with(rhs, true)
+    }
+
+    /// LTL Release operator (R): `rhs` holds up to and including
+    /// the first position where `self` holds, if any.
+    #[inline]
+    pub(crate) fn release(&self, rhs: &Self) -> Self {
+        rhs.until_with(&(self & rhs), true)
+    }
+
+    /// Boolean implication operator (->)
+    #[inline]
+    pub(crate) fn implies(&self, rhs: &Self) -> Self {
+        !self | rhs.clone()
+    }
+
+    /// Boolean equivalence operator (<->)
+    #[inline]
+    pub(crate) fn equiv(&self, rhs: &Self) -> Self {
+        !self.clone().zip_with(rhs, |x, y| x ^ y)
+    }
+}
+
+/// Interval-bounded operators.
+///
+/// They unfold into `X`, so that positions after the end of a finite trace take the value
+/// given by the semantics: existential for `F[a,b]` and `U[a,b]`, universal for `G[a,b]`.
+impl CharSeq {
+    /// Value after the end of the trace for existential (resp. universal if `universal` is set)
+    /// operators, which is irrelevant for lassos.
+    fn end_value(&self, universal: bool) -> bool {
+        match self.end {
+            End::Finite(semantics) if universal => semantics.universal_end(),
+            End::Finite(semantics) => semantics.existential_end(),
+            End::Loop(_) => false,
+        }
+    }
+
+    /// Next operator applied `k` times, filling positions after the end of a finite trace with `end`.
+    fn next_by(&self, k: usize, end: bool) -> Self {
+        if k == 0 {
+            return self.clone();
+        }
+        if let End::Loop(_) = self.end {
+            return (0..k).fold(self.clone(), |x, _| x.next());
+        }
+
+        let (q, r) = (k / WORD_BITS, k % WORD_BITS);
+        let src = self.words();
+        let mut res = self.clone();
+        for (i, x) in res.words_mut().iter_mut().enumerate() {
+            let low = src.get(i + q).map_or(0, |y| y >> r);
+            let high = match r {
+                0 => 0,
+                _ => src.get(i + q + 1).map_or(0, |y| y << (WORD_BITS - r)),
+            };
+            *x = low | high;
+        }
+        if end {
+            // Set the last `k` positions.
+            let start = self.length.saturating_sub(k);
+            for (i, x) in res.words_mut().iter_mut().enumerate() {
+                let shift = start.saturating_sub(i * WORD_BITS);
+                if shift < WORD_BITS {
+                    *x |= u64::MAX << shift;
+                }
+            }
+        }
+        res.truncate()
+    }
+
+    /// Equivalent interval `[lo, hi]` with bounds less than twice the length of the trace.
+    ///
+    /// Once `k` reaches the length of the trace, the next operator applied `k` times is constant
+    /// on finite traces, and periodic with the loop on lassos, so that larger bounds only
+    /// repeat values already seen.
+    fn clamp_interval(&self, lo: usize, hi: usize) -> (usize, usize) {
+        let period = match self.end {
+            End::Finite(_) => 1,
+            End::Loop(start) => (self.length - start).max(1),
+        };
+        let shift = lo.saturating_sub(self.length) / period * period;
+        let (lo, hi) = (lo - shift, hi - shift);
+        (lo, hi.min(lo.max(self.length) + period - 1))
+    }
+
+    /// Bounded Finally operator (F[lo,hi])
+    #[inline]
+    pub(crate) fn bounded_finally(&self, lo: usize, hi: usize) -> Self {
+        let (lo, hi) = self.clamp_interval(lo, hi);
+        let end = self.end_value(false);
+        let mut step = self.next_by(lo, end);
+        let mut res = step.clone();
+        for _ in lo..hi {
+            step = step.next_by(1, end);
+            res = res | step.clone();
+        }
+        res
+    }
+
+    /// Bounded Globally operator (G[lo,hi])
+    #[inline]
+    pub(crate) fn bounded_globally(&self, lo: usize, hi: usize) -> Self {
+        let (lo, hi) = self.clamp_interval(lo, hi);
+        let end = self.end_value(true);
+        let mut step = self.next_by(lo, end);
+        let mut res = step.clone();
+        for _ in lo..hi {
+            step = step.next_by(1, end);
+            res = res & step.clone();
+        }
+        res
+    }
+
+    /// Bounded Until operator (U[lo,hi]): `rhs` holds within `lo` to `hi` steps,
+    /// and `self` holds until then.
+    #[inline]
+    pub(crate) fn bounded_until(&self, rhs: &Self, lo: usize, hi: usize) -> Self {
+        let (lo, hi) = self.clamp_interval(lo, hi);
+        let end = self.end_value(false);
+        let mut res = self.constant(false);
+        // `self` holds at the `k` next positions.
+        let mut prefix = self.constant(true);
+        let (mut lhs_k, mut rhs_k) = (self.clone(), rhs.clone());
+        for k in 0..=hi {
+            if k >= lo {
+                res = res | (&prefix & &rhs_k);
+            }
+            if k == hi {
+                break;
+            }
+            prefix = &prefix & &lhs_k;
+            lhs_k = lhs_k.next_by(1, end);
+            rhs_k = rhs_k.next_by(1, end);
+        }
+        res
+    }
+}
+
+/// Past-time operators.
+///
+/// They are only defined on finite traces: on a lasso, the past of a position
+/// depends on the number of times the loop was taken before reaching it.
+impl CharSeq {
+    fn assert_finite(&self) {
+        assert!(
+            matches!(self.end, End::Finite(_)),
+            "past-time operators are not supported on lassos"
+        );
+    }
+
+    /// Past-time Yesterday operator (Y), which never holds at the first position.
+    #[inline]
+    pub(crate) fn yesterday(&self) -> Self {
+        self.assert_finite();
+        self.shift_later()
+    }
+
+    /// Past-time Weak Yesterday operator (Z), which always holds at the first position.
+    #[inline]
+    pub(crate) fn weak_yesterday(&self) -> Self {
+        self.assert_finite();
+        let mut res = self.shift_later();
+        if !res.is_empty() {
+            res.words_mut()[0] |= 1;
+        }
+        res
+    }
+
+    /// Past-time Once operator (O)
+    #[inline]
+    pub(crate) fn once(&self) -> Self {
+        self.assert_finite();
+        let mut res = self.clone();
+        match &mut res.values {
+            Values::Word(x) => *x = once_word(*x),
+            Values::Words(xs) => {
+                // Whether the operand holds at some position of an earlier word.
+                let mut carry = false;
+                for x in xs.iter_mut() {
+                    let seen = *x != 0;
+                    *x = if carry { u64::MAX } else { once_word(*x) };
+                    carry |= seen;
+                }
+            }
+        }
+        res.truncate()
+    }
+
+    /// Past-time Historically operator (H)
+    #[inline]
+    pub(crate) fn historically(&self) -> Self {
+        self.not().once().not()
+    }
+
+    /// Past-time Since operator (S)
+    #[inline]
+    pub(crate) fn since(&self, rhs: &Self) -> Self {
+        self.assert_finite();
+        assert_eq!(self.length, rhs.length);
+        let mut res = rhs.clone();
+        match (&self.values, &mut res.values) {
+            (&Values::Word(x), Values::Word(y)) => *y = since_word(x, *y),
+            _ => {
+                // Whether the since holds at the last position of the previous word.
+                let mut carry = false;
+                for (&x, y) in self.words().iter().zip(res.words_mut()) {
+                    *y = since_word(x, *y);
+                    if carry {
+                        // `x` holds from the start of the word to position `i`.
+                        *y |= !once_word(!x);
+                    }
+                    carry = *y >> (WORD_BITS - 1) == 1;
+                }
+            }
+        }
+        res.truncate()
     }
 }
 
@@ -137,9 +763,8 @@ impl Debug for CharSeq {
 
 impl Display for CharSeq {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let x = self.values;
         for i in 0..self.len() {
-            write!(f, "{}", (x >> i) & 1)?;
+            write!(f, "{}", self.get(i) as u8)?;
         }
         Ok(())
     }
@@ -147,20 +772,26 @@ impl Display for CharSeq {
 
 impl FromIterator<bool> for CharSeq {
     fn from_iter<T: IntoIterator<Item = bool>>(iter: T) -> Self {
-        let mut x: u64 = 0;
+        let mut words = vec![0u64];
         let mut len = 0;
-        iter.into_iter().enumerate().for_each(|(i, b)| {
-            if i >= 64 {
-                panic!("Trace is too long! (max len 64)");
+        for b in iter {
+            if len > 0 && len % WORD_BITS == 0 {
+                words.push(0);
             }
             if b {
-                x |= 1 << i;
+                *words.last_mut().unwrap() |= 1 << (len % WORD_BITS);
             }
             len += 1;
-        });
+        }
+        let values = if len <= WORD_BITS {
+            Values::Word(words[0])
+        } else {
+            Values::Words(words.into_boxed_slice())
+        };
         CharSeq {
-            values: x,
+            values,
             length: len,
+            end: End::Finite(Semantics::default()),
         }
     }
 }
@@ -172,37 +803,35 @@ mod tests {
     use super::*;
 
     #[allow(non_snake_case)]
-    pub(crate) fn X(phi: CharSeq) -> CharSeq {
+    pub(crate) fn X(phi: &CharSeq) -> CharSeq {
         phi.next()
     }
 
     #[allow(non_snake_case)]
-    pub(crate) fn G(phi: CharSeq) -> CharSeq {
+    pub(crate) fn G(phi: &CharSeq) -> CharSeq {
         phi.globally()
     }
 
     #[allow(non_snake_case)]
-    pub(crate) fn F(phi: CharSeq) -> CharSeq {
+    pub(crate) fn F(phi: &CharSeq) -> CharSeq {
         phi.finally()
     }
 
     #[allow(non_snake_case)]
-    pub(crate) fn U(phi: CharSeq, psi: CharSeq) -> CharSeq {
+    pub(crate) fn U(phi: &CharSeq, psi: &CharSeq) -> CharSeq {
         phi.until(psi)
     }
 
+    /// Maximum length of random sequences, chosen to span several words.
+    const MAX_LEN: usize = 200;
+
     fn random_seq_with_len(len: usize, rng: &mut impl Rng) -> CharSeq {
-        let x: u64 = rng.gen();
-        let x = if len < 64 { x & ((1u64 << len) - 1) } else { x };
-        CharSeq {
-            values: x,
-            length: len,
-        }
+        (0..len).map(|_| rng.gen()).collect()
     }
 
     fn random_pair() -> (CharSeq, CharSeq) {
         let mut rng = thread_rng();
-        let len = rng.gen_range(0..64);
+        let len = rng.gen_range(0..MAX_LEN);
         (
             random_seq_with_len(len, &mut rng),
             random_seq_with_len(len, &mut rng),
@@ -211,15 +840,19 @@ mod tests {
 
     fn random_seq() -> CharSeq {
         let mut rng = thread_rng();
-        let len = rng.gen_range(0..64);
+        let len = rng.gen_range(0..MAX_LEN);
         random_seq_with_len(len, &mut rng)
     }
 
+    fn constant_seq(b: bool, len: usize) -> CharSeq {
+        std::iter::repeat_n(b, len).collect()
+    }
+
     #[test]
     fn phi_and_not_phi_is_zero() {
         for _ in 0..100 {
             let x = random_seq();
-            assert_eq!((x & !x).values, 0);
+            assert_eq!(&x & &!&x, constant_seq(false, x.len()));
         }
     }
 
@@ -227,7 +860,7 @@ mod tests {
     fn phi_or_not_phi_is_true() {
         for _ in 0..100 {
             let x = random_seq();
-            assert_eq!((x | !x).values, (1 << x.length) - 1);
+            assert_eq!(&x | &!&x, constant_seq(true, x.len()));
         }
     }
 
@@ -235,7 +868,7 @@ mod tests {
     fn not_is_involutive() {
         for _ in 0..100 {
             let x = random_seq();
-            assert_eq!(x, !!x);
+            assert_eq!(x, !!&x);
         }
     }
 
@@ -243,7 +876,7 @@ mod tests {
     fn and_is_idempotent() {
         for _ in 0..100 {
             let x = random_seq();
-            assert_eq!(x & x, x);
+            assert_eq!(&x & &x, x);
         }
     }
 
@@ -251,7 +884,7 @@ mod tests {
     fn or_is_idempotent() {
         for _ in 0..100 {
             let x = random_seq();
-            assert_eq!(x | x, x);
+            assert_eq!(&x | &x, x);
         }
     }
 
@@ -260,7 +893,7 @@ mod tests {
     fn de_morgan_or_and() {
         for _ in 0..100 {
             let (x1, x2) = random_pair();
-            assert_eq!(!(x1 | x2), !x1 & !x2);
+            assert_eq!(!(&x1 | &x2), !x1 & !x2);
         }
     }
 
@@ -269,7 +902,7 @@ mod tests {
     fn de_morgan_f_g() {
         for _ in 0..100 {
             let x = random_seq();
-            assert_eq!(!F(x), G(!x));
+            assert_eq!(!F(&x), G(&!&x));
         }
     }
 
@@ -277,7 +910,7 @@ mod tests {
     fn ff_is_f() {
         for _ in 0..100 {
             let x = random_seq();
-            assert_eq!(F(F(x)), F(x));
+            assert_eq!(F(&F(&x)), F(&x));
         }
     }
 
@@ -285,7 +918,7 @@ mod tests {
     fn f_as_phi_or_x_f_phi() {
         for _ in 0..100 {
             let x = random_seq();
-            assert_eq!(F(x), x | X(F(x)));
+            assert_eq!(F(&x), &x | &X(&F(&x)));
         }
     }
 
@@ -293,7 +926,7 @@ mod tests {
     fn and_distributes_g() {
         for _ in 0..100 {
             let (x, y) = random_pair();
-            assert_eq!(G(x & y), G(x) & G(y));
+            assert_eq!(G(&(&x & &y)), G(&x) & G(&y));
         }
     }
 
@@ -301,7 +934,7 @@ mod tests {
     fn or_distributes_f() {
         for _ in 0..100 {
             let (x, y) = random_pair();
-            assert_eq!(F(x | y), F(x) | F(y));
+            assert_eq!(F(&(&x | &y)), F(&x) | F(&y));
         }
     }
 
@@ -309,7 +942,7 @@ mod tests {
     fn gg_is_g() {
         for _ in 0..100 {
             let x = random_seq();
-            assert_eq!(G(G(x)), G(x));
+            assert_eq!(G(&G(&x)), G(&x));
         }
     }
 
@@ -317,7 +950,354 @@ mod tests {
     fn expand_u() {
         for _ in 0..100 {
             let (x, y) = random_pair();
-            assert_eq!(U(x, y), y | (x & X(U(x, y))));
+            assert_eq!(U(&x, &y), &y | &(&x & &X(&U(&x, &y))));
+        }
+    }
+
+    #[test]
+    fn weak_until_as_until_or_globally() {
+        for _ in 0..100 {
+            let (x, y) = random_pair();
+            assert_eq!(x.weak_until(&y), U(&x, &y) | G(&x));
+        }
+    }
+
+    #[test]
+    fn release_is_dual_of_until() {
+        for _ in 0..100 {
+            let (x, y) = random_pair();
+            assert_eq!(x.release(&y), !U(&!&x, &!&y));
+        }
+    }
+
+    #[test]
+    fn release_as_weak_until() {
+        for _ in 0..100 {
+            let (x, y) = random_pair();
+            assert_eq!(x.release(&y), y.weak_until(&(&x & &y)));
+        }
+    }
+
+    #[test]
+    fn implies_as_not_or() {
+        for _ in 0..100 {
+            let (x, y) = random_pair();
+            assert_eq!(x.implies(&y), !&x | y);
+        }
+    }
+
+    #[test]
+    fn equiv_as_double_implication() {
+        for _ in 0..100 {
+            let (x, y) = random_pair();
+            assert_eq!(x.equiv(&y), x.implies(&y) & y.implies(&x));
+        }
+    }
+
+    #[test]
+    fn historically_as_not_once_not() {
+        for _ in 0..100 {
+            let x = random_seq();
+            assert_eq!(x.historically(), !(!&x).once());
+        }
+    }
+
+    #[test]
+    fn expand_s() {
+        for _ in 0..100 {
+            let (x, y) = random_pair();
+            let s = x.since(&y);
+            assert_eq!(s, &y | &(&x & &s.yesterday()));
+        }
+    }
+
+    #[test]
+    fn past_operators_match_naive_evaluation() {
+        for _ in 0..100 {
+            let (x, y) = random_pair();
+            let len = x.len();
+
+            let yesterday = (0..len).map(|i| i > 0 && x.get(i - 1));
+            assert_eq!(x.yesterday(), yesterday.collect());
+
+            let weak_yesterday = (0..len).map(|i| i == 0 || x.get(i - 1));
+            assert_eq!(x.weak_yesterday(), weak_yesterday.collect());
+
+            let once = (0..len).map(|i| (0..=i).any(|j| x.get(j)));
+            assert_eq!(x.once(), once.collect());
+
+            let historically = (0..len).map(|i| (0..=i).all(|j| x.get(j)));
+            assert_eq!(x.historically(), historically.collect());
+
+            let since =
+                (0..len).map(|i| (0..=i).any(|j| y.get(j) && (j + 1..=i).all(|k| x.get(k))));
+            assert_eq!(x.since(&y), since.collect());
+        }
+    }
+
+    #[test]
+    fn operators_match_naive_evaluation() {
+        for _ in 0..100 {
+            let (x, y) = random_pair();
+            let len = x.len();
+
+            let next = (0..len).map(|i| i + 1 < len && x.get(i + 1));
+            assert_eq!(X(&x), next.collect());
+
+            let finally = (0..len).map(|i| (i..len).any(|j| x.get(j)));
+            assert_eq!(F(&x), finally.collect());
+
+            let globally = (0..len).map(|i| (i..len).all(|j| x.get(j)));
+            assert_eq!(G(&x), globally.collect());
+
+            let until = (0..len).map(|i| (i..len).any(|j| y.get(j) && (i..j).all(|k| x.get(k))));
+            assert_eq!(U(&x, &y), until.collect());
+        }
+    }
+
+    /// Naive evaluation of a temporal operator, unfolded backwards from the end of the trace:
+    /// `step(i, later)` is the value at position `i` given the value `later` at position `i + 1`,
+    /// and `end` is the value after the last position.
+    fn unfold(len: usize, end: bool, step: impl Fn(usize, bool) -> bool) -> Vec<bool> {
+        let mut res = vec![end; len + 1];
+        for i in (0..len).rev() {
+            res[i] = step(i, res[i + 1]);
+        }
+        res.truncate(len);
+        res
+    }
+
+    #[test]
+    fn operators_match_naive_evaluation_for_each_semantics() {
+        for &semantics in Semantics::value_variants() {
+            // Value after the end of the trace for `X`, `F` and `U`, and for `G`.
+            let (existential, universal) = match semantics {
+                Semantics::Ltlf => (false, true),
+                Semantics::Strong => (false, false),
+                Semantics::Weak => (true, true),
+            };
+            let expected =
+                |v: Vec<bool>| v.into_iter().collect::<CharSeq>().with_semantics(semantics);
+
+            for _ in 0..100 {
+                let (x, y) = random_pair();
+                let (x, y) = (x.with_semantics(semantics), y.with_semantics(semantics));
+                let len = x.len();
+                let at = |s: &CharSeq, i: usize, end: bool| if i < len { s.get(i) } else { end };
+
+                let next = (0..len).map(|i| at(&x, i + 1, existential)).collect();
+                assert_eq!(X(&x), expected(next));
+
+                let weak_next = (0..len).map(|i| at(&x, i + 1, true)).collect();
+                assert_eq!(x.weak_next(), expected(weak_next));
+
+                let finally = unfold(len, existential, |i, later| x.get(i) || later);
+                assert_eq!(F(&x), expected(finally));
+
+                let globally = unfold(len, universal, |i, later| x.get(i) && later);
+                assert_eq!(G(&x), expected(globally));
+
+                let until = unfold(len, existential, |i, later| y.get(i) || (x.get(i) && later));
+                assert_eq!(U(&x, &y), expected(until));
+
+                let weak_until = unfold(len, universal, |i, later| y.get(i) || (x.get(i) && later));
+                assert_eq!(x.weak_until(&y), expected(weak_until));
+
+                let release = unfold(len, universal, |i, later| y.get(i) && (x.get(i) || later));
+                assert_eq!(x.release(&y), expected(release));
+            }
+        }
+    }
+
+    #[test]
+    fn bounded_operators_match_naive_evaluation_for_each_semantics() {
+        let mut rng = thread_rng();
+        for &semantics in Semantics::value_variants() {
+            let (existential, universal) = match semantics {
+                Semantics::Ltlf => (false, true),
+                Semantics::Strong => (false, false),
+                Semantics::Weak => (true, true),
+            };
+            let expected =
+                |v: Vec<bool>| v.into_iter().collect::<CharSeq>().with_semantics(semantics);
+
+            for _ in 0..100 {
+                let (x, y) = random_pair();
+                let (x, y) = (x.with_semantics(semantics), y.with_semantics(semantics));
+                let len = x.len();
+                let lo = rng.gen_range(0..=len);
+                let hi = lo + rng.gen_range(0..4);
+                let at = |s: &CharSeq, i: usize, end: bool| if i < len { s.get(i) } else { end };
+
+                let finally = (0..len)
+                    .map(|i| (lo..=hi).any(|k| at(&x, i + k, existential)))
+                    .collect();
+                assert_eq!(x.bounded_finally(lo, hi), expected(finally));
+
+                let globally = (0..len)
+                    .map(|i| (lo..=hi).all(|k| at(&x, i + k, universal)))
+                    .collect();
+                assert_eq!(x.bounded_globally(lo, hi), expected(globally));
+
+                let until = (0..len)
+                    .map(|i| {
+                        (lo..=hi).any(|k| {
+                            at(&y, i + k, existential) && (0..k).all(|j| at(&x, i + j, existential))
+                        })
+                    })
+                    .collect();
+                assert_eq!(x.bounded_until(&y, lo, hi), expected(until));
+            }
+        }
+    }
+
+    #[test]
+    fn bounded_operators_special_cases() {
+        for _ in 0..100 {
+            let (x, y) = random_pair();
+            let len = x.len();
+            assert_eq!(x.bounded_finally(0, 0), x);
+            assert_eq!(x.bounded_finally(1, 1), X(&x));
+            assert_eq!(x.bounded_finally(0, len), F(&x));
+            assert_eq!(x.bounded_globally(0, len), G(&x));
+            assert_eq!(x.bounded_until(&y, 0, len), U(&x, &y));
+        }
+    }
+
+    #[test]
+    fn bounded_operators_with_huge_bounds() {
+        const HUGE: usize = 4_000_000_000;
+        for &semantics in Semantics::value_variants() {
+            for _ in 0..100 {
+                let (x, y) = random_pair();
+                let (x, y) = (x.with_semantics(semantics), y.with_semantics(semantics));
+                let len = x.len();
+                assert_eq!(x.bounded_finally(0, HUGE), F(&x));
+                assert_eq!(x.bounded_globally(0, HUGE), G(&x));
+                assert_eq!(x.bounded_until(&y, 0, HUGE), U(&x, &y));
+                assert_eq!(x.bounded_finally(HUGE, HUGE), x.bounded_finally(len, len));
+                assert_eq!(x.bounded_globally(HUGE, HUGE), x.bounded_globally(len, len));
+                assert_eq!(
+                    x.bounded_until(&y, HUGE, HUGE),
+                    x.bounded_until(&y, len, len)
+                );
+            }
+        }
+
+        for _ in 0..100 {
+            let (x, y) = random_lasso_pair();
+            let (len, start) = (x.len(), x.loop_start().unwrap());
+            assert_eq!(x.bounded_finally(0, HUGE), F(&x));
+            assert_eq!(x.bounded_globally(0, HUGE), G(&x));
+            assert_eq!(x.bounded_until(&y, 0, HUGE), U(&x, &y));
+
+            // Position reached from `i` after `k` steps.
+            let step = |i: usize, k: usize| match i + k {
+                j if j < len => j,
+                j => start + (j - start) % (len - start),
+            };
+            let expected = |v: Vec<bool>| v.into_iter().collect::<CharSeq>().with_loop(start);
+            let (lo, hi) = (HUGE, HUGE + 2);
+
+            let bounded_finally = (0..len)
+                .map(|i| (lo..=hi).any(|k| x.get(step(i, k))))
+                .collect();
+            assert_eq!(x.bounded_finally(lo, hi), expected(bounded_finally));
+
+            let bounded_globally = (0..len)
+                .map(|i| (lo..=hi).all(|k| x.get(step(i, k))))
+                .collect();
+            assert_eq!(x.bounded_globally(lo, hi), expected(bounded_globally));
+
+            // All the positions reachable from `i` are visited within `2 * len` steps.
+            let bounded_until = (0..len)
+                .map(|i| {
+                    (lo..=hi).any(|k| {
+                        y.get(step(i, k)) && (0..k.min(2 * len)).all(|j| x.get(step(i, j)))
+                    })
+                })
+                .collect();
+            assert_eq!(x.bounded_until(&y, lo, hi), expected(bounded_until));
+        }
+    }
+
+    fn random_lasso_pair() -> (CharSeq, CharSeq) {
+        let mut rng = thread_rng();
+        let len = rng.gen_range(1..MAX_LEN);
+        let start = rng.gen_range(0..len);
+        (
+            random_seq_with_len(len, &mut rng).with_loop(start),
+            random_seq_with_len(len, &mut rng).with_loop(start),
+        )
+    }
+
+    #[test]
+    fn operators_match_naive_evaluation_on_lassos() {
+        for _ in 0..100 {
+            let (x, y) = random_lasso_pair();
+            let (len, start) = (x.len(), x.loop_start().unwrap());
+            let expected = |v: Vec<bool>| v.into_iter().collect::<CharSeq>().with_loop(start);
+
+            let succ = |i: usize| if i + 1 < len { i + 1 } else { start };
+            // Positions visited from `i`, long enough to reach every position of the loop.
+            let path =
+                |i: usize| std::iter::successors(Some(i), move |&j| Some(succ(j))).take(len + 1);
+
+            let next: Vec<_> = (0..len).map(|i| x.get(succ(i))).collect();
+            assert_eq!(X(&x), expected(next.clone()));
+            assert_eq!(x.weak_next(), expected(next));
+
+            let finally = (0..len).map(|i| path(i).any(|j| x.get(j))).collect();
+            assert_eq!(F(&x), expected(finally));
+
+            let globally = (0..len).map(|i| path(i).all(|j| x.get(j))).collect();
+            assert_eq!(G(&x), expected(globally));
+
+            let until = (0..len)
+                .map(|i| {
+                    path(i)
+                        .find(|&j| y.get(j) || !x.get(j))
+                        .is_some_and(|j| y.get(j))
+                })
+                .collect();
+            assert_eq!(U(&x, &y), expected(until));
+
+            let weak_until = (0..len)
+                .map(|i| {
+                    path(i)
+                        .find(|&j| y.get(j) || !x.get(j))
+                        .is_none_or(|j| y.get(j))
+                })
+                .collect();
+            assert_eq!(x.weak_until(&y), expected(weak_until));
+
+            let release = (0..len)
+                .map(|i| {
+                    path(i)
+                        .find(|&j| x.get(j) || !y.get(j))
+                        .is_none_or(|j| y.get(j))
+                })
+                .collect();
+            assert_eq!(x.release(&y), expected(release));
+
+            let (lo, hi) = (len / 2, len / 2 + 2);
+            // Position reached from `i` after `k` steps.
+            let step = |i: usize, k: usize| (0..k).fold(i, |j, _| succ(j));
+
+            let bounded_finally = (0..len)
+                .map(|i| (lo..=hi).any(|k| x.get(step(i, k))))
+                .collect();
+            assert_eq!(x.bounded_finally(lo, hi), expected(bounded_finally));
+
+            let bounded_globally = (0..len)
+                .map(|i| (lo..=hi).all(|k| x.get(step(i, k))))
+                .collect();
+            assert_eq!(x.bounded_globally(lo, hi), expected(bounded_globally));
+
+            let bounded_until = (0..len)
+                .map(|i| (lo..=hi).any(|k| y.get(step(i, k)) && (0..k).all(|j| x.get(step(i, j)))))
+                .collect();
+            assert_eq!(x.bounded_until(&y, lo, hi), expected(bounded_until));
         }
     }
 }
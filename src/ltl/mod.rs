@@ -8,11 +8,22 @@ pub mod charac;
 pub mod cm;
 pub mod cs;
 pub mod hash;
+pub mod persist;
 pub mod trace;
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub struct Predicate(pub(crate) String, pub(crate) PredicateForm);
 
+impl Predicate {
+    /// Name of the predicate in the alphabet, without negation.
+    pub(crate) fn name(&self) -> &str {
+        match self.1 {
+            PredicateForm::Positive(_) => &self.0,
+            PredicateForm::Negative(_) => self.0.strip_prefix('!').unwrap_or(&self.0),
+        }
+    }
+}
+
 /// Formula corresponding to a single variable `x_i`, which may be negated.
 #[derive(Debug, Hash, PartialEq, Eq, Clone)]
 pub enum PredicateForm {
@@ -0,0 +1,338 @@
+//! On-disk format of the [LTL cache](super::cache::LtlCache).
+//!
+//! The LTL enumeration only depends on the traces, the alphabet and the operators with their
+//! weights, so that its cache can be saved to a file and reused by later runs on the same
+//! traces, which resume the enumeration where the file stops.
+//!
+//! A file starts with a magic number, the [version](VERSION) of the format and the
+//! [key](cache_key) of the traces and operators, followed by the lines of the cache.
+//! Each formula is stored with its cost, its hash, its node, the children being referred to
+//! by their hashes as in memory, and its characteristic matrix.
+//! All integers are stored as little-endian `u64`, and tags as single bytes.
+use std::{
+    fs::File,
+    hash::{Hash, Hasher},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+    sync::Arc,
+};
+
+use fxhash::{FxHashSet, FxHasher64};
+use thiserror::Error;
+
+use crate::{
+    formula::{tree::FormulaTree, FormulaNode},
+    ops::{binary::LtlBinaryOp, unary::LtlUnaryOp},
+    traits::{Hashed, Rehash},
+};
+
+use super::{
+    cache::LtlCache,
+    charac::LtlCharac,
+    cs::CharSeq,
+    hash::LtlHash,
+    trace::{Operators, Trace},
+    LtlFormula, Predicate, PredicateForm,
+};
+
+/// Magic number at the start of cache files.
+const MAGIC: &[u8; 8] = b"LTLCACHE";
+/// Version of the format, to be increased whenever it changes.
+pub(crate) const VERSION: u32 = 1;
+
+#[derive(Debug, Error)]
+pub enum CacheFileError {
+    #[error("cannot read cache file: {0}")]
+    Io(#[from] io::Error),
+    #[error("not an LTL cache file")]
+    NotACacheFile,
+    #[error("unsupported cache file version {0}, expected {VERSION}")]
+    Version(u32),
+    #[error("cache file computed for other traces or operators")]
+    KeyMismatch,
+}
+
+/// Key identifying the traces, the alphabet and the operators with their weights,
+/// which determine the content of the LTL cache.
+pub(crate) fn cache_key(traces: &[Trace], alphabet: &[String], operators: &Operators) -> u64 {
+    let mut h = FxHasher64::default();
+    traces.hash(&mut h);
+    for name in alphabet {
+        (name, operators.costs.atom(name)).hash(&mut h);
+    }
+    for &op in &operators.unary {
+        (op.to_string(), operators.costs.unary(op)).hash(&mut h);
+    }
+    for &op in &operators.binary {
+        (op.to_string(), operators.costs.binary(op)).hash(&mut h);
+    }
+    h.finish()
+}
+
+/// Save the first `nb_lines` lines of `cache` to the file at `path`, identified by `key`.
+pub(crate) fn save_file(
+    path: &Path,
+    cache: &LtlCache,
+    key: u64,
+    nb_lines: usize,
+) -> io::Result<()> {
+    let mut w = BufWriter::new(File::create(path)?);
+    save(&mut w, cache, key, nb_lines)?;
+    w.flush()
+}
+
+/// Load the cache saved in the file at `path`, see [`load`].
+pub(crate) fn load_file(
+    path: &Path,
+    key: u64,
+    atoms: Vec<LtlFormula>,
+) -> Result<LtlCache, CacheFileError> {
+    let mut r = BufReader::new(File::open(path)?);
+    load(&mut r, key, atoms)
+}
+
+/// Write the first `nb_lines` lines of `cache` to `w`, identified by `key`.
+pub(crate) fn save(
+    w: &mut impl Write,
+    cache: &LtlCache,
+    key: u64,
+    nb_lines: usize,
+) -> io::Result<()> {
+    let lines = &cache.lines[..nb_lines.min(cache.lines.len())];
+    w.write_all(MAGIC)?;
+    w.write_all(&VERSION.to_le_bytes())?;
+    write_u64(w, key)?;
+    write_u64(w, lines.len() as u64)?;
+    for line in lines {
+        write_u64(w, line.len() as u64)?;
+        for f in line {
+            write_formula(w, f)?;
+        }
+    }
+    Ok(())
+}
+
+/// Read a cache written by [`save`] with the same `key`.
+///
+/// The `atoms` more expensive than the formulas read are added when enumeration reaches their cost.
+pub(crate) fn load(
+    r: &mut impl Read,
+    key: u64,
+    atoms: Vec<LtlFormula>,
+) -> Result<LtlCache, CacheFileError> {
+    let mut magic = [0; 8];
+    r.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        return Err(CacheFileError::NotACacheFile);
+    }
+    let mut version = [0; 4];
+    r.read_exact(&mut version)?;
+    let version = u32::from_le_bytes(version);
+    if version != VERSION {
+        return Err(CacheFileError::Version(version));
+    }
+    if read_u64(r)? != key {
+        return Err(CacheFileError::KeyMismatch);
+    }
+
+    let nb_lines = read_u64(r)? as usize;
+    let mut hashes = FxHashSet::default();
+    let mut lines = vec![];
+    for cost in 0..nb_lines {
+        let mut line = vec![];
+        for _ in 0..read_u64(r)? {
+            let f = read_formula(r)?;
+            // Children are cheaper than their parents, hence read before them.
+            let children_known = match &f.node {
+                FormulaNode::Base(_) => true,
+                FormulaNode::Unary { child, .. } => hashes.contains(child),
+                FormulaNode::Binary { left, right, .. } => {
+                    hashes.contains(left) && hashes.contains(right)
+                }
+            };
+            if f.cost != cost || !children_known || !hashes.insert(f.hashed()) {
+                return Err(invalid_data("inconsistent formulas").into());
+            }
+            line.push(f);
+        }
+        lines.push(line);
+    }
+    Ok(LtlCache::from_lines(lines, atoms))
+}
+
+fn write_formula(w: &mut impl Write, f: &LtlFormula) -> io::Result<()> {
+    write_u64(w, f.cost as u64)?;
+    write_u64(w, f.hashed().0)?;
+    match &f.node {
+        FormulaNode::Base(base) => {
+            let FormulaTree::Atom(Predicate(name, form)) = base.as_ref() else {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "only atoms can be saved as base formulas",
+                ));
+            };
+            write_u8(w, 0)?;
+            write_str(w, name)?;
+            match form {
+                PredicateForm::Positive(i) => {
+                    write_u8(w, 0)?;
+                    write_u64(w, *i as u64)?;
+                }
+                PredicateForm::Negative(i) => {
+                    write_u8(w, 1)?;
+                    write_u64(w, *i as u64)?;
+                }
+            }
+        }
+        FormulaNode::Unary { op, child } => {
+            write_u8(w, 1)?;
+            write_str(w, &op.to_string())?;
+            write_u64(w, child.0)?;
+        }
+        FormulaNode::Binary { op, left, right } => {
+            write_u8(w, 2)?;
+            write_str(w, &op.to_string())?;
+            write_u64(w, left.0)?;
+            write_u64(w, right.0)?;
+        }
+    }
+    write_u64(w, f.charac.cm.seqs.len() as u64)?;
+    f.charac.cm.seqs.iter().try_for_each(|cs| cs.write_to(w))
+}
+
+fn read_formula(r: &mut impl Read) -> io::Result<LtlFormula> {
+    let cost = read_u64(r)? as usize;
+    let hash = LtlHash(read_u64(r)?);
+    let node = match read_u8(r)? {
+        0 => {
+            let name = read_str(r)?;
+            let form = match read_u8(r)? {
+                0 => PredicateForm::Positive(read_u64(r)? as usize),
+                1 => PredicateForm::Negative(read_u64(r)? as usize),
+                _ => return Err(invalid_data("unknown predicate form")),
+            };
+            FormulaNode::Base(Arc::new(FormulaTree::Atom(Predicate(name, form))))
+        }
+        1 => {
+            let op = LtlUnaryOp::try_from(read_str(r)?.as_str())
+                .map_err(|_| invalid_data("unknown unary operator"))?;
+            let child = LtlHash(read_u64(r)?);
+            FormulaNode::Unary { op, child }
+        }
+        2 => {
+            let op = LtlBinaryOp::try_from(read_str(r)?.as_str())
+                .map_err(|_| invalid_data("unknown binary operator"))?;
+            let left = LtlHash(read_u64(r)?);
+            let right = LtlHash(read_u64(r)?);
+            FormulaNode::Binary { op, left, right }
+        }
+        _ => return Err(invalid_data("unknown formula node")),
+    };
+    let mut charac = (0..read_u64(r)?)
+        .map(|_| CharSeq::read_from(r))
+        .collect::<io::Result<LtlCharac>>()?;
+    // The hash may differ from the one of the matrix after a collision.
+    charac.set_hash(hash);
+    Ok(LtlFormula { charac, cost, node })
+}
+
+pub(super) fn invalid_data(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+pub(super) fn write_u8(w: &mut impl Write, x: u8) -> io::Result<()> {
+    w.write_all(&[x])
+}
+
+pub(super) fn read_u8(r: &mut impl Read) -> io::Result<u8> {
+    let mut buf = [0];
+    r.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+pub(super) fn write_u64(w: &mut impl Write, x: u64) -> io::Result<()> {
+    w.write_all(&x.to_le_bytes())
+}
+
+pub(super) fn read_u64(r: &mut impl Read) -> io::Result<u64> {
+    let mut buf = [0; 8];
+    r.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn write_str(w: &mut impl Write, s: &str) -> io::Result<()> {
+    write_u64(w, s.len() as u64)?;
+    w.write_all(s.as_bytes())
+}
+
+fn read_str(r: &mut impl Read) -> io::Result<String> {
+    let len = read_u64(r)?;
+    let mut buf = vec![];
+    r.take(len).read_to_end(&mut buf)?;
+    if buf.len() as u64 != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    String::from_utf8(buf).map_err(|_| invalid_data("invalid string"))
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        algos::{
+            atoms, budget::Budget, create_initial_cache, enumeration::aux::enum_aux,
+            meta::test::F_P_AND_G_Q,
+        },
+        ltl::trace::parse_traces,
+    };
+
+    use super::*;
+
+    #[test]
+    fn resumes_enumeration_from_saved_cache() {
+        // Also enumerate a bounded operator, which carries its interval.
+        let buf = F_P_AND_G_Q.replace("U\n", "U,F[1,2]\n");
+        let (mut traces, alphabet, mut target, operators) = parse_traces(&buf).unwrap();
+        // A trace labelled both ways, so that no formula is found and all the lines are complete.
+        traces.push(traces[0].clone());
+        target.push(!target[0]);
+        let atoms = atoms(&traces, alphabet.clone(), &operators.costs);
+        let enumerate = |cache: &mut LtlCache, max_size| {
+            let res = enum_aux(
+                cache,
+                &operators,
+                &target,
+                0,
+                max_size,
+                1,
+                &Budget::unlimited(),
+            );
+            assert_eq!(res, Ok(None));
+        };
+
+        let (_, mut full) = create_initial_cache(atoms.clone(), &target, 0);
+        enumerate(&mut full, 5);
+        let (_, mut partial) = create_initial_cache(atoms.clone(), &target, 0);
+        enumerate(&mut partial, 3);
+
+        let key = cache_key(&traces, &alphabet, &operators);
+        let mut file = vec![];
+        save(&mut file, &partial, key, 4).unwrap();
+        let mut resumed = load(&mut file.as_slice(), key, atoms.clone()).unwrap();
+        assert_eq!(resumed.lines, partial.lines);
+        enumerate(&mut resumed, 5);
+        assert_eq!(resumed.lines, full.lines);
+
+        // Atoms of the removed lines are added again by the enumeration.
+        let mut truncated = load(&mut file.as_slice(), key, atoms.clone()).unwrap();
+        truncated.truncate(1);
+        enumerate(&mut truncated, 5);
+        assert_eq!(truncated.lines, full.lines);
+
+        let other = cache_key(&traces[1..], &alphabet, &operators);
+        let res = load(&mut file.as_slice(), other, atoms.clone());
+        assert!(matches!(res, Err(CacheFileError::KeyMismatch)));
+        file[8] += 1;
+        let res = load(&mut file.as_slice(), key, atoms);
+        assert!(matches!(res, Err(CacheFileError::Version(2))));
+    }
+}
@@ -1,16 +1,52 @@
-use std::{fs::File, io::Read, path::Path};
+//! Traces and parsing of trace files.
+//!
+//! A trace file is made of four sections separated by lines containing `---`:
+//! positive traces, negative traces, the list of operators and the alphabet.
+//! Each trace is written on a single line as a `;`-separated list of steps,
+//! each step being a `,`-separated list of the values (`0` or `1`) of the predicates.
+//! A trace may end with `::` followed by a position `i`, in which case it denotes the
+//! infinite lasso looping back to step `i` after the last step: `0;1;1::1` stands for `0(11)^w`.
+//! The operators section may also be `All Operators`, standing for `X`, `F`, `G`, `&`, `|`
+//! and `U`, or `All Extended Operators`, standing for every operator without an interval.
+//! Bounded operators are listed with their interval, e.g. `F[0,3]`, `G[1,2]` or `U[0,2]`,
+//! and are never part of these sets.
+//! Past-time operators are not supported on lassos: they are rejected if listed explicitly,
+//! and left out of `All Extended Operators` when some trace is a lasso.
+//! Operators and predicates may be given a weight in the [cost model](crate::formula::cost)
+//! by writing them `name=weight`, e.g. `U=3` or `p=2`.
+use std::{fmt::Display, fs::File, io::Read, path::Path};
 
-use crate::ops::{binary::LtlBinaryOp, unary::LtlUnaryOp};
+use thiserror::Error;
 
-use super::cs::CharSeq;
+use crate::{
+    formula::cost::{split_weight, CostModel, Weight},
+    ops::{binary::LtlBinaryOp, unary::LtlUnaryOp},
+};
+
+use super::cs::{CharSeq, Semantics};
 
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct Operators {
     pub(crate) unary: Vec<LtlUnaryOp>,
     pub(crate) binary: Vec<LtlBinaryOp>,
+    /// Weights of the operators and of the predicates of the alphabet.
+    pub(crate) costs: CostModel,
 }
 
 impl Operators {
+    /// Weights of the operators and of the predicates of the alphabet.
+    pub fn costs(&self) -> &CostModel {
+        &self.costs
+    }
+
+    /// Same operators, with the given weights replacing the current ones.
+    pub fn with_weights(mut self, weights: impl IntoIterator<Item = Weight>) -> Self {
+        for w in weights {
+            self.costs.set(w);
+        }
+        self
+    }
+
     pub(crate) fn filter_bool(mut self) -> Self {
         self.unary.retain(|op| op.is_boolean());
         self.binary.retain(|op| op.is_boolean());
@@ -20,6 +56,10 @@ impl Operators {
     pub fn len(&self) -> usize {
         self.unary.len() + self.binary.len()
     }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
 }
 
 /// Stores the [`CharSeq`] of each predicate on a given trace.
@@ -28,100 +68,427 @@ pub struct Trace {
     pub alphabet: Vec<CharSeq>,
 }
 
-fn parse_trace(trace: &str) -> Option<Trace> {
-    let seq_pred: Vec<_> = trace
-        .split(';')
-        .map(|s| s.split(',').map(|v| v == "1").collect::<Vec<_>>())
-        .collect();
+impl Trace {
+    /// Whether the trace is an infinite lasso.
+    pub fn is_lasso(&self) -> bool {
+        self.alphabet
+            .first()
+            .is_some_and(|cs| cs.loop_start().is_some())
+    }
 
-    let n_pred = seq_pred.first()?.len();
-    let alphabet = (0..n_pred)
-        .map(|i| CharSeq::from_iter(seq_pred.iter().map(|v| v[i])))
-        .collect();
+    /// Same trace, with temporal operators interpreted with the given semantics.
+    pub fn with_semantics(self, semantics: Semantics) -> Self {
+        let alphabet = self
+            .alphabet
+            .into_iter()
+            .map(|cs| cs.with_semantics(semantics))
+            .collect();
+        Trace { alphabet }
+    }
+}
 
-    Some(Trace { alphabet })
+/// Sections of a trace file, in the order in which they appear.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Positives,
+    Negatives,
+    Operators,
+    Alphabet,
 }
 
-pub fn traces_from_file(
-    fname: impl AsRef<Path>,
-) -> (Vec<Trace>, Vec<String>, Vec<bool>, Operators) {
-    let mut file = File::open(fname).expect("Failed to open trace file");
+impl Section {
+    const ALL: [Section; 4] = [
+        Section::Positives,
+        Section::Negatives,
+        Section::Operators,
+        Section::Alphabet,
+    ];
+}
 
-    let mut buf = String::new();
-    file.read_to_string(&mut buf)
-        .expect("Failed to read trace file.");
+impl Display for Section {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Section::Positives => write!(f, "positive traces"),
+            Section::Negatives => write!(f, "negative traces"),
+            Section::Operators => write!(f, "operators"),
+            Section::Alphabet => write!(f, "alphabet"),
+        }
+    }
+}
 
-    parse_traces(&buf)
+/// Kind of error encountered while parsing a trace file.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+pub enum ParseErrorKind {
+    #[error("missing {0} section")]
+    MissingSection(Section),
+    #[error("empty {0} section")]
+    EmptySection(Section),
+    #[error("unexpected section after the alphabet")]
+    ExtraSection,
+    #[error("invalid value, expected '0' or '1'")]
+    InvalidValue,
+    #[error("invalid loop start, expected a step number below {length}")]
+    InvalidLoopStart { length: usize },
+    #[error("step has {found} predicates, expected {expected} as in the first step")]
+    RaggedRow { expected: usize, found: usize },
+    #[error("trace has {found} predicates, but the alphabet has {expected}")]
+    AlphabetMismatch { expected: usize, found: usize },
+    #[error("unknown operator")]
+    UnknownOperator,
+    #[error("invalid weight, expected a positive integer")]
+    InvalidWeight,
+    #[error("past-time operators are not supported on lasso traces")]
+    PastOperatorOnLasso,
+    #[error("empty predicate name")]
+    EmptyPredicateName,
 }
 
-pub(crate) fn parse_traces(buf: &str) -> (Vec<Trace>, Vec<String>, Vec<bool>, Operators) {
-    let mut traces: Vec<_> = buf
-        .split("---")
-        .take(2)
-        .map(|trs| {
-            trs.trim_matches('\n')
-                .lines()
-                .filter_map(parse_trace)
-                .collect::<Vec<_>>()
-        })
-        .collect();
+/// Error encountered while parsing a trace file, with its position.
+///
+/// Lines and columns start at 1.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("line {line}, column {column}: {kind} (found '{text}')")]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    /// Offending text.
+    pub text: String,
+    pub kind: ParseErrorKind,
+}
 
-    let op_desc = buf
-        .split("---")
-        .nth(2)
-        .expect("No operators list.")
-        .trim_matches('\n');
+impl ParseError {
+    fn new(line: usize, column: usize, text: &str, kind: ParseErrorKind) -> Self {
+        Self {
+            line,
+            column,
+            text: text.to_owned(),
+            kind,
+        }
+    }
+}
 
-    let operators = if op_desc == "All Operators" {
-        Operators {
-            unary: LtlUnaryOp::all(),
-            binary: LtlBinaryOp::all(),
+/// Error returned when reading a trace file.
+#[derive(Debug, Error)]
+pub enum TraceFileError {
+    #[error("failed to read trace file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error(transparent)]
+    Parse(#[from] ParseError),
+}
+
+/// Content of a trace file: the traces, the alphabet, the target
+/// (whether each trace is positive) and the operators.
+pub type ParsedTraces = (Vec<Trace>, Vec<String>, Vec<bool>, Operators);
+
+/// Column (starting at 1) of the byte offset `offset` in `line`.
+fn column(line: &str, offset: usize) -> usize {
+    line[..offset].chars().count() + 1
+}
+
+/// Split `s` on `sep`, yielding each piece with its byte offset in `s`.
+fn split_with_offsets(s: &str, sep: char) -> impl Iterator<Item = (usize, &str)> {
+    s.split(sep).scan(0, move |offset, piece| {
+        let start = *offset;
+        *offset += piece.len() + sep.len_utf8();
+        Some((start, piece))
+    })
+}
+
+/// Split `line` on the commas that are not inside brackets, such as the one in `F[1,3]`,
+/// yielding each token with its byte offset in `line`.
+fn split_tokens(line: &str) -> Vec<(usize, &str)> {
+    let mut tokens = vec![];
+    let mut depth = 0usize;
+    let mut start = 0;
+    for (i, c) in line.char_indices() {
+        match c {
+            '[' => depth += 1,
+            ']' => depth = depth.saturating_sub(1),
+            ',' if depth == 0 => {
+                tokens.push((start, &line[start..i]));
+                start = i + 1;
+            }
+            _ => {}
         }
-    } else {
-        let unary = op_desc
-            .split(',')
-            .filter_map(|s| LtlUnaryOp::try_from(s).ok())
-            .collect::<Vec<_>>();
-        let binary = op_desc
-            .split(',')
-            .filter_map(|s| LtlBinaryOp::try_from(s).ok())
-            .collect::<Vec<_>>();
-        Operators { unary, binary }
+    }
+    tokens.push((start, &line[start..]));
+    tokens
+}
+
+/// Parse a single trace, written on line number `line_nb`.
+fn parse_trace(line: &str, line_nb: usize) -> Result<Trace, ParseError> {
+    let (body, loop_start) = match line.split_once("::") {
+        Some((body, start)) => (body, Some(start)),
+        None => (line, None),
     };
 
-    let alphabet = buf
-        .split("---")
-        .skip(3)
-        .take(1)
-        .map(|trs| {
-            trs.trim_matches('\n')
-                .split(',')
-                .map(str::to_owned)
-                .collect::<Vec<_>>()
+    let mut steps: Vec<Vec<bool>> = vec![];
+    for (step_offset, step) in split_with_offsets(body, ';') {
+        let values = split_with_offsets(step, ',')
+            .map(|(offset, v)| match v.trim() {
+                "1" => Ok(true),
+                "0" => Ok(false),
+                _ => Err(ParseError::new(
+                    line_nb,
+                    column(line, step_offset + offset),
+                    v,
+                    ParseErrorKind::InvalidValue,
+                )),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if let Some(first) = steps.first() {
+            if first.len() != values.len() {
+                return Err(ParseError::new(
+                    line_nb,
+                    column(line, step_offset),
+                    step,
+                    ParseErrorKind::RaggedRow {
+                        expected: first.len(),
+                        found: values.len(),
+                    },
+                ));
+            }
+        }
+        steps.push(values);
+    }
+
+    let loop_start = loop_start
+        .map(|start| match start.trim().parse() {
+            Ok(i) if i < steps.len() => Ok(i),
+            _ => Err(ParseError::new(
+                line_nb,
+                column(line, body.len() + 2),
+                start,
+                ParseErrorKind::InvalidLoopStart {
+                    length: steps.len(),
+                },
+            )),
         })
-        .next()
-        .expect("No alphabet definition.");
+        .transpose()?;
+
+    let n_pred = steps[0].len();
+    let alphabet = (0..n_pred)
+        .map(|i| {
+            let cs = CharSeq::from_iter(steps.iter().map(|v| v[i]));
+            match loop_start {
+                Some(start) => cs.with_loop(start),
+                None => cs,
+            }
+        })
+        .collect();
+
+    Ok(Trace { alphabet })
+}
+
+/// A line of a trace file, with its line number.
+type Line<'a> = (usize, &'a str);
+/// Non-blank lines of a section.
+type SectionLines<'a> = Vec<Line<'a>>;
+
+/// Split the content of a trace file into its sections.
+///
+/// Blank lines are ignored.
+/// Each section is returned with the line that ends it: the next separator,
+/// or the line after the end of the file for the last section.
+fn split_sections(buf: &str) -> Result<Vec<(SectionLines<'_>, Line<'_>)>, ParseError> {
+    let mut sections = vec![];
+    let mut current = vec![];
+    let mut nb_lines = 0;
+    for (i, line) in buf.lines().enumerate() {
+        let line_nb = i + 1;
+        let line = line.trim_end_matches('\r');
+        nb_lines = line_nb;
+        if line.trim() == "---" {
+            if sections.len() + 1 == Section::ALL.len() {
+                return Err(ParseError::new(
+                    line_nb,
+                    1,
+                    line,
+                    ParseErrorKind::ExtraSection,
+                ));
+            }
+            sections.push((std::mem::take(&mut current), (line_nb, line)));
+        } else if !line.trim().is_empty() {
+            current.push((line_nb, line));
+        }
+    }
+    sections.push((current, (nb_lines + 1, "")));
+
+    if let Some(&missing) = Section::ALL.get(sections.len()) {
+        return Err(ParseError::new(
+            nb_lines + 1,
+            1,
+            "",
+            ParseErrorKind::MissingSection(missing),
+        ));
+    }
+
+    Ok(sections)
+}
+
+/// Parse the traces of a section, keeping the lines they come from.
+fn parse_trace_section<'a>(lines: &[Line<'a>]) -> Result<Vec<(usize, &'a str, Trace)>, ParseError> {
+    lines
+        .iter()
+        .map(|&(line_nb, line)| Ok((line_nb, line, parse_trace(line, line_nb)?)))
+        .collect()
+}
+
+/// Parse the `,`-separated tokens of a section.
+fn parse_tokens<'a, T>(
+    lines: &[Line<'a>],
+    mut parse: impl FnMut(&'a str) -> Result<T, ParseErrorKind>,
+) -> Result<Vec<T>, ParseError> {
+    let mut res = vec![];
+    for &(line_nb, line) in lines {
+        for (offset, token) in split_tokens(line) {
+            let value = parse(token.trim())
+                .map_err(|kind| ParseError::new(line_nb, column(line, offset), token, kind))?;
+            res.push(value);
+        }
+    }
+    Ok(res)
+}
+
+/// Parse the operators section, rejecting past-time operators if `lassos` is set.
+fn parse_operators(lines: &[Line], lassos: bool) -> Result<Operators, ParseError> {
+    if let [(_, line)] = lines {
+        let keyword = match line.trim() {
+            "All Operators" => Some((LtlUnaryOp::all(), LtlBinaryOp::all())),
+            "All Extended Operators" => Some((LtlUnaryOp::extended(), LtlBinaryOp::extended())),
+            _ => None,
+        };
+        if let Some((mut unary, mut binary)) = keyword {
+            if lassos {
+                unary.retain(|op| !op.is_past());
+                binary.retain(|op| !op.is_past());
+            }
+            return Ok(Operators {
+                unary,
+                binary,
+                costs: CostModel::default(),
+            });
+        }
+    }
+
+    let mut unary = vec![];
+    let mut binary = vec![];
+    let mut costs = CostModel::default();
+    parse_tokens(lines, |token| {
+        let (token, weight) = split_weight(token).map_err(|_| ParseErrorKind::InvalidWeight)?;
+        if let Ok(op) = LtlUnaryOp::try_from(token) {
+            if lassos && op.is_past() {
+                return Err(ParseErrorKind::PastOperatorOnLasso);
+            }
+            unary.push(op);
+            if let Some(w) = weight {
+                costs.set(Weight::Unary(op, w));
+            }
+        } else if let Ok(op) = LtlBinaryOp::try_from(token) {
+            if lassos && op.is_past() {
+                return Err(ParseErrorKind::PastOperatorOnLasso);
+            }
+            binary.push(op);
+            if let Some(w) = weight {
+                costs.set(Weight::Binary(op, w));
+            }
+        } else {
+            return Err(ParseErrorKind::UnknownOperator);
+        }
+        Ok(())
+    })?;
+
+    Ok(Operators {
+        unary,
+        binary,
+        costs,
+    })
+}
+
+/// Parse the alphabet section, adding the weights of the predicates to `costs`.
+fn parse_alphabet(lines: &[Line], costs: &mut CostModel) -> Result<Vec<String>, ParseError> {
+    parse_tokens(lines, |token| {
+        let (token, weight) = split_weight(token).map_err(|_| ParseErrorKind::InvalidWeight)?;
+        if token.is_empty() {
+            return Err(ParseErrorKind::EmptyPredicateName);
+        }
+        if let Some(w) = weight {
+            costs.set(Weight::Atom(token.to_owned(), w));
+        }
+        Ok(token.to_owned())
+    })
+}
+
+/// Read and parse a trace file, see the [module documentation](self) for the format.
+pub fn traces_from_file(fname: impl AsRef<Path>) -> Result<ParsedTraces, TraceFileError> {
+    let mut file = File::open(fname)?;
+
+    let mut buf = String::new();
+    file.read_to_string(&mut buf)?;
+
+    Ok(parse_traces(&buf)?)
+}
+
+/// Parse the content of a trace file.
+pub(crate) fn parse_traces(buf: &str) -> Result<ParsedTraces, ParseError> {
+    let sections = split_sections(buf)?;
+
+    for ((lines, (end_nb, end)), &section) in sections.iter().zip(Section::ALL.iter()) {
+        if lines.is_empty() {
+            return Err(ParseError::new(
+                *end_nb,
+                1,
+                end,
+                ParseErrorKind::EmptySection(section),
+            ));
+        }
+    }
 
-    let target = traces[0]
+    let positives = parse_trace_section(&sections[0].0)?;
+    let negatives = parse_trace_section(&sections[1].0)?;
+    let lassos = positives
+        .iter()
+        .chain(&negatives)
+        .any(|(_, _, trace)| trace.is_lasso());
+    let mut operators = parse_operators(&sections[2].0, lassos)?;
+    let alphabet = parse_alphabet(&sections[3].0, &mut operators.costs)?;
+
+    let target = positives
         .iter()
         .map(|_| true)
-        .chain(traces[1].iter().map(|_| false))
+        .chain(negatives.iter().map(|_| false))
         .collect();
 
-    let neg = traces.pop().expect("Negative instances");
-    let mut traces = traces.pop().expect("Positive instances");
+    let traces = positives
+        .into_iter()
+        .chain(negatives)
+        .map(|(line_nb, line, trace)| {
+            if trace.alphabet.len() == alphabet.len() {
+                Ok(trace)
+            } else {
+                Err(ParseError::new(
+                    line_nb,
+                    1,
+                    line,
+                    ParseErrorKind::AlphabetMismatch {
+                        expected: alphabet.len(),
+                        found: trace.alphabet.len(),
+                    },
+                ))
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
 
-    traces.extend(neg);
-    (traces, alphabet, target, operators)
+    Ok((traces, alphabet, target, operators))
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::ops::interval::Interval;
 
-    #[test]
-    fn parsing() {
-        let buf = "0,0;0,1;0,0;0,1;0,0
+    const BUF: &str = "0,0;0,1;0,0;0,1;0,0
 0,1;0,0;0,1;0,1;0,0
 0,1;0,0;0,1;0,0;0,1
 0,1;0,1;0,0;0,0;0,1
@@ -146,6 +513,195 @@ mod test {
 F,G,X,!,&,|
 ---
 p,q";
-        let _res = parse_traces(buf);
+
+    fn parse_err(buf: &str) -> ParseError {
+        parse_traces(buf).expect_err("Parsing should fail")
+    }
+
+    #[test]
+    fn parsing() {
+        let (.., operators) = parse_traces(BUF).unwrap();
+        assert_eq!(
+            operators.unary,
+            vec![
+                LtlUnaryOp::Finally,
+                LtlUnaryOp::Globally,
+                LtlUnaryOp::Next,
+                LtlUnaryOp::Not
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_operator() {
+        let err = parse_err(&BUF.replace("!", "~"));
+        assert_eq!(err.kind, ParseErrorKind::UnknownOperator);
+        assert_eq!((err.line, err.column), (23, 7));
+        assert_eq!(err.text, "~");
+    }
+
+    #[test]
+    fn parsing_valid() {
+        let buf = BUF.replace("F,G,X,!,&,|", "F,G,X,&,|");
+        let (traces, alphabet, target, operators) = parse_traces(&buf).unwrap();
+        assert_eq!(traces.len(), 20);
+        assert!(traces
+            .iter()
+            .all(|t| t.alphabet.len() == 2 && t.alphabet[0].len() == 5));
+        assert_eq!(alphabet, vec!["p", "q"]);
+        assert_eq!(target, [[true; 10], [false; 10]].concat());
+        assert_eq!(
+            operators,
+            Operators {
+                unary: vec![LtlUnaryOp::Finally, LtlUnaryOp::Globally, LtlUnaryOp::Next],
+                binary: vec![LtlBinaryOp::And, LtlBinaryOp::Or],
+                costs: CostModel::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn parsing_all_operators_and_blank_lines() {
+        let buf = "1;0\n\n---\n0;0\n---\nAll Operators\n---\np\n\n";
+        let (traces, _, _, operators) = parse_traces(buf).unwrap();
+        assert_eq!(traces.len(), 2);
+        assert_eq!(
+            operators,
+            Operators {
+                unary: vec![LtlUnaryOp::Next, LtlUnaryOp::Finally, LtlUnaryOp::Globally],
+                binary: vec![LtlBinaryOp::Or, LtlBinaryOp::And, LtlBinaryOp::Until],
+                costs: CostModel::default(),
+            }
+        );
+
+        let buf = "1;0\n---\n0;0\n---\nAll Extended Operators\n---\np";
+        let (.., operators) = parse_traces(buf).unwrap();
+        assert_eq!(operators.unary, LtlUnaryOp::extended());
+        assert_eq!(operators.binary, LtlBinaryOp::extended());
+    }
+
+    #[test]
+    fn parsing_long_trace() {
+        let trace = vec!["1"; 150].join(";");
+        let buf = format!("{trace}\n---\n0\n---\nF\n---\np");
+        let (traces, ..) = parse_traces(&buf).unwrap();
+        assert_eq!(traces[0].alphabet[0].len(), 150);
+    }
+
+    #[test]
+    fn parsing_bounded_operators() {
+        let buf = "1;0\n---\n0;0\n---\nF[0,3], X, U[1,2]\n---\np";
+        let (.., operators) = parse_traces(buf).unwrap();
+        assert_eq!(
+            operators,
+            Operators {
+                unary: vec![
+                    LtlUnaryOp::BoundedFinally(Interval::new(0, 3).unwrap()),
+                    LtlUnaryOp::Next
+                ],
+                binary: vec![LtlBinaryOp::BoundedUntil(Interval::new(1, 2).unwrap())],
+                costs: CostModel::default(),
+            }
+        );
+    }
+
+    #[test]
+    fn parsing_weights() {
+        let buf = "1,0;0,1\n---\n0,0;0,0\n---\nF, U=3\n---\np=2, q";
+        let (_, alphabet, _, operators) = parse_traces(buf).unwrap();
+        assert_eq!(alphabet, vec!["p", "q"]);
+        assert_eq!(operators.unary, vec![LtlUnaryOp::Finally]);
+        assert_eq!(operators.costs.binary(LtlBinaryOp::Until), 3);
+        assert_eq!(operators.costs.unary(LtlUnaryOp::Finally), 1);
+        assert_eq!(operators.costs.atom("p"), 2);
+        assert_eq!(operators.costs.atom("q"), 1);
+
+        let err = parse_err("1;0\n---\n0;0\n---\nF, U=0\n---\np");
+        assert_eq!(err.kind, ParseErrorKind::InvalidWeight);
+        assert_eq!((err.line, err.column, err.text.as_str()), (5, 3, " U=0"));
+    }
+
+    #[test]
+    fn parsing_lassos() {
+        let buf = "1;0;1::1\n---\n0;1\n---\nF\n---\np";
+        let (traces, ..) = parse_traces(buf).unwrap();
+        assert_eq!(traces[0].alphabet[0].loop_start(), Some(1));
+        assert_eq!(traces[1].alphabet[0].loop_start(), None);
+    }
+
+    #[test]
+    fn past_operators_on_lassos() {
+        let err = parse_err("1;0::1\n---\n0\n---\nF,Y\n---\np");
+        assert_eq!(err.kind, ParseErrorKind::PastOperatorOnLasso);
+        assert_eq!((err.line, err.column, err.text.as_str()), (5, 3, "Y"));
+
+        let buf = "1;0::1\n---\n0\n---\nAll Extended Operators\n---\np";
+        let (.., operators) = parse_traces(buf).unwrap();
+        assert!(operators.unary.iter().all(|op| !op.is_past()));
+        assert!(operators.binary.iter().all(|op| !op.is_past()));
+    }
+
+    #[test]
+    fn invalid_loop_start() {
+        let err = parse_err("1;0::2\n---\n0\n---\nF\n---\np");
+        assert_eq!(err.kind, ParseErrorKind::InvalidLoopStart { length: 2 });
+        assert_eq!((err.line, err.column, err.text.as_str()), (1, 6, "2"));
+
+        let err = parse_err("1;0::x\n---\n0\n---\nF\n---\np");
+        assert_eq!(err.kind, ParseErrorKind::InvalidLoopStart { length: 2 });
+    }
+
+    #[test]
+    fn invalid_value() {
+        let err = parse_err("1,0;1,x\n---\n0,0\n---\nF\n---\np,q");
+        assert_eq!(err.kind, ParseErrorKind::InvalidValue);
+        assert_eq!((err.line, err.column, err.text.as_str()), (1, 7, "x"));
+    }
+
+    #[test]
+    fn ragged_row() {
+        let err = parse_err("1,0;1,1\n---\n0,0;1;0,1\n---\nF\n---\np,q");
+        assert_eq!(
+            err.kind,
+            ParseErrorKind::RaggedRow {
+                expected: 2,
+                found: 1
+            }
+        );
+        assert_eq!((err.line, err.column, err.text.as_str()), (3, 5, "1"));
+    }
+
+    #[test]
+    fn alphabet_mismatch() {
+        let err = parse_err("1,0;1,1\n---\n0;1\n---\nF\n---\np,q");
+        assert_eq!(
+            err.kind,
+            ParseErrorKind::AlphabetMismatch {
+                expected: 2,
+                found: 1
+            }
+        );
+        assert_eq!((err.line, err.column), (3, 1));
+    }
+
+    #[test]
+    fn missing_section() {
+        let err = parse_err("1\n---\n0\n---\nF");
+        assert_eq!(err.kind, ParseErrorKind::MissingSection(Section::Alphabet));
+        assert_eq!(err.line, 6);
+    }
+
+    #[test]
+    fn empty_section() {
+        let err = parse_err("1\n---\n\n---\nF\n---\np");
+        assert_eq!(err.kind, ParseErrorKind::EmptySection(Section::Negatives));
+        assert_eq!((err.line, err.text.as_str()), (4, "---"));
+    }
+
+    #[test]
+    fn extra_section() {
+        let err = parse_err("1\n---\n0\n---\nF\n---\np\n---\n");
+        assert_eq!(err.kind, ParseErrorKind::ExtraSection);
+        assert_eq!(err.line, 8);
     }
 }
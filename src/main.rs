@@ -1,15 +1,35 @@
-use std::path::PathBuf;
+use std::{path::PathBuf, process};
 
-use clap::{Parser, Subcommand};
+use clap::{
+    builder::RangedU64ValueParser, error::ErrorKind, Args, CommandFactory, Parser, Subcommand,
+};
 
+use itertools::Itertools;
 use log::info;
 use ltl_rs::{
     algos::{
-        beam_search::BeamSearchParams, enumeration::EnumParams, meta::divide_conquer,
-        set_cover::SetCoverParams, BoolAlgoParams,
+        beam_search::BeamSearchParams,
+        budget::{Budget, BudgetParams},
+        decision_tree::DecisionTreeParams,
+        enumeration::EnumParams,
+        meta::{anytime::anytime, divide_conquer, MetaParams},
+        minimal::all_minimal,
+        pareto::pareto_front,
+        portfolio::{PortfolioData, PortfolioParams},
+        sat::{exact, ExactParams, SatParams},
+        set_cover::SetCoverParams,
+        BoolAlgoParams,
+    },
+    cache::collisions,
+    formula::{
+        cost::Weight,
+        parser::parse_formula,
+        tree::{Classification, FormulaTree},
+    },
+    ltl::{
+        cs::Semantics,
+        trace::{traces_from_file, ParsedTraces, Trace},
     },
-    formula::tree::FormulaTree,
-    ltl::trace::traces_from_file,
 };
 
 fn main() {
@@ -17,58 +37,454 @@ fn main() {
 
     let CliArgs {
         input_filename,
-        max_size_ltl,
-        domin_nb,
+        semantics,
+        costs,
+        report_collisions,
+        anytime,
+        budget,
+        meta_params,
         command,
     } = CliArgs::parse();
+    let budget = budget.start();
+
+    if report_collisions {
+        collisions::enable_report();
+    }
+
+    if anytime && !command.learns() {
+        CliArgs::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "`--anytime` only applies to learning, not to the eval, pareto, minimal \
+                and exact subcommands",
+            )
+            .exit()
+    }
 
-    let sol = match command {
-        AlgoCommand::Enum(p) => get_name_time_sol(&input_filename, max_size_ltl, domin_nb, p),
-        AlgoCommand::SetCover(p) => get_name_time_sol(&input_filename, max_size_ltl, domin_nb, p),
-        AlgoCommand::BeamSearch(p) => get_name_time_sol(&input_filename, max_size_ltl, domin_nb, p),
+    let (input_filename, meta_params, command) = match (command, input_filename, meta_params) {
+        (AlgoCommand::Eval(args), ..) => return eval(args, semantics, &costs),
+        (AlgoCommand::Pareto(args), ..) => return pareto(args, semantics, &costs, &budget),
+        (AlgoCommand::Minimal(args), ..) => return minimal(args, semantics, &costs, &budget),
+        (AlgoCommand::Exact(args), ..) => return exact_min(args, semantics, &costs, &budget),
+        (command, Some(input_filename), Some(meta_params)) => {
+            (input_filename, meta_params, command)
+        }
+        _ => CliArgs::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "learning requires an input file, `max_size_ltl` and `domin_nb`",
+            )
+            .exit(),
     };
 
-    println!("{}", sol.map_or(String::new(), |f| format!("{f}")))
+    let args = LearnArgs {
+        fname: &input_filename,
+        semantics,
+        costs: &costs,
+        meta_params: &meta_params,
+        budget: &budget,
+    };
+    let sol = match (command, anytime) {
+        (AlgoCommand::Enum(p), false) => get_name_time_sol(args, p).0,
+        (AlgoCommand::SetCover(p), false) => get_name_time_sol(args, p).0,
+        (AlgoCommand::BeamSearch(p), false) => get_name_time_sol(args, p).0,
+        (AlgoCommand::Portfolio(p), false) => {
+            let (sol, data) = get_name_time_sol(args, p);
+            print_portfolio(&data);
+            sol
+        }
+        (AlgoCommand::Sat(p), false) => get_name_time_sol(args, p).0,
+        (AlgoCommand::DecisionTree(p), false) => get_name_time_sol(args, p).0,
+        (AlgoCommand::Enum(p), true) => print_anytime_sols(args, p),
+        (AlgoCommand::SetCover(p), true) => print_anytime_sols(args, p),
+        (AlgoCommand::BeamSearch(p), true) => print_anytime_sols(args, p),
+        (AlgoCommand::Portfolio(p), true) => print_anytime_sols(args, p),
+        (AlgoCommand::Sat(p), true) => print_anytime_sols(args, p),
+        (AlgoCommand::DecisionTree(p), true) => print_anytime_sols(args, p),
+        (
+            AlgoCommand::Eval(_)
+            | AlgoCommand::Pareto(_)
+            | AlgoCommand::Minimal(_)
+            | AlgoCommand::Exact(_),
+            _,
+        ) => unreachable!(),
+    };
+
+    if report_collisions {
+        for (kind, count) in collisions::collision_counts() {
+            if count > 0 {
+                eprintln!("{kind}: {count} hash collisions");
+            }
+        }
+    }
+
+    // Solutions of the anytime search are printed as they are found.
+    if !anytime {
+        println!("{}", sol.map_or(String::new(), |f| format!("{f}")))
+    }
+}
+
+/// Parameters of a learning run from the command line.
+struct LearnArgs<'a> {
+    fname: &'a PathBuf,
+    semantics: Semantics,
+    costs: &'a [Weight],
+    meta_params: &'a MetaParams,
+    budget: &'a Budget,
 }
 
+/// Read a trace file, interpreting its traces with the given semantics,
+/// and overriding the weights of the file with `costs`.
+fn read_traces(fname: &PathBuf, semantics: Semantics, costs: &[Weight]) -> ParsedTraces {
+    let (traces, alphabet, target, operators) = traces_from_file(fname).unwrap_or_else(|e| {
+        eprintln!("{}: {e}", fname.display());
+        process::exit(1)
+    });
+    for w in costs {
+        if let Weight::Atom(name, _) = w {
+            if !alphabet.contains(name) {
+                eprintln!("Invalid weight: unknown operator or predicate '{name}'");
+                process::exit(1)
+            }
+        }
+    }
+    let traces = traces
+        .into_iter()
+        .map(|t| t.with_semantics(semantics))
+        .collect();
+    let operators = operators.with_weights(costs.iter().cloned());
+    (traces, alphabet, target, operators)
+}
+
+/// Learn a formula, returned with the data of each run of the Boolean algorithm.
 fn get_name_time_sol<P: BoolAlgoParams + Clone>(
-    fname: &PathBuf,
-    max_size_ltl: usize,
-    domin_nb: usize,
+    args: LearnArgs,
     params: P,
-) -> Option<FormulaTree> {
-    let (traces, alphabet, target, operators) = traces_from_file(fname);
+) -> (Option<FormulaTree>, Vec<P::Data>) {
+    let LearnArgs {
+        fname,
+        semantics,
+        costs,
+        meta_params,
+        budget,
+    } = args;
+    let (traces, alphabet, target, operators) = read_traces(fname, semantics, costs);
 
     let res = divide_conquer(
         &traces,
         alphabet,
         operators,
         target.clone(),
-        max_size_ltl,
-        domin_nb,
+        meta_params,
         params,
+        budget,
     );
 
+    if let Some(e) = res.exhausted() {
+        eprintln!("Stopped early: {e}");
+        if let Some(f) = res.partial() {
+            eprintln!("Most accurate formula found: {f}");
+        }
+    }
+
+    let max_errors = meta_params.allowed_errors(target.len());
     if let Some(t) = res.sol() {
-        let actual_value = t.eval(&traces).accepted_vec();
-        assert_eq!(actual_value, target);
+        let classification = t.classify(&traces, &target);
+        assert!(classification.errors() <= max_errors);
         info!("Correctness check OK!");
+        if classification.errors() > 0 {
+            print_sacrificed(&classification);
+        }
+    }
+    if let Some(cost) = res.cost() {
+        info!("Solution has cost {cost}");
+    }
+
+    (res.sol(), res.algo_data)
+}
+
+/// Run the anytime search, printing each improving solution with its size, cost
+/// and the time at which it was found.
+fn print_anytime_sols<P: BoolAlgoParams + Clone>(
+    args: LearnArgs,
+    params: P,
+) -> Option<FormulaTree> {
+    let LearnArgs {
+        fname,
+        semantics,
+        costs,
+        meta_params,
+        budget,
+    } = args;
+    let (traces, alphabet, target, operators) = read_traces(fname, semantics, costs);
+    let max_errors = meta_params.allowed_errors(target.len());
+
+    let res = anytime(
+        &traces,
+        alphabet,
+        operators,
+        target.clone(),
+        meta_params,
+        params,
+        budget,
+        |i| {
+            assert!(i.formula.classify(&traces, &target).errors() <= max_errors);
+            println!(
+                "[{:.3}s] size {}, cost {}: {}",
+                i.elapsed.as_secs_f64(),
+                i.size,
+                i.cost,
+                i.formula
+            );
+        },
+    );
+
+    if let Some(e) = res.exhausted {
+        eprintln!("Stopped early: {e}");
+    }
+
+    if let Some(i) = &res.best {
+        let classification = i.formula.classify(&traces, &target);
+        if classification.errors() > 0 {
+            print_sacrificed(&classification);
+        }
+    }
+
+    res.best.map(|i| i.formula)
+}
+
+/// Print the running time of each member of a portfolio, and which one found a formula,
+/// for each run of the portfolio by divide and conquer.
+fn print_portfolio(runs: &[PortfolioData]) {
+    for (k, data) in runs.iter().enumerate() {
+        for (i, (name, time)) in data.times.iter().enumerate() {
+            eprintln!(
+                "Portfolio run {}, member {name}: {:.3}s{}",
+                k + 1,
+                time.as_secs_f64(),
+                if data.winner == Some(i) {
+                    " (winner)"
+                } else {
+                    ""
+                }
+            );
+        }
+    }
+}
+
+/// Print the traces misclassified by a solution learnt with `--max-errors` or `--max-error-rate`.
+fn print_sacrificed(res: &Classification) {
+    eprintln!(
+        "Sacrificed positive traces: [{}]",
+        res.misclassified_positives.iter().join(", ")
+    );
+    eprintln!(
+        "Sacrificed negative traces: [{}]",
+        res.misclassified_negatives.iter().join(", ")
+    );
+}
+
+/// Evaluate a formula on a trace file and print the results.
+fn eval(args: EvalArgs, semantics: Semantics, costs: &[Weight]) {
+    let (traces, alphabet, target, operators) = read_traces(&args.input_filename, semantics, costs);
+    let f = parse_formula(&args.formula, &alphabet).unwrap_or_else(|e| {
+        eprintln!("Invalid formula: {e}");
+        process::exit(1)
+    });
+    if f.has_past() && traces.iter().any(Trace::is_lasso) {
+        eprintln!("Invalid formula: past-time operators are not supported on lasso traces");
+        process::exit(1)
+    }
+
+    let res = f.classify(&traces, &target);
+    let cost = f.cost(operators.costs());
+    if args.json {
+        print_json(&f, cost, &res);
+    } else {
+        print_human(&f, cost, &res);
+    }
+
+    if args.strict && res.errors() > 0 {
+        process::exit(2);
+    }
+}
+
+/// Compute the Pareto front of the cost of formulas versus their errors on a trace file,
+/// and print it as a table.
+fn pareto(args: ParetoArgs, semantics: Semantics, costs: &[Weight], budget: &Budget) {
+    let (traces, alphabet, target, operators) = read_traces(&args.input_filename, semantics, costs);
+    let front = pareto_front(
+        &traces,
+        alphabet,
+        operators,
+        target.clone(),
+        args.max_size_ltl,
+        args.threads,
+        budget,
+    );
+    if let Some(e) = front.exhausted {
+        eprintln!("Stopped early: {e}");
     }
 
-    res.sol()
+    println!(
+        "{:>6} {:>6} {:>6} {:>8}  formula",
+        "cost", "size", "errors", "accuracy"
+    );
+    for p in front.points {
+        let accuracy = p.formula.classify(&traces, &target).accuracy();
+        println!(
+            "{:>6} {:>6} {:>6} {:>8.4}  {}",
+            p.cost,
+            p.formula.size(),
+            p.errors,
+            accuracy,
+            p.formula
+        );
+    }
+}
+
+/// Print all the separating formulas of minimal cost of a trace file, one per line,
+/// and whether the minimal formula is unique to stderr.
+fn minimal(args: MinimalArgs, semantics: Semantics, costs: &[Weight], budget: &Budget) {
+    let (traces, alphabet, target, operators) = read_traces(&args.input_filename, semantics, costs);
+    let res = all_minimal(
+        &traces,
+        alphabet,
+        operators,
+        target,
+        args.max_size_ltl,
+        args.limit,
+        args.threads,
+        budget,
+    );
+    if let Some(e) = res.exhausted {
+        eprintln!("Stopped early: {e}");
+    }
+
+    for f in &res.formulas {
+        println!("{f}");
+    }
+    let Some(cost) = res.cost else {
+        eprintln!("No formula of cost at most {}", args.max_size_ltl);
+        return;
+    };
+    let uniqueness = match res.is_unique() {
+        Some(true) => "unique",
+        Some(false) => "not unique",
+        None => "maybe not unique",
+    };
+    let nb = res.formulas.len();
+    eprintln!(
+        "{}{nb} minimal formula{} of cost {cost}: the minimal formula is {uniqueness}",
+        if res.complete { "" } else { "Stopped after " },
+        if nb > 1 { "s" } else { "" },
+    );
+}
+
+/// Print a formula of minimal cost of a trace file, found by exact learning.
+fn exact_min(args: ExactArgs, semantics: Semantics, costs: &[Weight], budget: &Budget) {
+    let (traces, alphabet, target, operators) = read_traces(&args.input_filename, semantics, costs);
+    let res =
+        exact(&traces, alphabet, operators, &target, args.params, budget).unwrap_or_else(|e| {
+            eprintln!("{}: {e}", args.input_filename.display());
+            process::exit(1)
+        });
+    if let Some(e) = res.exhausted {
+        eprintln!("Stopped early: {e}");
+    }
+
+    let (Some(f), Some(cost)) = (res.formula, res.cost) else {
+        eprintln!("No formula of cost at most {}", args.params.max_size);
+        return;
+    };
+    let classification = f.classify(&traces, &target);
+    assert!(classification.errors() <= args.params.max_errors);
+    if classification.errors() > 0 {
+        print_sacrificed(&classification);
+    }
+    eprintln!(
+        "{} of cost {cost}",
+        if res.exhausted.is_none() {
+            "Minimal formula"
+        } else {
+            "Formula"
+        }
+    );
+    println!("{f}");
+}
+
+fn print_human(f: &FormulaTree, cost: usize, res: &Classification) {
+    let total = res.errors() + res.true_positives + res.true_negatives;
+    println!("Formula: {f}");
+    println!("Cost: {cost}");
+    println!(
+        "Accuracy: {:.4} ({}/{total})",
+        res.accuracy(),
+        total - res.errors()
+    );
+    println!("{:>10} {:>10} {:>10}", "", "accepted", "rejected");
+    println!(
+        "{:>10} {:>10} {:>10}",
+        "positive", res.true_positives, res.false_negatives
+    );
+    println!(
+        "{:>10} {:>10} {:>10}",
+        "negative", res.false_positives, res.true_negatives
+    );
+    println!(
+        "Misclassified positive traces: [{}]",
+        res.misclassified_positives.iter().join(", ")
+    );
+    println!(
+        "Misclassified negative traces: [{}]",
+        res.misclassified_negatives.iter().join(", ")
+    );
+}
+
+fn print_json(f: &FormulaTree, cost: usize, res: &Classification) {
+    let formula = format!("{f}").replace('\\', "\\\\").replace('"', "\\\"");
+    println!(
+        "{{\"formula\": \"{formula}\", \"cost\": {cost}, \"accuracy\": {}, \
+        \"true_positives\": {}, \"false_negatives\": {}, \
+        \"true_negatives\": {}, \"false_positives\": {}, \
+        \"misclassified_positives\": [{}], \"misclassified_negatives\": [{}]}}",
+        res.accuracy(),
+        res.true_positives,
+        res.false_negatives,
+        res.true_negatives,
+        res.false_positives,
+        res.misclassified_positives.iter().join(", "),
+        res.misclassified_negatives.iter().join(", "),
+    );
 }
 
 #[derive(Parser)]
-// #[command(version, about, long_about = None)]
+#[command(
+    about = "Learn LTL formulas separating positive from negative traces",
+    long_about = None,
+    subcommand_negates_reqs = true
+)]
 struct CliArgs {
     /// Name of the .trace file to read.
-    input_filename: PathBuf,
-    /// Run LTL enumeration until `max_size_ltl`
-    /// before switching to boolean algorithm.
-    max_size_ltl: usize,
-    /// Number of candidates to use for domination checking
-    /// in the step that converts LTL formulas to boolean formulas.
-    domin_nb: usize,
+    #[arg(required = true)]
+    input_filename: Option<PathBuf>,
+    /// Interpretation of temporal operators at the end of finite traces.
+    #[arg(long, value_enum, default_value_t, global = true)]
+    semantics: Semantics,
+    /// Weight of an operator or predicate in the cost of formulas, e.g. `U=3` or `p=2`,
+    /// taking precedence over the weights of the trace file.
+    #[arg(long = "cost", value_name = "NAME=WEIGHT", global = true)]
+    costs: Vec<Weight>,
+    /// Print the number of hash collisions met by each cache to stderr.
+    #[arg(long, global = true)]
+    report_collisions: bool,
+    /// Print better and better solutions as they are found, instead of a single one at the end.
+    #[arg(long, global = true)]
+    anytime: bool,
+    #[command(flatten)]
+    budget: BudgetParams,
+    #[command(flatten)]
+    meta_params: Option<MetaParams>,
     #[command(subcommand)]
     command: AlgoCommand,
 }
@@ -81,4 +497,79 @@ enum AlgoCommand {
     SetCover(SetCoverParams),
     /// Bottom-up beam search
     BeamSearch(BeamSearchParams),
+    /// Run several of the above algorithms and keep the best result
+    Portfolio(PortfolioParams),
+    /// Exact synthesis of minimal Boolean combinations with a SAT solver
+    Sat(SatParams),
+    /// Decision tree over the LTL formulas, compiled into a Boolean combination
+    DecisionTree(DecisionTreeParams),
+    /// Evaluate a formula on a trace file instead of learning one
+    Eval(EvalArgs),
+    /// Print the Pareto front of the cost of formulas versus their number of
+    /// misclassified traces, instead of learning a single formula
+    Pareto(ParetoArgs),
+    /// Print all the separating formulas of minimal cost, instead of a single one
+    Minimal(MinimalArgs),
+    /// Learn a formula of minimal cost with a SAT solver, instead of the enumeration
+    Exact(ExactArgs),
+}
+
+impl AlgoCommand {
+    /// Whether the command learns a formula with divide and conquer.
+    fn learns(&self) -> bool {
+        !matches!(
+            self,
+            AlgoCommand::Eval(_)
+                | AlgoCommand::Pareto(_)
+                | AlgoCommand::Minimal(_)
+                | AlgoCommand::Exact(_)
+        )
+    }
+}
+
+#[derive(Args)]
+struct EvalArgs {
+    /// Name of the .trace file to read.
+    input_filename: PathBuf,
+    /// Formula to evaluate, over the alphabet of the trace file.
+    formula: String,
+    /// Print the results as a single JSON object.
+    #[arg(long)]
+    json: bool,
+    /// Exit with status 2 if any trace is misclassified.
+    #[arg(long)]
+    strict: bool,
+}
+
+#[derive(Args)]
+struct ParetoArgs {
+    /// Name of the .trace file to read.
+    input_filename: PathBuf,
+    /// Enumerate LTL formulas up to this cost.
+    max_size_ltl: usize,
+    /// Number of threads generating the candidates of the LTL enumeration.
+    #[arg(long, default_value_t = 1, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    threads: usize,
+}
+
+#[derive(Args)]
+struct MinimalArgs {
+    /// Name of the .trace file to read.
+    input_filename: PathBuf,
+    /// Enumerate LTL formulas up to this cost.
+    max_size_ltl: usize,
+    /// Stop after this many formulas.
+    #[arg(long)]
+    limit: Option<usize>,
+    /// Number of threads generating the candidates of the LTL enumeration.
+    #[arg(long, default_value_t = 1, value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+    threads: usize,
+}
+
+#[derive(Args)]
+struct ExactArgs {
+    /// Name of the .trace file to read.
+    input_filename: PathBuf,
+    #[command(flatten)]
+    params: ExactParams,
 }
@@ -8,29 +8,48 @@ use thiserror::Error;
 
 use crate::{bool::cv::CharVec, ltl::cm::CharMatrix};
 
-use super::traits::Commutativity;
+use super::{interval::Interval, traits::Commutativity};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
-/// Binary LTL Operators: Or, And, Until
+/// Binary LTL Operators: Or, And, Implies, Equiv, Until, WeakUntil, Release, Since, BoundedUntil
 pub enum LtlBinaryOp {
     Or,
     And,
+    Implies,
+    Equiv,
     Until,
+    WeakUntil,
+    Release,
+    /// Past-time dual of [`LtlBinaryOp::Until`].
+    Since,
+    /// Until within the given interval of steps.
+    BoundedUntil(Interval),
 }
 
 impl LtlBinaryOp {
-    /// Returns a list of all binary operators.
+    /// Returns the binary operators of `All Operators`: or, and and until.
     pub(crate) fn all() -> Vec<LtlBinaryOp> {
         use LtlBinaryOp::*;
         vec![Or, And, Until]
     }
 
+    /// Returns a list of all binary operators, except bounded ones.
+    pub(crate) fn extended() -> Vec<LtlBinaryOp> {
+        use LtlBinaryOp::*;
+        vec![Or, And, Implies, Equiv, Until, WeakUntil, Release, Since]
+    }
+
     /// Whether this LTL operator is boolean.
     pub(crate) fn is_boolean(&self) -> bool {
-        match self {
-            LtlBinaryOp::Or | LtlBinaryOp::And => true,
-            _ => false,
-        }
+        matches!(
+            self,
+            LtlBinaryOp::Or | LtlBinaryOp::And | LtlBinaryOp::Implies | LtlBinaryOp::Equiv
+        )
+    }
+
+    /// Whether this operator refers to the past of the trace.
+    pub(crate) fn is_past(&self) -> bool {
+        matches!(self, LtlBinaryOp::Since)
     }
 
     /// Apply the operator to two characteristic vectors.
@@ -38,10 +57,12 @@ impl LtlBinaryOp {
     /// # Panics
     ///
     /// Panics if the operator is not boolean.
-    pub(crate) fn apply_cv(op: Self, lhs: CharVec, rhs: CharVec) -> CharVec {
+    pub(crate) fn apply_cv(op: Self, lhs: &CharVec, rhs: &CharVec) -> CharVec {
         match op {
             LtlBinaryOp::Or => lhs.bitor(rhs),
             LtlBinaryOp::And => lhs.bitand(rhs),
+            LtlBinaryOp::Implies => lhs.implies(rhs),
+            LtlBinaryOp::Equiv => lhs.equiv(rhs),
             _ => panic!("Cannot apply non-boolean operator to characteristic vectors"),
         }
     }
@@ -51,7 +72,13 @@ impl LtlBinaryOp {
         match op {
             LtlBinaryOp::Or => lhs.or(rhs),
             LtlBinaryOp::And => lhs.and(rhs),
+            LtlBinaryOp::Implies => lhs.implies(rhs),
+            LtlBinaryOp::Equiv => lhs.equiv(rhs),
             LtlBinaryOp::Until => lhs.until(rhs),
+            LtlBinaryOp::WeakUntil => lhs.weak_until(rhs),
+            LtlBinaryOp::Release => lhs.release(rhs),
+            LtlBinaryOp::Since => lhs.since(rhs),
+            LtlBinaryOp::BoundedUntil(interval) => lhs.bounded_until(rhs, interval),
         }
     }
 }
@@ -59,14 +86,19 @@ impl LtlBinaryOp {
 impl Commutativity for LtlBinaryOp {
     fn commutes(&self) -> bool {
         match self {
-            LtlBinaryOp::Or | LtlBinaryOp::And => true,
-            LtlBinaryOp::Until => false,
+            LtlBinaryOp::Or | LtlBinaryOp::And | LtlBinaryOp::Equiv => true,
+            LtlBinaryOp::Implies
+            | LtlBinaryOp::Until
+            | LtlBinaryOp::WeakUntil
+            | LtlBinaryOp::Release
+            | LtlBinaryOp::Since
+            | LtlBinaryOp::BoundedUntil(_) => false,
         }
     }
 }
 
 #[derive(Debug, Error, PartialEq)]
-#[error("Invalid binary operator '{}', expected one of '&', '|', 'U'.", .0)]
+#[error("Invalid binary operator '{}', expected one of '&', '|', '->', '<->', 'U', 'W', 'R', 'S', 'U[a,b]'.", .0)]
 pub struct InvalidBinaryOp<'a>(&'a str);
 
 impl<'a> TryFrom<&'a str> for LtlBinaryOp {
@@ -80,14 +112,28 @@ impl<'a> TryFrom<&'a str> for LtlBinaryOp {
     /// |:-------|:---------------------|
     /// | `"\|"` | [`LtlBinaryOp::Or`]   |
     /// | `"&"`  | [`LtlBinaryOp::And`]  |
+    /// | `"->"` | [`LtlBinaryOp::Implies`]|
+    /// | `"<->"`| [`LtlBinaryOp::Equiv`]|
     /// | `"U"`  | [`LtlBinaryOp::Until`]|
+    /// | `"W"`  | [`LtlBinaryOp::WeakUntil`]|
+    /// | `"R"`  | [`LtlBinaryOp::Release`]|
+    /// | `"S"`  | [`LtlBinaryOp::Since`]|
+    /// | `"U[a,b]"`  | [`LtlBinaryOp::BoundedUntil`]|
     /// | Other value  | `Error`  |
     fn try_from(value: &'a str) -> Result<Self, Self::Error> {
         match value {
             "|" => Ok(LtlBinaryOp::Or),
             "&" => Ok(LtlBinaryOp::And),
+            "->" => Ok(LtlBinaryOp::Implies),
+            "<->" => Ok(LtlBinaryOp::Equiv),
             "U" => Ok(LtlBinaryOp::Until),
-            _ => Err(InvalidBinaryOp(value)),
+            "W" => Ok(LtlBinaryOp::WeakUntil),
+            "R" => Ok(LtlBinaryOp::Release),
+            "S" => Ok(LtlBinaryOp::Since),
+            _ => match Interval::split_bounded(value) {
+                Some(("U", interval)) => Ok(LtlBinaryOp::BoundedUntil(interval)),
+                _ => Err(InvalidBinaryOp(value)),
+            },
         }
     }
 }
@@ -97,7 +143,13 @@ impl Display for LtlBinaryOp {
         match self {
             LtlBinaryOp::And => write!(f, "&"),
             LtlBinaryOp::Or => write!(f, "|"),
+            LtlBinaryOp::Implies => write!(f, "->"),
+            LtlBinaryOp::Equiv => write!(f, "<->"),
             LtlBinaryOp::Until => write!(f, "U"),
+            LtlBinaryOp::WeakUntil => write!(f, "W"),
+            LtlBinaryOp::Release => write!(f, "R"),
+            LtlBinaryOp::Since => write!(f, "S"),
+            LtlBinaryOp::BoundedUntil(interval) => write!(f, "U{interval}"),
         }
     }
 }
@@ -117,13 +169,23 @@ mod test {
         let parsed = "U".try_into();
         assert_eq!(parsed, Ok(LtlBinaryOp::Until));
 
+        let parsed = "<->".try_into();
+        assert_eq!(parsed, Ok(LtlBinaryOp::Equiv));
+
+        let parsed = "R".try_into();
+        assert_eq!(parsed, Ok(LtlBinaryOp::Release));
+
+        let parsed = "S".try_into();
+        assert_eq!(parsed, Ok(LtlBinaryOp::Since));
+
         let parsed: Result<LtlBinaryOp, _> = ":".try_into();
         assert!(parsed.is_err());
     }
 
     #[test]
     fn binary_op_display_then_parse_is_ident() {
-        for op in LtlBinaryOp::all() {
+        let bounded = LtlBinaryOp::BoundedUntil(Interval::new(1, 4).unwrap());
+        for op in LtlBinaryOp::extended().into_iter().chain([bounded]) {
             assert_eq!(Ok(op), format!("{op}").as_str().try_into())
         }
     }
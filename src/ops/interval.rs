@@ -0,0 +1,45 @@
+//! Bounds of interval-bounded temporal operators.
+use std::fmt::Display;
+
+/// Closed interval `[lo, hi]` of steps, bounding a temporal operator such as `F[lo,hi]`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Interval {
+    pub(crate) lo: usize,
+    pub(crate) hi: usize,
+}
+
+impl Interval {
+    /// Interval `[lo, hi]`, if it is not empty.
+    pub fn new(lo: usize, hi: usize) -> Option<Self> {
+        (lo <= hi).then_some(Interval { lo, hi })
+    }
+
+    /// Split a bounded operator such as `"F[1,3]"` into its name and its interval.
+    pub(crate) fn split_bounded(s: &str) -> Option<(&str, Self)> {
+        let (name, bounds) = s.strip_suffix(']')?.split_once('[')?;
+        let (lo, hi) = bounds.split_once(',')?;
+        let interval = Interval::new(lo.trim().parse().ok()?, hi.trim().parse().ok()?)?;
+        Some((name, interval))
+    }
+}
+
+impl Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[{},{}]", self.lo, self.hi)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn split_bounded_operators() {
+        let interval = Interval::new(1, 3).unwrap();
+        assert_eq!(Interval::split_bounded("F[1,3]"), Some(("F", interval)));
+        assert_eq!(Interval::split_bounded("U[ 1, 3 ]"), Some(("U", interval)));
+        assert_eq!(Interval::split_bounded("F[3,1]"), None);
+        assert_eq!(Interval::split_bounded("F[1]"), None);
+        assert_eq!(Interval::split_bounded("F"), None);
+    }
+}
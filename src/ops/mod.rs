@@ -1,4 +1,5 @@
 //! LTL Operators types and related traits.
 pub(crate) mod binary;
+pub mod interval;
 pub(crate) mod traits;
 pub(crate) mod unary;
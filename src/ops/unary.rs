@@ -5,40 +5,97 @@ use thiserror::Error;
 
 use crate::ltl::cm::CharMatrix;
 
+use super::interval::Interval;
+
 #[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 pub enum LtlUnaryOp {
-    // Not,
+    Not,
     Next,
+    /// Weak next, which holds at the last position of the trace whatever the semantics.
+    WeakNext,
     Finally,
     Globally,
+    /// Past-time yesterday, which never holds at the first position of the trace.
+    Yesterday,
+    /// Weak yesterday, which holds at the first position of the trace.
+    WeakYesterday,
+    Once,
+    Historically,
+    /// Finally within the given interval of steps.
+    BoundedFinally(Interval),
+    /// Globally within the given interval of steps.
+    BoundedGlobally(Interval),
 }
 
 impl LtlUnaryOp {
+    /// Returns the unary operators of `All Operators`: next, finally and globally.
     pub(crate) fn all() -> Vec<LtlUnaryOp> {
         use LtlUnaryOp::*;
         vec![Next, Finally, Globally]
-        // vec![Not, Next, Finally, Globally]
+    }
+
+    /// Returns a list of all unary operators, except bounded ones.
+    pub(crate) fn extended() -> Vec<LtlUnaryOp> {
+        use LtlUnaryOp::*;
+        vec![
+            Not,
+            Next,
+            WeakNext,
+            Finally,
+            Globally,
+            Yesterday,
+            WeakYesterday,
+            Once,
+            Historically,
+        ]
     }
 
     pub(crate) fn is_boolean(&self) -> bool {
         match self {
-            // LtlUnaryOp::Not => true,
-            LtlUnaryOp::Next | LtlUnaryOp::Finally | LtlUnaryOp::Globally => false,
+            LtlUnaryOp::Not => true,
+            LtlUnaryOp::Next
+            | LtlUnaryOp::WeakNext
+            | LtlUnaryOp::Finally
+            | LtlUnaryOp::Globally
+            | LtlUnaryOp::Yesterday
+            | LtlUnaryOp::WeakYesterday
+            | LtlUnaryOp::Once
+            | LtlUnaryOp::Historically
+            | LtlUnaryOp::BoundedFinally(_)
+            | LtlUnaryOp::BoundedGlobally(_) => false,
         }
     }
 
+    /// Whether this operator refers to the past of the trace.
+    pub(crate) fn is_past(&self) -> bool {
+        matches!(
+            self,
+            LtlUnaryOp::Yesterday
+                | LtlUnaryOp::WeakYesterday
+                | LtlUnaryOp::Once
+                | LtlUnaryOp::Historically
+        )
+    }
+
     pub(crate) fn apply_cm(op: Self, cm: &CharMatrix) -> CharMatrix {
         match op {
-            // LtlUnaryOp::Not => cm.not(),
+            LtlUnaryOp::Not => cm.not(),
             LtlUnaryOp::Next => cm.next(),
+            LtlUnaryOp::WeakNext => cm.weak_next(),
             LtlUnaryOp::Finally => cm.finally(),
             LtlUnaryOp::Globally => cm.globally(),
+            LtlUnaryOp::Yesterday => cm.yesterday(),
+            LtlUnaryOp::WeakYesterday => cm.weak_yesterday(),
+            LtlUnaryOp::Once => cm.once(),
+            LtlUnaryOp::Historically => cm.historically(),
+            LtlUnaryOp::BoundedFinally(interval) => cm.bounded_finally(interval),
+            LtlUnaryOp::BoundedGlobally(interval) => cm.bounded_globally(interval),
         }
     }
 }
 
 #[derive(Debug, Error, PartialEq)]
-#[error("Invalid unary operator '{}', expected one of 'X', 'F', 'G'.", .0)]
+#[error("Invalid unary operator '{}', expected one of '!', 'X', 'N', 'F', 'G', 'Y', 'Z', 'O', 'H', 'F[a,b]', 'G[a,b]'.", .0)]
 pub struct InvalidUnaryOp<'a>(&'a str);
 
 impl<'a> TryFrom<&'a str> for LtlUnaryOp {
@@ -50,16 +107,34 @@ impl<'a> TryFrom<&'a str> for LtlUnaryOp {
     ///
     /// | String |   Result  |
     /// |:-------|:---------------------|
+    /// | `"!"`  | [`LtlUnaryOp::Not`]  |
     /// | `"X"`  | [`LtlUnaryOp::Next`]  |
+    /// | `"N"`  | [`LtlUnaryOp::WeakNext`]  |
     /// | `"F"`  | [`LtlUnaryOp::Finally`]   |
     /// | `"G"`  | [`LtlUnaryOp::Globally`]|
+    /// | `"Y"`  | [`LtlUnaryOp::Yesterday`]|
+    /// | `"Z"`  | [`LtlUnaryOp::WeakYesterday`]|
+    /// | `"O"`  | [`LtlUnaryOp::Once`]|
+    /// | `"H"`  | [`LtlUnaryOp::Historically`]|
+    /// | `"F[a,b]"`  | [`LtlUnaryOp::BoundedFinally`]|
+    /// | `"G[a,b]"`  | [`LtlUnaryOp::BoundedGlobally`]|
     /// | Other value  | `Error` |
     fn try_from(value: &'a str) -> Result<Self, Self::Error> {
         match value {
+            "!" => Ok(LtlUnaryOp::Not),
             "X" => Ok(LtlUnaryOp::Next),
+            "N" => Ok(LtlUnaryOp::WeakNext),
             "F" => Ok(LtlUnaryOp::Finally),
             "G" => Ok(LtlUnaryOp::Globally),
-            _ => Err(InvalidUnaryOp(value)),
+            "Y" => Ok(LtlUnaryOp::Yesterday),
+            "Z" => Ok(LtlUnaryOp::WeakYesterday),
+            "O" => Ok(LtlUnaryOp::Once),
+            "H" => Ok(LtlUnaryOp::Historically),
+            _ => match Interval::split_bounded(value) {
+                Some(("F", interval)) => Ok(LtlUnaryOp::BoundedFinally(interval)),
+                Some(("G", interval)) => Ok(LtlUnaryOp::BoundedGlobally(interval)),
+                _ => Err(InvalidUnaryOp(value)),
+            },
         }
     }
 }
@@ -67,9 +142,17 @@ impl<'a> TryFrom<&'a str> for LtlUnaryOp {
 impl Display for LtlUnaryOp {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
+            LtlUnaryOp::Not => write!(f, "!"),
             LtlUnaryOp::Next => write!(f, "X"),
+            LtlUnaryOp::WeakNext => write!(f, "N"),
             LtlUnaryOp::Finally => write!(f, "F"),
             LtlUnaryOp::Globally => write!(f, "G"),
+            LtlUnaryOp::Yesterday => write!(f, "Y"),
+            LtlUnaryOp::WeakYesterday => write!(f, "Z"),
+            LtlUnaryOp::Once => write!(f, "O"),
+            LtlUnaryOp::Historically => write!(f, "H"),
+            LtlUnaryOp::BoundedFinally(interval) => write!(f, "F{interval}"),
+            LtlUnaryOp::BoundedGlobally(interval) => write!(f, "G{interval}"),
         }
     }
 }
@@ -80,22 +163,42 @@ mod test {
 
     #[test]
     fn string_try_into_binary_op() {
+        let parsed = "!".try_into();
+        assert_eq!(parsed, Ok(LtlUnaryOp::Not));
+
         let parsed = "X".try_into();
         assert_eq!(parsed, Ok(LtlUnaryOp::Next));
 
+        let parsed = "N".try_into();
+        assert_eq!(parsed, Ok(LtlUnaryOp::WeakNext));
+
         let parsed = "F".try_into();
         assert_eq!(parsed, Ok(LtlUnaryOp::Finally));
 
         let parsed = "G".try_into();
         assert_eq!(parsed, Ok(LtlUnaryOp::Globally));
 
+        let parsed = "H".try_into();
+        assert_eq!(parsed, Ok(LtlUnaryOp::Historically));
+
+        let parsed = "F[1,3]".try_into();
+        assert_eq!(
+            parsed,
+            Ok(LtlUnaryOp::BoundedFinally(Interval::new(1, 3).unwrap()))
+        );
+
         let parsed: Result<LtlUnaryOp, _> = ":".try_into();
         assert!(parsed.is_err());
     }
 
     #[test]
     fn binary_op_display_then_parse_is_ident() {
-        for op in LtlUnaryOp::all() {
+        let interval = Interval::new(0, 2).unwrap();
+        let bounded = [
+            LtlUnaryOp::BoundedFinally(interval),
+            LtlUnaryOp::BoundedGlobally(interval),
+        ];
+        for op in LtlUnaryOp::extended().into_iter().chain(bounded) {
             assert_eq!(Ok(op), format!("{op}").as_str().try_into())
         }
     }
@@ -1,8 +1,14 @@
 use std::{fmt::Debug, hash::Hash};
 
+/// Acceptance test of a formula against the target classification of the inputs.
 pub(crate) trait EqTarget {
     type TargetType: ?Sized;
-    fn eq_target(&self, target: &Self::TargetType) -> bool;
+    /// Number of inputs on which `self` differs from `target`.
+    fn errors(&self, target: &Self::TargetType) -> usize;
+    /// Whether `self` differs from `target` on at most `max_errors` inputs.
+    fn eq_target(&self, target: &Self::TargetType, max_errors: usize) -> bool {
+        self.errors(target) <= max_errors
+    }
 }
 
 /// A type that can be [`Hash`]ed and return its hash.
@@ -10,6 +16,18 @@ pub(crate) trait EqTarget {
 /// This is different from [`Hash`], as [`Hashed`] gives an opportunity
 /// to store the hash and avoid recomputing it at every call.
 pub trait Hashed {
-    type HashType: Eq + Hash + Clone + Copy + Debug;
+    type HashType: Eq + Hash + Clone + Copy + Debug + Send + Sync;
     fn hashed(&self) -> Self::HashType;
 }
+
+/// A [`Hashed`] type whose stored hash can be replaced.
+///
+/// Caches use it to give distinct hashes to different values whose hashes collide.
+pub(crate) trait Rehash: Hashed {
+    /// Replace the stored hash.
+    fn set_hash(&mut self, hash: Self::HashType);
+    /// Hash to try after `hash` when it is taken by a different value.
+    fn next_hash(hash: Self::HashType) -> Self::HashType;
+    /// Whether `self` and `other` are equal, whatever their stored hashes.
+    fn same_value(&self, other: &Self) -> bool;
+}